@@ -0,0 +1,142 @@
+// Only the memory reader of the delta modulation channel is emulated. It fetches the sample bytes
+// via DMA, which steals cycles from the CPU, and asserts an IRQ at the end of a sample. The output
+// unit just consumes the bytes with the correct rate, since there is no sound output yet.
+
+use crate::region::Region;
+
+const IRQ_ENABLE_IDX: u8 = 7;
+const LOOP_IDX: u8 = 6;
+const ENABLE_IDX: u8 = 4;
+const DMC_IRQ_IDX: u8 = 7;
+
+// timer periods in CPU cycles
+const NTSC_RATES: [u16; 16] =
+	[428, 380, 340, 320, 286, 254, 226, 214, 190, 160, 142, 128, 106, 84, 72, 54];
+const PAL_RATES: [u16; 16] =
+	[398, 354, 316, 298, 276, 236, 210, 198, 176, 148, 132, 118, 98, 78, 66, 50];
+
+pub struct Dmc {
+	rates: &'static [u16; 16],
+	irq_enabled: bool,
+	loop_sample: bool,
+	irq_flag: bool,
+
+	period: u16,
+	timer: u16,
+
+	sample_addr: u16,
+	sample_len: u16,
+	cur_addr: u16,
+	bytes_remaining: u16,
+
+	sample_buffer: Option<u8>,
+	bits_remaining: u8,
+}
+
+impl Dmc {
+	pub fn new(region: Region) -> Self {
+		let rates = if region.pal_apu() {
+			&PAL_RATES
+		} else {
+			&NTSC_RATES
+		};
+
+		Self {
+			rates,
+			irq_enabled: false,
+			loop_sample: false,
+			irq_flag: false,
+			period: rates[0],
+			timer: rates[0],
+			sample_addr: 0xC000,
+			sample_len: 1,
+			cur_addr: 0xC000,
+			bytes_remaining: 0,
+			sample_buffer: None,
+			bits_remaining: 8,
+		}
+	}
+
+	// called once every CPU cycle
+	pub fn clock(&mut self) {
+		self.timer -= 1;
+		if self.timer > 0 {
+			return;
+		}
+		self.timer = self.period;
+
+		// the output unit shifts out one bit per period and empties the buffer every 8 bits
+		self.bits_remaining -= 1;
+		if self.bits_remaining == 0 {
+			self.bits_remaining = 8;
+			self.sample_buffer = None;
+		}
+	}
+
+	pub fn irq(&self) -> bool {
+		self.irq_flag
+	}
+
+	// the memory reader wants the next byte of the sample
+	pub fn dma_request(&self) -> bool {
+		self.sample_buffer.is_none() && self.bytes_remaining > 0
+	}
+
+	pub fn dma_addr(&self) -> u16 {
+		self.cur_addr
+	}
+
+	pub fn dma_complete(&mut self, val: u8) {
+		self.sample_buffer = Some(val);
+
+		// the address wraps around to $8000
+		self.cur_addr = self.cur_addr.wrapping_add(1) | 0x8000;
+		self.bytes_remaining -= 1;
+		if self.bytes_remaining == 0 {
+			if self.loop_sample {
+				self.restart();
+			} else if self.irq_enabled {
+				self.irq_flag = true;
+			}
+		}
+	}
+
+	// $4015 read, bits 4 and 7
+	pub fn status_read(&self) -> u8 {
+		(((self.bytes_remaining > 0) as u8) << ENABLE_IDX) | ((self.irq_flag as u8) << DMC_IRQ_IDX)
+	}
+
+	// $4015 write, acknowledges the IRQ and starts or stops the sample
+	pub fn enable_write(&mut self, val: u8) {
+		self.irq_flag = false;
+
+		if (val >> ENABLE_IDX) & 0x01 == 0 {
+			self.bytes_remaining = 0;
+		} else if self.bytes_remaining == 0 {
+			self.restart();
+		}
+	}
+
+	// $4010-$4013
+	pub fn write(&mut self, reg: usize, val: u8) {
+		match reg {
+			0 => {
+				self.irq_enabled = (val >> IRQ_ENABLE_IDX) & 0x01 > 0;
+				if !self.irq_enabled {
+					self.irq_flag = false;
+				}
+				self.loop_sample = (val >> LOOP_IDX) & 0x01 > 0;
+				self.period = self.rates[(val & 0x0F) as usize];
+			}
+			1 => {} // output level, there is no sound output yet
+			2 => self.sample_addr = 0xC000 | ((val as u16) << 6),
+			3 => self.sample_len = ((val as u16) << 4) | 0x01,
+			_ => panic!("Dmc::write(): invalid register: {}", reg),
+		}
+	}
+
+	fn restart(&mut self) {
+		self.cur_addr = self.sample_addr;
+		self.bytes_remaining = self.sample_len;
+	}
+}
@@ -0,0 +1,105 @@
+// Only the frame counter of the APU is emulated for now, since its IRQ is needed by a lot of games
+// and test ROMs. The sequencer steps aren't used because there are no channels yet.
+
+use crate::region::Region;
+
+const MODE_IDX: u8 = 7;
+const IRQ_INHIBIT_IDX: u8 = 6;
+const FRAME_IRQ_IDX: u8 = 6;
+
+// the IRQ flag is set during the last cycles of the 4-step sequence
+// (first IRQ cycle, length of the 4-step and of the 5-step sequence)
+const NTSC_CYCLES: (u32, u32, u32) = (29829, 29830, 37282);
+const PAL_CYCLES: (u32, u32, u32) = (33253, 33254, 41566);
+
+pub struct FrameCounter {
+	irq_first_cycle: u32,
+	four_step_cycles: u32,
+	five_step_cycles: u32,
+
+	five_step: bool,
+	irq_inhibit: bool,
+	irq_flag: bool,
+
+	frame_cycle: u32,
+	cpu_cycle: u64,
+	new_mode: u8,
+	write_delay: u8, // cycles until a $4017 write resets the sequencer
+}
+
+impl FrameCounter {
+	pub fn new(region: Region) -> Self {
+		let (irq_first_cycle, four_step_cycles, five_step_cycles) = if region.pal_apu() {
+			PAL_CYCLES
+		} else {
+			NTSC_CYCLES
+		};
+
+		Self {
+			irq_first_cycle,
+			four_step_cycles,
+			five_step_cycles,
+			five_step: false,
+			irq_inhibit: false,
+			irq_flag: false,
+			frame_cycle: 0,
+			cpu_cycle: 0,
+			new_mode: 0,
+			write_delay: 0,
+		}
+	}
+
+	// called once every CPU cycle
+	pub fn clock(&mut self) {
+		self.cpu_cycle += 1;
+
+		if self.write_delay > 0 {
+			self.write_delay -= 1;
+			if self.write_delay == 0 {
+				self.five_step = (self.new_mode >> MODE_IDX) & 0x01 > 0;
+				self.frame_cycle = 0;
+			}
+		}
+
+		self.frame_cycle += 1;
+		if self.five_step {
+			if self.frame_cycle == self.five_step_cycles {
+				self.frame_cycle = 0;
+			}
+		} else {
+			if self.frame_cycle >= self.irq_first_cycle && !self.irq_inhibit {
+				self.irq_flag = true;
+			}
+
+			if self.frame_cycle == self.four_step_cycles {
+				self.frame_cycle = 0;
+			}
+		}
+	}
+
+	pub fn irq(&self) -> bool {
+		self.irq_flag
+	}
+
+	// $4015 read, only the frame IRQ bit is supported, reading acknowledges the IRQ
+	pub fn status_read(&mut self) -> u8 {
+		let ret = (self.irq_flag as u8) << FRAME_IRQ_IDX;
+		self.irq_flag = false;
+		ret
+	}
+
+	// $4017 write, the sequencer is reset 3 or 4 cycles after the write depending on the alignment
+	pub fn write(&mut self, val: u8) {
+		self.irq_inhibit = (val >> IRQ_INHIBIT_IDX) & 0x01 > 0;
+		if self.irq_inhibit {
+			self.irq_flag = false;
+		}
+
+		self.new_mode = val;
+		self.write_delay = if (self.cpu_cycle & 0x01) > 0 {
+			3
+		} else {
+			4
+		};
+	}
+}
@@ -0,0 +1,150 @@
+// Loading of ROMs which are stored inside of archives. The format of the archive is detected by
+// its magic bytes, so the file ending of the archive doesn't matter.
+
+use std::fmt;
+use std::io::{Cursor, Read};
+
+use flate2::read::GzDecoder;
+use sevenz_rust::{Password, SevenZReader};
+use zip::ZipArchive;
+
+// if no entry is selected explicitly, the first entry with one of these endings is used
+pub const ROM_FILE_ENDINGS: [&str; 4] = [".nes", ".fds", ".nsf", ".unf"];
+
+const ZIP_MAGIC: &[u8] = &[0x50, 0x4B, 0x03, 0x04];
+const SEVEN_ZIP_MAGIC: &[u8] = &[0x37, 0x7A, 0xBC, 0xAF, 0x27, 0x1C];
+const GZIP_MAGIC: &[u8] = &[0x1F, 0x8B];
+
+pub enum ArchiveErr {
+	NoRomFound,
+	EntryNotFound(String),
+	Corrupted(String),
+}
+
+enum Format {
+	Zip,
+	SevenZip,
+	Gzip,
+}
+
+impl fmt::Debug for ArchiveErr {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::NoRomFound => write!(f, "NoRomFound"),
+			Self::EntryNotFound(name) => write!(f, "EntryNotFound: {}", name),
+			Self::Corrupted(e) => write!(f, "Corrupted: {}", e),
+		}
+	}
+}
+
+fn detect_format(data: &[u8]) -> Option<Format> {
+	if data.starts_with(ZIP_MAGIC) {
+		Some(Format::Zip)
+	} else if data.starts_with(SEVEN_ZIP_MAGIC) {
+		Some(Format::SevenZip)
+	} else if data.starts_with(GZIP_MAGIC) {
+		Some(Format::Gzip)
+	} else {
+		None
+	}
+}
+
+fn is_rom_name(name: &str) -> bool {
+	let name = name.to_lowercase();
+	ROM_FILE_ENDINGS.iter().any(|ending| name.ends_with(ending))
+}
+
+// Select the entry to load. The desired entry can be passed with its full path inside the archive
+// or only with its file name.
+fn select_entry(names: &[String], entry: Option<&str>) -> Result<usize, ArchiveErr> {
+	match entry {
+		Some(e) => names
+			.iter()
+			.position(|n| n == e || n.rsplit('/').next() == Some(e))
+			.ok_or_else(|| ArchiveErr::EntryNotFound(String::from(e))),
+		None => names.iter().position(|n| is_rom_name(n)).ok_or(ArchiveErr::NoRomFound),
+	}
+}
+
+fn extract_zip(data: &[u8], entry: Option<&str>) -> Result<Vec<u8>, ArchiveErr> {
+	let mut zip =
+		ZipArchive::new(Cursor::new(data)).map_err(|e| ArchiveErr::Corrupted(e.to_string()))?;
+
+	let names: Vec<String> = zip.file_names().map(String::from).collect();
+	let name = &names[select_entry(&names, entry)?];
+
+	let mut f = zip.by_name(name).map_err(|e| ArchiveErr::Corrupted(e.to_string()))?;
+	// the size in the header isn't trusted for the allocation
+	let mut rom = Vec::new();
+	f.read_to_end(&mut rom).map_err(|e| ArchiveErr::Corrupted(e.to_string()))?;
+
+	Ok(rom)
+}
+
+fn extract_7z(data: &[u8], entry: Option<&str>) -> Result<Vec<u8>, ArchiveErr> {
+	let mut sz = SevenZReader::new(Cursor::new(data), data.len() as u64, Password::empty())
+		.map_err(|e| ArchiveErr::Corrupted(e.to_string()))?;
+
+	let names: Vec<String> = sz
+		.archive()
+		.files
+		.iter()
+		.map(|f| {
+			if f.is_directory() {
+				String::new()
+			} else {
+				String::from(f.name())
+			}
+		})
+		.collect();
+	let name = names[select_entry(&names, entry)?].clone();
+
+	let mut rom = Vec::new();
+	sz.for_each_entries(|e, reader| {
+		if e.name() == name {
+			reader.read_to_end(&mut rom)?;
+			Ok(false) // we are done, stop iterating over the entries
+		} else {
+			Ok(true)
+		}
+	})
+	.map_err(|e| ArchiveErr::Corrupted(e.to_string()))?;
+
+	Ok(rom)
+}
+
+fn extract_gzip(data: &[u8]) -> Result<Vec<u8>, ArchiveErr> {
+	// a gzip file always contains exactly one file, so there is nothing to select
+	let mut rom = Vec::new();
+	GzDecoder::new(data).read_to_end(&mut rom).map_err(|e| ArchiveErr::Corrupted(e.to_string()))?;
+
+	Ok(rom)
+}
+
+pub fn is_archive(data: &[u8]) -> bool {
+	detect_format(data).is_some()
+}
+
+// extract the ROM out of the archive, if no entry is passed the first ROM in the archive is used
+pub fn extract(data: &[u8], entry: Option<&str>) -> Result<Vec<u8>, ArchiveErr> {
+	match detect_format(data) {
+		Some(Format::Zip) => extract_zip(data, entry),
+		Some(Format::SevenZip) => extract_7z(data, entry),
+		Some(Format::Gzip) => extract_gzip(data),
+		None => Err(ArchiveErr::Corrupted(String::from("unknown archive format"))),
+	}
+}
+
+#[test]
+fn test_select_entry() {
+	let names: Vec<String> = ["readme.txt", "roms/Game (U).NES", "roms/Game (E).nes"]
+		.iter()
+		.map(|n| String::from(*n))
+		.collect();
+
+	assert_eq!(select_entry(&names, None).ok(), Some(1));
+	assert_eq!(select_entry(&names, Some("Game (E).nes")).ok(), Some(2));
+	assert_eq!(select_entry(&names, Some("roms/Game (E).nes")).ok(), Some(2));
+	assert!(select_entry(&names, Some("missing.nes")).is_err());
+	assert!(select_entry(&names[..1], None).is_err());
+}
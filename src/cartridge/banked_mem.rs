@@ -49,6 +49,11 @@ impl BankedMemory {
 		&self.data
 	}
 
+	// the index in the data of the whole memory, e.g. the offset in the PRG ROM
+	pub fn offset(&self, bank_idx: usize, addr: usize) -> usize {
+		self.idx_in_data(bank_idx, addr)
+	}
+
 	pub fn reload(&mut self, data: &[u8]) {
 		self.data.copy_from_slice(data);
 	}
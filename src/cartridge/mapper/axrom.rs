@@ -0,0 +1,116 @@
+use super::banked_mem::*;
+use super::mem::{BankedSegment, PpuSegment, Segment};
+use super::{has_bus_conflicts, Cartridge, CartridgeInfo, LoadRom};
+
+const PRG_BANK_SIZE: usize = 32768;
+
+pub(crate) struct AxRom {
+	prg_rom: BankedMemory,
+	chr_ram: BankedMemory,
+	ci_ram: BankedMemory,
+	prg_bank: usize,
+	nt_idx: usize, // one-screen mirroring, selects the used nametable
+	bus_conflicts: bool,
+}
+
+impl Segment for AxRom {
+	fn read(&self, addr: usize) -> u8 {
+		match addr {
+			0x4020..=0x7FFF => 0x00, // no expansion ROM or PRG_RAM
+			0x8000..=0xFFFF => self.prg_rom.read(self.prg_bank, addr),
+			_ => panic!("AxRom segment read(): address out of memory range: 0x{:x}", addr),
+		}
+	}
+
+	fn write(&mut self, addr: usize, val: u8) {
+		const PRG_BANK_MASK: u8 = 0x07;
+		const NT_IDX: u8 = 4;
+
+		match addr {
+			0x4020..=0x7FFF => {}
+			0x8000..=0xFFFF => {
+				// ANROM and AOROM have no bus conflicts, only AMROM has
+				let val = if self.bus_conflicts {
+					val & Segment::read(self, addr)
+				} else {
+					val
+				};
+
+				self.prg_bank = ((val & PRG_BANK_MASK) as usize) % self.prg_rom.bank_cnt();
+				self.nt_idx = ((val >> NT_IDX) & 0x01) as usize;
+			}
+			_ => panic!("AxRom segment write(): address out of memory range: 0x{:x}", addr),
+		}
+	}
+}
+
+impl PpuSegment for AxRom {
+	fn read(&mut self, addr: usize) -> u8 {
+		match addr {
+			0..=0x1FFF => self.chr_ram.read(0, addr),
+			0x2000..=0x3EFF => self.ci_ram.read(self.nt_idx, addr),
+			_ => panic!("AxRom PPU segment read(): address out of memory range: 0x{:x}", addr),
+		}
+	}
+
+	fn write(&mut self, addr: usize, val: u8) {
+		match addr {
+			0..=0x1FFF => self.chr_ram.write(0, addr, val),
+			0x2000..=0x3EFF => self.ci_ram.write(self.nt_idx, addr, val),
+			_ => panic!("AxRom PPU segment write(): address out of memory range: 0x{:x}", addr),
+		}
+	}
+
+	fn irq(&mut self) -> bool {
+		false
+	}
+}
+
+impl LoadRom for AxRom {
+	fn load(data: &[u8], info: &CartridgeInfo) -> Box<dyn Cartridge> {
+		println!("Load AxROM ROM");
+
+		let prg_rom_bytes = info.prg_rom_cnt * PRG_ROM_BANK_SIZE;
+		assert_eq!(data.len(), prg_rom_bytes);
+
+		Box::new(Self {
+			prg_rom: BankedMemory::load(data, PRG_BANK_SIZE, prg_rom_bytes / PRG_BANK_SIZE),
+			// always only 1 CHR_RAM bank since no CHR_ROM is available
+			chr_ram: BankedMemory::empty(CHR_RAM_BANK_SIZE, 1),
+			ci_ram: BankedMemory::empty(CI_RAM_BANK_SIZE, CI_RAM_BANK_CNT),
+			prg_bank: 0,
+			nt_idx: 0,
+			bus_conflicts: has_bus_conflicts(info, false),
+		})
+	}
+}
+
+impl Cartridge for AxRom {
+	fn support_savestates(&self) -> bool {
+		false
+	}
+
+	// no PRG RAM
+	fn cpu_read_mapped(&self, addr: usize) -> bool {
+		addr >= 0x8000
+	}
+
+	fn prg_rom_offset(&self, addr: usize) -> Option<usize> {
+		match addr {
+			0x8000..=0xFFFF => Some(self.prg_rom.offset(self.prg_bank, addr)),
+			_ => None,
+		}
+	}
+
+	fn get_battery_ram<'a>(&'a self) -> &'a [u8] {
+		panic!("AxROM: savestates are not supported");
+	}
+
+	fn set_battery_ram(&mut self, _ram: &[u8]) {
+		panic!("AxROM: savestates are not supported");
+	}
+
+	fn load_trainer(&mut self, _trainer: &[u8]) {
+		println!("AxROM: no PRG RAM available, the trainer is ignored");
+	}
+}
@@ -0,0 +1,243 @@
+use super::banked_mem::*;
+use super::mem::{BankedSegment, PpuSegment, Segment};
+use super::{has_bus_conflicts, Cartridge, CartridgeInfo, LoadRom, PpuMirror};
+
+// Mapper 34 covers two different boards: BNROM (CHR_RAM, bank register at $8000-$FFFF with bus
+// conflicts) and NINA-001 (CHR_ROM, bank registers at $7FFD-$7FFF without bus conflicts).
+
+const PRG_BANK_SIZE: usize = 32768;
+const NINA_CHR_BANK_SIZE: usize = 4096;
+
+pub(crate) struct BNRom {
+	prg_ram: BankedMemory,
+	prg_rom: BankedMemory,
+	chr_mem: BankedMemory,
+	ci_ram: BankedMemory,
+	nt1_idx: usize,
+	nt2_idx: usize,
+	prg_bank: usize,
+	chr_banks: [usize; 2],
+	use_chr_ram: bool,
+	nina: bool,
+	bus_conflicts: bool,
+}
+
+impl Segment for BNRom {
+	fn read(&self, addr: usize) -> u8 {
+		match addr {
+			0x4020..=0x5FFF => 0x00, // no expansion ROM supported
+			0x6000..=0x7FFF => {
+				if self.nina {
+					self.prg_ram.read(0, addr)
+				} else {
+					0x00
+				}
+			}
+			0x8000..=0xFFFF => self.prg_rom.read(self.prg_bank, addr),
+			_ => panic!("BNRom segment read(): address out of memory range: 0x{:x}", addr),
+		}
+	}
+
+	fn write(&mut self, addr: usize, val: u8) {
+		match addr {
+			0x4020..=0x5FFF => {}
+			0x6000..=0x7FFF => {
+				if self.nina {
+					// the registers don't disable the RAM, the value is written to both
+					self.prg_ram.write(0, addr, val);
+
+					let chr_cnt = self.chr_mem.bank_cnt();
+					match addr {
+						0x7FFD => self.prg_bank = ((val & 0x01) as usize) % self.prg_rom.bank_cnt(),
+						0x7FFE => self.chr_banks[0] = ((val & 0x0F) as usize) % chr_cnt,
+						0x7FFF => self.chr_banks[1] = ((val & 0x0F) as usize) % chr_cnt,
+						_ => {}
+					}
+				}
+			}
+			0x8000..=0xFFFF => {
+				if !self.nina {
+					let val = if self.bus_conflicts {
+						val & Segment::read(self, addr)
+					} else {
+						val
+					};
+
+					self.prg_bank = (val as usize) % self.prg_rom.bank_cnt();
+				}
+			}
+			_ => panic!("BNRom segment write(): address out of memory range: 0x{:x}", addr),
+		}
+	}
+}
+
+impl PpuSegment for BNRom {
+	fn read(&mut self, addr: usize) -> u8 {
+		let bank_addr = addr % self.ci_ram.bank_size();
+
+		match addr {
+			0..=0x0FFF => self.chr_mem.read(self.chr_banks[0], addr),
+			0x1000..=0x1FFF => self.chr_mem.read(self.chr_banks[1], addr),
+			0x2000..=0x23FF | 0x3000..=0x33FF => self.ci_ram.read(0, bank_addr),
+			0x2400..=0x27FF | 0x3400..=0x37FF => self.ci_ram.read(self.nt1_idx, bank_addr),
+			0x2800..=0x2BFF | 0x3800..=0x3BFF => self.ci_ram.read(self.nt2_idx, bank_addr),
+			0x2C00..=0x2FFF | 0x3C00..=0x3EFF => self.ci_ram.read(1, bank_addr),
+			_ => panic!("BNRom PPU segment read(): address out of memory range: 0x{:x}", addr),
+		}
+	}
+
+	fn write(&mut self, addr: usize, val: u8) {
+		let bank_addr = addr % self.ci_ram.bank_size();
+
+		match addr {
+			0..=0x1FFF => {
+				if self.use_chr_ram {
+					self.chr_mem.write(0, addr, val);
+				}
+			}
+			0x2000..=0x23FF | 0x3000..=0x33FF => self.ci_ram.write(0, bank_addr, val),
+			0x2400..=0x27FF | 0x3400..=0x37FF => self.ci_ram.write(self.nt1_idx, bank_addr, val),
+			0x2800..=0x2BFF | 0x3800..=0x3BFF => self.ci_ram.write(self.nt2_idx, bank_addr, val),
+			0x2C00..=0x2FFF | 0x3C00..=0x3EFF => self.ci_ram.write(1, bank_addr, val),
+			_ => panic!("BNRom PPU segment write(): address out of memory range: 0x{:x}", addr),
+		}
+	}
+
+	fn irq(&mut self) -> bool {
+		false
+	}
+}
+
+impl LoadRom for BNRom {
+	fn load(data: &[u8], info: &CartridgeInfo) -> Box<dyn Cartridge> {
+		// submapper 1 is NINA-001 and 2 is BNROM, otherwise only NINA-001 has more than 8KB CHR
+		let nina = match info.submapper_id {
+			1 => true,
+			2 => false,
+			_ => info.chr_rom_cnt > 1,
+		};
+
+		if nina {
+			println!("Load NINA-001 ROM");
+		} else {
+			println!("Load BNROM ROM");
+		}
+
+		let (nt1, nt2) = if let PpuMirror::Horizontal = info.ppu_mirror {
+			(0, 1)
+		} else {
+			(1, 0)
+		};
+
+		let prg_rom_bytes = info.prg_rom_cnt * PRG_ROM_BANK_SIZE;
+		assert_eq!(data.len(), prg_rom_bytes + info.chr_rom_cnt * CHR_ROM_BANK_SIZE);
+
+		let use_chr_ram = info.chr_rom_cnt == 0;
+		let chr = &data[prg_rom_bytes..];
+		let (chr_mem, chr_banks) = if use_chr_ram {
+			(BankedMemory::empty(CHR_RAM_BANK_SIZE, 1), [0, 0])
+		} else if nina {
+			(BankedMemory::load(chr, NINA_CHR_BANK_SIZE, chr.len() / NINA_CHR_BANK_SIZE), [0, 1])
+		} else {
+			(BankedMemory::load(chr, CHR_ROM_BANK_SIZE, 1), [0, 0])
+		};
+
+		Box::new(Self {
+			prg_ram: BankedMemory::empty(PRG_RAM_BANK_SIZE, 1),
+			prg_rom: BankedMemory::load(
+				&data[..prg_rom_bytes],
+				PRG_BANK_SIZE,
+				prg_rom_bytes / PRG_BANK_SIZE,
+			),
+			chr_mem,
+			ci_ram: BankedMemory::empty(CI_RAM_BANK_SIZE, CI_RAM_BANK_CNT),
+			nt1_idx: nt1,
+			nt2_idx: nt2,
+			prg_bank: 0,
+			chr_banks,
+			use_chr_ram,
+			nina,
+			bus_conflicts: !nina && has_bus_conflicts(info, true),
+		})
+	}
+}
+
+impl Cartridge for BNRom {
+	fn support_savestates(&self) -> bool {
+		false
+	}
+
+	// only the NINA-001 has PRG RAM
+	fn cpu_read_mapped(&self, addr: usize) -> bool {
+		addr >= 0x8000 || (self.nina && addr >= 0x6000)
+	}
+
+	fn prg_rom_offset(&self, addr: usize) -> Option<usize> {
+		match addr {
+			0x8000..=0xFFFF => Some(self.prg_rom.offset(self.prg_bank, addr)),
+			_ => None,
+		}
+	}
+
+	fn chr_rom_offset(&self, addr: usize) -> Option<usize> {
+		match addr {
+			_ if self.use_chr_ram => None,
+			0..=0x0FFF => Some(self.chr_mem.offset(self.chr_banks[0], addr)),
+			0x1000..=0x1FFF => Some(self.chr_mem.offset(self.chr_banks[1], addr)),
+			_ => None,
+		}
+	}
+
+	fn get_battery_ram<'a>(&'a self) -> &'a [u8] {
+		panic!("BNROM: savestates are not supported");
+	}
+
+	fn set_battery_ram(&mut self, _ram: &[u8]) {
+		panic!("BNROM: savestates are not supported");
+	}
+
+	fn load_trainer(&mut self, trainer: &[u8]) {
+		if self.nina {
+			for (i, b) in trainer.iter().enumerate() {
+				self.prg_ram.write(0, 0x7000 + i, *b);
+			}
+		} else {
+			println!("BNROM: no PRG RAM available, the trainer is ignored");
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn bus_conflicts() {
+		// the byte at $8000 in bank 0 is 0x01, so BNROM selects bank 1 on a write of 0x03
+		let info = CartridgeInfo {
+			prg_rom_cnt: 8,
+			chr_rom_cnt: 1,
+			..Default::default()
+		};
+		let mut data = vec![0xFF; 4 * PRG_BANK_SIZE + CHR_ROM_BANK_SIZE];
+		data[0] = 0x01;
+
+		let mut cartr = BNRom::load(&data, &info);
+		Segment::write(cartr.as_mut(), 0x8000, 0x03);
+		assert_eq!(cartr.prg_rom_offset(0x8000), Some(PRG_BANK_SIZE));
+
+		// NINA-001 has its registers next to the PRG RAM, so the flag of the header is ignored
+		let info = CartridgeInfo {
+			submapper_id: 1,
+			prg_rom_cnt: 4,
+			chr_rom_cnt: 2,
+			bus_conflicts: true,
+			..Default::default()
+		};
+		let data = vec![0x00; 2 * PRG_BANK_SIZE + 2 * CHR_ROM_BANK_SIZE];
+
+		let mut cartr = BNRom::load(&data, &info);
+		Segment::write(cartr.as_mut(), 0x7FFD, 0x01);
+		assert_eq!(cartr.prg_rom_offset(0x8000), Some(PRG_BANK_SIZE));
+	}
+}
@@ -1,6 +1,6 @@
 use super::banked_mem::*;
 use super::mem::{BankedSegment, PpuSegment, Segment};
-use super::{Cartridge, CartridgeInfo, LoadRom, PpuMirror};
+use super::{has_bus_conflicts, Cartridge, CartridgeInfo, LoadRom, PpuMirror};
 
 pub(crate) struct CNRom {
 	prg_rom: BankedMemory,
@@ -9,19 +9,31 @@ pub(crate) struct CNRom {
 	nt1_idx: usize,
 	nt2_idx: usize,
 	chr_rom_bank: u8,
+	bus_conflicts: bool,
 }
 
 impl Segment for CNRom {
 	fn read(&self, addr: usize) -> u8 {
 		match addr {
+			0x4020..=0x7FFF => 0x00, // no expansion ROM or PRG_RAM
 			0x8000..=0xBFFF => self.prg_rom.read(0, addr),
-			0xC000..=0xFFFF => self.prg_rom.read(1, addr),
+			// 16KB of PRG_ROM are mirrored
+			0xC000..=0xFFFF => self.prg_rom.read(self.prg_rom.bank_cnt() - 1, addr),
 			_ => panic!("CNRom segment read(): address out of memory range: 0x{:x}", addr),
 		}
 	}
 	fn write(&mut self, addr: usize, val: u8) {
 		match addr {
-			0x8000..=0xFFFF => self.chr_rom_bank = val,
+			0x4020..=0x7FFF => {}
+			0x8000..=0xFFFF => {
+				let val = if self.bus_conflicts {
+					val & Segment::read(self, addr)
+				} else {
+					val
+				};
+
+				self.chr_rom_bank = val % (self.chr_rom.bank_cnt() as u8);
+			}
 			_ => panic!("CNRom segment write(): address out of memory range: 0x{:x}", addr),
 		}
 	}
@@ -85,6 +97,7 @@ impl LoadRom for CNRom {
 			nt1_idx: nt1,
 			nt2_idx: nt2,
 			chr_rom_bank: 0,
+			bus_conflicts: has_bus_conflicts(info, true),
 		})
 	}
 }
@@ -94,6 +107,26 @@ impl Cartridge for CNRom {
 		false
 	}
 
+	// no PRG RAM
+	fn cpu_read_mapped(&self, addr: usize) -> bool {
+		addr >= 0x8000
+	}
+
+	fn prg_rom_offset(&self, addr: usize) -> Option<usize> {
+		match addr {
+			0x8000..=0xBFFF => Some(self.prg_rom.offset(0, addr)),
+			0xC000..=0xFFFF => Some(self.prg_rom.offset(self.prg_rom.bank_cnt() - 1, addr)),
+			_ => None,
+		}
+	}
+
+	fn chr_rom_offset(&self, addr: usize) -> Option<usize> {
+		match addr {
+			0..=0x1FFF => Some(self.chr_rom.offset(self.chr_rom_bank as usize, addr)),
+			_ => None,
+		}
+	}
+
 	fn get_battery_ram<'a>(&'a self) -> &'a [u8] {
 		panic!("CNROM: savestates are not supported");
 	}
@@ -101,4 +134,8 @@ impl Cartridge for CNRom {
 	fn set_battery_ram(&mut self, ram: &[u8]) {
 		panic!("CNROM: savestates are not supported");
 	}
+
+	fn load_trainer(&mut self, _trainer: &[u8]) {
+		println!("CNROM: no PRG RAM available, the trainer is ignored");
+	}
 }
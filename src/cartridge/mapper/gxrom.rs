@@ -0,0 +1,152 @@
+use super::banked_mem::*;
+use super::mem::{BankedSegment, PpuSegment, Segment};
+use super::{has_bus_conflicts, Cartridge, CartridgeInfo, LoadRom, PpuMirror};
+
+const PRG_BANK_SIZE: usize = 32768;
+
+pub(crate) struct GxRom {
+	prg_rom: BankedMemory,
+	chr_rom: BankedMemory,
+	ci_ram: BankedMemory,
+	nt1_idx: usize,
+	nt2_idx: usize,
+	prg_bank: usize,
+	chr_bank: usize,
+	bus_conflicts: bool,
+}
+
+impl Segment for GxRom {
+	fn read(&self, addr: usize) -> u8 {
+		match addr {
+			0x4020..=0x7FFF => 0x00, // no expansion ROM or PRG_RAM
+			0x8000..=0xFFFF => self.prg_rom.read(self.prg_bank, addr),
+			_ => panic!("GxRom segment read(): address out of memory range: 0x{:x}", addr),
+		}
+	}
+
+	fn write(&mut self, addr: usize, val: u8) {
+		const CHR_BANK_MASK: u8 = 0x03;
+		const PRG_BANK_IDX: u8 = 4;
+		const PRG_BANK_MASK: u8 = 0x03;
+
+		match addr {
+			0x4020..=0x7FFF => {}
+			0x8000..=0xFFFF => {
+				let val = if self.bus_conflicts {
+					val & Segment::read(self, addr)
+				} else {
+					val
+				};
+
+				self.chr_bank = ((val & CHR_BANK_MASK) as usize) % self.chr_rom.bank_cnt();
+				self.prg_bank =
+					(((val >> PRG_BANK_IDX) & PRG_BANK_MASK) as usize) % self.prg_rom.bank_cnt();
+			}
+			_ => panic!("GxRom segment write(): address out of memory range: 0x{:x}", addr),
+		}
+	}
+}
+
+impl PpuSegment for GxRom {
+	fn read(&mut self, addr: usize) -> u8 {
+		let bank_addr = addr % self.ci_ram.bank_size();
+
+		match addr {
+			0..=0x1FFF => self.chr_rom.read(self.chr_bank, addr),
+			0x2000..=0x23FF | 0x3000..=0x33FF => self.ci_ram.read(0, bank_addr),
+			0x2400..=0x27FF | 0x3400..=0x37FF => self.ci_ram.read(self.nt1_idx, bank_addr),
+			0x2800..=0x2BFF | 0x3800..=0x3BFF => self.ci_ram.read(self.nt2_idx, bank_addr),
+			0x2C00..=0x2FFF | 0x3C00..=0x3EFF => self.ci_ram.read(1, bank_addr),
+			_ => panic!("GxRom PPU segment read(): address out of memory range: 0x{:x}", addr),
+		}
+	}
+
+	fn write(&mut self, addr: usize, val: u8) {
+		let bank_addr = addr % self.ci_ram.bank_size();
+
+		match addr {
+			0..=0x1FFF => (),
+			0x2000..=0x23FF | 0x3000..=0x33FF => self.ci_ram.write(0, bank_addr, val),
+			0x2400..=0x27FF | 0x3400..=0x37FF => self.ci_ram.write(self.nt1_idx, bank_addr, val),
+			0x2800..=0x2BFF | 0x3800..=0x3BFF => self.ci_ram.write(self.nt2_idx, bank_addr, val),
+			0x2C00..=0x2FFF | 0x3C00..=0x3EFF => self.ci_ram.write(1, bank_addr, val),
+			_ => panic!("GxRom PPU segment write(): address out of memory range: 0x{:x}", addr),
+		}
+	}
+
+	fn irq(&mut self) -> bool {
+		false
+	}
+}
+
+impl LoadRom for GxRom {
+	fn load(data: &[u8], info: &CartridgeInfo) -> Box<dyn Cartridge> {
+		println!("Load GxROM ROM");
+
+		let (nt1, nt2) = if let PpuMirror::Horizontal = info.ppu_mirror {
+			(0, 1)
+		} else {
+			(1, 0)
+		};
+
+		let prg_rom_bytes = info.prg_rom_cnt * PRG_ROM_BANK_SIZE;
+		assert_eq!(data.len(), prg_rom_bytes + info.chr_rom_cnt * CHR_ROM_BANK_SIZE);
+
+		Box::new(Self {
+			prg_rom: BankedMemory::load(
+				&data[..prg_rom_bytes],
+				PRG_BANK_SIZE,
+				prg_rom_bytes / PRG_BANK_SIZE,
+			),
+			chr_rom: BankedMemory::load(
+				&data[prg_rom_bytes..],
+				CHR_ROM_BANK_SIZE,
+				info.chr_rom_cnt,
+			),
+			ci_ram: BankedMemory::empty(CI_RAM_BANK_SIZE, CI_RAM_BANK_CNT),
+			nt1_idx: nt1,
+			nt2_idx: nt2,
+			prg_bank: 0,
+			chr_bank: 0,
+			// all GxROM boards have bus conflicts
+			bus_conflicts: has_bus_conflicts(info, true),
+		})
+	}
+}
+
+impl Cartridge for GxRom {
+	fn support_savestates(&self) -> bool {
+		false
+	}
+
+	// no PRG RAM
+	fn cpu_read_mapped(&self, addr: usize) -> bool {
+		addr >= 0x8000
+	}
+
+	fn prg_rom_offset(&self, addr: usize) -> Option<usize> {
+		match addr {
+			0x8000..=0xFFFF => Some(self.prg_rom.offset(self.prg_bank, addr)),
+			_ => None,
+		}
+	}
+
+	fn chr_rom_offset(&self, addr: usize) -> Option<usize> {
+		match addr {
+			0..=0x1FFF => Some(self.chr_rom.offset(self.chr_bank, addr)),
+			_ => None,
+		}
+	}
+
+	fn get_battery_ram<'a>(&'a self) -> &'a [u8] {
+		panic!("GxROM: savestates are not supported");
+	}
+
+	fn set_battery_ram(&mut self, _ram: &[u8]) {
+		panic!("GxROM: savestates are not supported");
+	}
+
+	fn load_trainer(&mut self, _trainer: &[u8]) {
+		println!("GxROM: no PRG RAM available, the trainer is ignored");
+	}
+}
@@ -134,6 +134,7 @@ impl Mmc1 {
 impl Segment for Mmc1 {
 	fn read(&self, addr: usize) -> u8 {
 		match addr {
+			0x4020..=0x5FFF => 0x00, // no expansion ROM
 			0x6000..=0x7FFF => {
 				if self.prg_ram.is_none() {
 					0
@@ -150,6 +151,7 @@ impl Segment for Mmc1 {
 	}
 	fn write(&mut self, addr: usize, val: u8) {
 		match addr {
+			0x4020..=0x5FFF => {} // no expansion ROM
 			0x6000..=0x7FFF => {
 				if let Some(ram) = self.prg_ram.as_mut() {
 					if (self.prg_reg & PRG_RAM_ENABLE_MASK) == 0 {
@@ -275,6 +277,31 @@ impl Cartridge for Mmc1 {
 		self.prg_ram.is_some()
 	}
 
+	// a missing or disabled PRG RAM is open bus
+	fn cpu_read_mapped(&self, addr: usize) -> bool {
+		addr >= 0x8000
+			|| (addr >= 0x6000
+				&& self.prg_ram.is_some()
+				&& (self.prg_reg & PRG_RAM_ENABLE_MASK) == 0)
+	}
+
+	fn prg_rom_offset(&self, addr: usize) -> Option<usize> {
+		match addr {
+			0x8000..=0xBFFF => Some(self.prg_rom.offset(self.prg_sel[0], addr)),
+			0xC000..=0xFFFF => Some(self.prg_rom.offset(self.prg_sel[1], addr)),
+			_ => None,
+		}
+	}
+
+	fn chr_rom_offset(&self, addr: usize) -> Option<usize> {
+		match addr {
+			_ if self.use_chr_ram => None,
+			0x0000..=0x0FFF => Some(self.chr_romram.offset(self.chr_sel[0], addr)),
+			0x1000..=0x1FFF => Some(self.chr_romram.offset(self.chr_sel[1], addr)),
+			_ => None,
+		}
+	}
+
 	fn get_battery_ram<'a>(&'a self) -> &'a [u8] {
 		self.prg_ram.as_ref().unwrap().data().as_slice()
 	}
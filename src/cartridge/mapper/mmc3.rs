@@ -5,6 +5,10 @@ use crate::mask;
 
 const MMC3_PRG_ROM_BANK_SIZE: usize = 8192;
 const MMC3_CHR_ROM_BANK_SIZE: usize = 1024;
+// A12 has to be low for a while to count a rise, so the pattern fetches of the sprites (which
+// alternate with nametable fetches) only clock the counter once per line. The MMC3 measures it
+// by M2, the reads of the PPU are close enough.
+const A12_LOW_READS: usize = 3;
 
 pub(crate) struct Mmc3 {
 	prg_rom: BankedMemory,
@@ -26,7 +30,7 @@ pub(crate) struct Mmc3 {
 	irq_load: u8,
 	irq_reload: bool,
 	irq_enable: bool,
-	prev_a12: bool,
+	a12_low_cnt: usize,
 	irq_thrown: bool,
 	irq_asserted: bool,
 }
@@ -37,8 +41,12 @@ impl Mmc3 {
 		const ADDR_MAX: usize = 0x2000;
 
 		let a12 = addr < ADDR_MAX && (addr & A12_MASK) > 0;
-		let rising_edge = self.prev_a12 == false && a12 == true;
-		self.prev_a12 = a12;
+		let rising_edge = a12 && self.a12_low_cnt >= A12_LOW_READS;
+		self.a12_low_cnt = if a12 {
+			0
+		} else {
+			self.a12_low_cnt.saturating_add(1)
+		};
 
 		// we have to clock the counter on the rising edge
 		if !rising_edge {
@@ -60,6 +68,26 @@ impl Mmc3 {
 		}
 	}
 
+	// CHR and nametables without clocking the IRQ counter
+	fn ppu_read(&self, addr: usize) -> u8 {
+		match addr {
+			0x0000..=0x03FF => self.chr_rom.read(self.chr_sel[0], addr),
+			0x0400..=0x07FF => self.chr_rom.read(self.chr_sel[1], addr),
+			0x0800..=0x0BFF => self.chr_rom.read(self.chr_sel[2], addr),
+			0x0C00..=0x0FFF => self.chr_rom.read(self.chr_sel[3], addr),
+			0x1000..=0x13FF => self.chr_rom.read(self.chr_sel[4], addr),
+			0x1400..=0x17FF => self.chr_rom.read(self.chr_sel[5], addr),
+			0x1800..=0x1BFF => self.chr_rom.read(self.chr_sel[6], addr),
+			0x1C00..=0x1FFF => self.chr_rom.read(self.chr_sel[7], addr),
+
+			0x2000..=0x23FF | 0x3000..=0x33FF => self.ci_ram.read(self.ci_sel[0], addr),
+			0x2400..=0x27FF | 0x3400..=0x37FF => self.ci_ram.read(self.ci_sel[1], addr),
+			0x2800..=0x2BFF | 0x3800..=0x3BFF => self.ci_ram.read(self.ci_sel[2], addr),
+			0x2C00..=0x2FFF | 0x3C00..=0x3EFF => self.ci_ram.read(self.ci_sel[3], addr),
+			_ => panic!("MMC3 PPU segment read(): address out of memory range: 0x{:x}", addr),
+		}
+	}
+
 	fn update_banks(&mut self) {
 		const DOUBLE_BANK_MASK: u8 = mask!(u8, 1, 0, true);
 		const A12_INV_MASK: u8 = mask!(u8, 1, 7, false);
@@ -91,6 +119,7 @@ impl Mmc3 {
 impl Segment for Mmc3 {
 	fn read(&self, addr: usize) -> u8 {
 		match addr {
+			0x4020..=0x5FFF => 0x00, // no expansion ROM
 			0x6000..=0x7FFF => {
 				if self.prg_ram.is_none() {
 					0
@@ -109,6 +138,7 @@ impl Segment for Mmc3 {
 	}
 	fn write(&mut self, addr: usize, val: u8) {
 		match addr {
+			0x4020..=0x5FFF => {} // no expansion ROM
 			0x6000..=0x7FFF => {
 				if let Some(ram) = self.prg_ram.as_mut() {
 					if !self.prg_ram_wp && self.prg_ram_enable {
@@ -175,23 +205,11 @@ impl Segment for Mmc3 {
 impl PpuSegment for Mmc3 {
 	fn read(&mut self, addr: usize) -> u8 {
 		self.clock_irq_counter(addr);
+		self.ppu_read(addr)
+	}
 
-		match addr {
-			0x0000..=0x03FF => self.chr_rom.read(self.chr_sel[0], addr),
-			0x0400..=0x07FF => self.chr_rom.read(self.chr_sel[1], addr),
-			0x0800..=0x0BFF => self.chr_rom.read(self.chr_sel[2], addr),
-			0x0C00..=0x0FFF => self.chr_rom.read(self.chr_sel[3], addr),
-			0x1000..=0x13FF => self.chr_rom.read(self.chr_sel[4], addr),
-			0x1400..=0x17FF => self.chr_rom.read(self.chr_sel[5], addr),
-			0x1800..=0x1BFF => self.chr_rom.read(self.chr_sel[6], addr),
-			0x1C00..=0x1FFF => self.chr_rom.read(self.chr_sel[7], addr),
-
-			0x2000..=0x23FF | 0x3000..=0x33FF => self.ci_ram.read(self.ci_sel[0], addr),
-			0x2400..=0x27FF | 0x3400..=0x37FF => self.ci_ram.read(self.ci_sel[1], addr),
-			0x2800..=0x2BFF | 0x3800..=0x3BFF => self.ci_ram.read(self.ci_sel[2], addr),
-			0x2C00..=0x2FFF | 0x3C00..=0x3EFF => self.ci_ram.read(self.ci_sel[3], addr),
-			_ => panic!("MMC3 PPU segment read(): address out of memory range: 0x{:x}", addr),
-		}
+	fn peek(&mut self, addr: usize) -> u8 {
+		self.ppu_read(addr)
 	}
 
 	fn write(&mut self, addr: usize, val: u8) {
@@ -205,10 +223,8 @@ impl PpuSegment for Mmc3 {
 	}
 
 	fn irq(&mut self) -> bool {
-		let ret = self.irq_asserted;
-		self.irq_asserted = false;
-
-		ret
+		// the line stays asserted until the IRQ is acknowledged by a write to $E000
+		self.irq_asserted
 	}
 }
 
@@ -265,7 +281,7 @@ impl LoadRom for Mmc3 {
 			irq_load: 0,
 			irq_reload: false,
 			irq_enable: false,
-			prev_a12: false,
+			a12_low_cnt: 0,
 			irq_thrown: false,
 			irq_asserted: false,
 		})
@@ -277,6 +293,28 @@ impl Cartridge for Mmc3 {
 		self.prg_ram.is_some()
 	}
 
+	// a missing or disabled PRG RAM is open bus
+	fn cpu_read_mapped(&self, addr: usize) -> bool {
+		addr >= 0x8000 || (addr >= 0x6000 && self.prg_ram.is_some() && self.prg_ram_enable)
+	}
+
+	fn prg_rom_offset(&self, addr: usize) -> Option<usize> {
+		match addr {
+			0x8000..=0x9FFF => Some(self.prg_rom.offset(self.prg_sel[0], addr)),
+			0xA000..=0xBFFF => Some(self.prg_rom.offset(self.prg_sel[1], addr)),
+			0xC000..=0xDFFF => Some(self.prg_rom.offset(self.prg_sel[2], addr)),
+			0xE000..=0xFFFF => Some(self.prg_rom.offset(self.prg_rom.bank_cnt() - 1, addr)),
+			_ => None,
+		}
+	}
+
+	fn chr_rom_offset(&self, addr: usize) -> Option<usize> {
+		match addr {
+			0x0000..=0x1FFF => Some(self.chr_rom.offset(self.chr_sel[addr / 0x400], addr)),
+			_ => None,
+		}
+	}
+
 	fn get_battery_ram<'a>(&'a self) -> &'a [u8] {
 		self.prg_ram.as_ref().unwrap().data().as_slice()
 	}
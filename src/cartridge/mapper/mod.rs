@@ -1,9 +1,13 @@
+pub(crate) mod axrom;
+pub(crate) mod bnrom;
 pub(crate) mod cnrom;
+pub(crate) mod gxrom;
 pub(crate) mod mmc1;
 pub(crate) mod mmc3;
 pub(crate) mod nrom;
 // pub(crate) mod unmapped;
 pub(crate) mod uxrom;
+pub(crate) mod vs_unisystem;
 
 use super::banked_mem;
 use super::mem;
@@ -6,6 +6,7 @@ pub(crate) struct NRom {
 	prg_ram: BankedMemory,
 	prg_rom: BankedMemory,
 	chr_rom: BankedMemory,
+	chr_ram: bool, // boards without CHR_ROM have 8KB CHR_RAM instead, e.g. a lot of test ROMs
 	ci_ram: BankedMemory,
 	nt1_idx: usize,
 	nt2_idx: usize,
@@ -61,7 +62,11 @@ impl PpuSegment for NRom {
 		let bank_addr = addr % self.ci_ram.bank_size();
 
 		match addr {
-			0..=0x1FFF => (),
+			0..=0x1FFF => {
+				if self.chr_ram {
+					self.chr_rom.write(0, addr, val);
+				}
+			}
 			0x2000..=0x23FF | 0x3000..=0x33FF => self.ci_ram.write(0, bank_addr, val),
 			0x2400..=0x27FF | 0x3400..=0x37FF => self.ci_ram.write(self.nt1_idx, bank_addr, val),
 			0x2800..=0x2BFF | 0x3800..=0x3BFF => self.ci_ram.write(self.nt2_idx, bank_addr, val),
@@ -98,11 +103,16 @@ impl LoadRom for NRom {
 				PRG_ROM_BANK_SIZE,
 				info.prg_rom_cnt,
 			),
-			chr_rom: BankedMemory::load(
-				&data[prg_rom_bytes..(prg_rom_bytes + info.chr_rom_cnt * CHR_ROM_BANK_SIZE)],
-				CHR_ROM_BANK_SIZE,
-				info.chr_rom_cnt,
-			),
+			chr_rom: if info.chr_rom_cnt == 0 {
+				BankedMemory::empty(CHR_RAM_BANK_SIZE, 1)
+			} else {
+				BankedMemory::load(
+					&data[prg_rom_bytes..(prg_rom_bytes + CHR_ROM_BANK_SIZE)],
+					CHR_ROM_BANK_SIZE,
+					1,
+				)
+			},
+			chr_ram: info.chr_rom_cnt == 0,
 			ci_ram: BankedMemory::empty(CI_RAM_BANK_SIZE, CI_RAM_BANK_CNT),
 			nt1_idx: nt1,
 			nt2_idx: nt2,
@@ -115,6 +125,21 @@ impl Cartridge for NRom {
 		true
 	}
 
+	fn prg_rom_offset(&self, addr: usize) -> Option<usize> {
+		match addr {
+			0x8000..=0xBFFF => Some(self.prg_rom.offset(0, addr)),
+			0xC000..=0xFFFF => Some(self.prg_rom.offset(self.prg_rom.bank_cnt() - 1, addr)),
+			_ => None,
+		}
+	}
+
+	fn chr_rom_offset(&self, addr: usize) -> Option<usize> {
+		match addr {
+			0..=0x1FFF if !self.chr_ram => Some(self.chr_rom.offset(0, addr)),
+			_ => None,
+		}
+	}
+
 	fn set_battery_ram(&mut self, ram: &[u8]) {
 		assert!(
 			ram.len() == self.prg_ram.size(),
@@ -139,7 +164,7 @@ fn assert_input(data: &[u8], info: &CartridgeInfo) {
 		data.len()
 	);
 
-	assert_eq!(info.chr_rom_cnt, 1, "NROM supports only 1 CHR_ROM bank");
+	assert!(info.chr_rom_cnt <= 1, "NROM supports only 1 CHR_ROM bank");
 
 	match info.ppu_mirror {
 		PpuMirror::Horizontal | PpuMirror::Vertical => (),
@@ -1,6 +1,6 @@
 use super::banked_mem::*;
 use super::mem::{BankedSegment, PpuSegment, Segment};
-use super::{Cartridge, CartridgeInfo, LoadRom, PpuMirror};
+use super::{has_bus_conflicts, Cartridge, CartridgeInfo, LoadRom, PpuMirror};
 
 pub(crate) struct UxRom {
 	prg_rom: BankedMemory,
@@ -10,11 +10,13 @@ pub(crate) struct UxRom {
 	bank_cnt: usize,
 	nt1_idx: usize,
 	nt2_idx: usize,
+	bus_conflicts: bool,
 }
 
 impl Segment for UxRom {
 	fn read(&self, addr: usize) -> u8 {
 		match addr {
+			0x4020..=0x7FFF => 0x00, // no expansion ROM or PRG_RAM
 			0x8000..=0xBFFF => self.prg_rom.read(self.bank_idx, addr),
 			0xC000..=0xFFFF => self.prg_rom.read(self.bank_cnt - 1, addr),
 			_ => panic!("UxRom segment read(): address out of memory range: 0x{:x}", addr),
@@ -23,7 +25,17 @@ impl Segment for UxRom {
 
 	fn write(&mut self, addr: usize, val: u8) {
 		match addr {
-			0x8000..=0xFFFF => self.bank_idx = (val & 0x0F) as usize,
+			0x4020..=0x7FFF => {}
+			0x8000..=0xFFFF => {
+				// UNROM has bus conflicts, UOROM doesn't (but the submapper tells us)
+				let val = if self.bus_conflicts {
+					val & Segment::read(self, addr)
+				} else {
+					val
+				};
+
+				self.bank_idx = ((val & 0x0F) as usize) % self.bank_cnt;
+			}
 			_ => panic!("UxRom segment write(): address out of memory range: 0x{:x}", addr),
 		}
 	}
@@ -82,6 +94,7 @@ impl LoadRom for UxRom {
 			bank_cnt: info.prg_rom_cnt,
 			nt1_idx: nt1,
 			nt2_idx: nt2,
+			bus_conflicts: has_bus_conflicts(info, true),
 		})
 	}
 }
@@ -91,6 +104,19 @@ impl Cartridge for UxRom {
 		false
 	}
 
+	// no PRG RAM
+	fn cpu_read_mapped(&self, addr: usize) -> bool {
+		addr >= 0x8000
+	}
+
+	fn prg_rom_offset(&self, addr: usize) -> Option<usize> {
+		match addr {
+			0x8000..=0xBFFF => Some(self.prg_rom.offset(self.bank_idx, addr)),
+			0xC000..=0xFFFF => Some(self.prg_rom.offset(self.bank_cnt - 1, addr)),
+			_ => None,
+		}
+	}
+
 	fn get_battery_ram<'a>(&'a self) -> &'a [u8] {
 		panic!("UxROM: savestates are not supported");
 	}
@@ -98,4 +124,8 @@ impl Cartridge for UxRom {
 	fn set_battery_ram(&mut self, ram: &[u8]) {
 		panic!("UxROM: savestates are not supported");
 	}
+
+	fn load_trainer(&mut self, _trainer: &[u8]) {
+		println!("UxROM: no PRG RAM available, the trainer is ignored");
+	}
 }
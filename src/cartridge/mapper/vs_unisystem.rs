@@ -0,0 +1,163 @@
+use super::banked_mem::*;
+use super::mem::{BankedSegment, PpuSegment, Segment};
+use super::{Cartridge, CartridgeInfo, LoadRom};
+
+// Mapper 99, the Vs. UniSystem. The CHR bank (and the first PRG bank of 40KB games like
+// Vs. Gumshoe) is selected with OUT2 of the controller port latch ($4016). The Vs. System always
+// has 4KB of VRAM, so it uses 4-screen mirroring.
+
+const PRG_BANK_SIZE: usize = 8192;
+const PRG_RAM_SIZE: usize = 2048; // mirrored over $6000-$7FFF
+const NT_CNT: usize = 4;
+const OUT2_IDX: usize = 2;
+
+pub(crate) struct VsUniSystem {
+	prg_ram: BankedMemory,
+	prg_rom: BankedMemory,
+	chr_mem: BankedMemory,
+	ci_ram: BankedMemory,
+	use_chr_ram: bool,
+	battery_ram: bool,
+
+	prg_bank: usize,
+	chr_bank: usize,
+}
+
+impl VsUniSystem {
+	fn nt_idx(addr: usize) -> usize {
+		((addr - 0x2000) / CI_RAM_BANK_SIZE) % NT_CNT
+	}
+}
+
+impl Segment for VsUniSystem {
+	fn read(&self, addr: usize) -> u8 {
+		let bank_cnt = self.prg_rom.bank_cnt();
+
+		match addr {
+			0x4020..=0x5FFF => 0x00, // no expansion ROM supported
+			0x6000..=0x7FFF => self.prg_ram.read(0, addr),
+			0x8000..=0x9FFF => self.prg_rom.read(self.prg_bank % bank_cnt, addr),
+			0xA000..=0xFFFF => {
+				self.prg_rom.read(((addr - 0x8000) / PRG_BANK_SIZE) % bank_cnt, addr)
+			}
+			_ => panic!("VsUniSystem segment read(): address out of memory range: 0x{:x}", addr),
+		}
+	}
+
+	fn write(&mut self, addr: usize, val: u8) {
+		match addr {
+			0x4020..=0x5FFF => {} // no expansion ROM supported
+			0x6000..=0x7FFF => self.prg_ram.write(0, addr, val),
+			0x8000..=0xFFFF => {} // no registers, everything is done via $4016
+			_ => panic!("VsUniSystem segment write(): address out of memory range: 0x{:x}", addr),
+		}
+	}
+}
+
+impl PpuSegment for VsUniSystem {
+	fn read(&mut self, addr: usize) -> u8 {
+		match addr {
+			0..=0x1FFF => self.chr_mem.read(self.chr_bank, addr),
+			0x2000..=0x3EFF => self.ci_ram.read(Self::nt_idx(addr), addr),
+			_ => {
+				panic!("VsUniSystem PPU segment read(): address out of memory range: 0x{:x}", addr)
+			}
+		}
+	}
+
+	fn write(&mut self, addr: usize, val: u8) {
+		match addr {
+			0..=0x1FFF => {
+				if self.use_chr_ram {
+					self.chr_mem.write(self.chr_bank, addr, val);
+				}
+			}
+			0x2000..=0x3EFF => self.ci_ram.write(Self::nt_idx(addr), addr, val),
+			_ => {
+				panic!("VsUniSystem PPU segment write(): address out of memory range: 0x{:x}", addr)
+			}
+		}
+	}
+
+	fn irq(&mut self) -> bool {
+		false
+	}
+}
+
+impl LoadRom for VsUniSystem {
+	fn load(data: &[u8], info: &CartridgeInfo) -> Box<dyn Cartridge> {
+		println!("Load Vs. UniSystem ROM");
+
+		let prg_rom_bytes = info.prg_rom_cnt * PRG_ROM_BANK_SIZE;
+		assert_eq!(
+			data.len(),
+			prg_rom_bytes + info.chr_rom_cnt * CHR_ROM_BANK_SIZE,
+			"VsUniSystem: length of data array does not match with bank_cnt and bank_size: 0x{:x}",
+			data.len()
+		);
+
+		let use_chr_ram = info.chr_rom_cnt == 0;
+		let chr_mem = if use_chr_ram {
+			BankedMemory::empty(CHR_RAM_BANK_SIZE, 1)
+		} else {
+			BankedMemory::load(&data[prg_rom_bytes..], CHR_ROM_BANK_SIZE, info.chr_rom_cnt)
+		};
+
+		Box::new(Self {
+			prg_ram: BankedMemory::empty(PRG_RAM_SIZE, 1),
+			prg_rom: BankedMemory::load(
+				&data[..prg_rom_bytes],
+				PRG_BANK_SIZE,
+				prg_rom_bytes / PRG_BANK_SIZE,
+			),
+			chr_mem,
+			ci_ram: BankedMemory::empty(CI_RAM_BANK_SIZE, NT_CNT),
+			use_chr_ram,
+			battery_ram: info.battery_ram,
+
+			prg_bank: 0,
+			chr_bank: 0,
+		})
+	}
+}
+
+impl Cartridge for VsUniSystem {
+	fn support_savestates(&self) -> bool {
+		self.battery_ram
+	}
+
+	fn prg_rom_offset(&self, addr: usize) -> Option<usize> {
+		let bank_cnt = self.prg_rom.bank_cnt();
+
+		match addr {
+			0x8000..=0x9FFF => Some(self.prg_rom.offset(self.prg_bank % bank_cnt, addr)),
+			0xA000..=0xFFFF => {
+				Some(self.prg_rom.offset(((addr - 0x8000) / PRG_BANK_SIZE) % bank_cnt, addr))
+			}
+			_ => None,
+		}
+	}
+
+	fn chr_rom_offset(&self, addr: usize) -> Option<usize> {
+		match addr {
+			0..=0x1FFF if !self.use_chr_ram => Some(self.chr_mem.offset(self.chr_bank, addr)),
+			_ => None,
+		}
+	}
+
+	fn get_battery_ram(&self) -> &[u8] {
+		self.prg_ram.data().as_slice()
+	}
+
+	fn set_battery_ram(&mut self, ram: &[u8]) {
+		self.prg_ram.reload(ram);
+	}
+
+	fn out_latch_write(&mut self, val: u8) {
+		let out2 = ((val >> OUT2_IDX) & 0x01) as usize;
+
+		self.chr_bank = out2 % self.chr_mem.bank_cnt();
+		// only games with more than 32KB PRG_ROM switch the first PRG bank, bank 4 is the 5th 8KB
+		self.prg_bank = out2 * 4;
+	}
+}
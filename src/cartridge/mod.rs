@@ -9,11 +9,38 @@ use crate::mem;
 use crate::nes::RomErr;
 use mapper::*;
 
+pub const TRAINER_SIZE: usize = 512;
+const TRAINER_ADDR: usize = 0x7000;
+
 pub trait Cartridge: mem::Segment + mem::PpuSegment {
 	fn support_savestates(&self) -> bool;
 	fn get_battery_ram<'a>(&'a self) -> &'a [u8];
 	fn set_battery_ram(&mut self, ram: &[u8]);
 
+	// if the cartridge answers a read of the CPU, otherwise the data bus keeps its value (open bus),
+	// e.g. for the expansion area or a missing or disabled PRG RAM
+	fn cpu_read_mapped(&self, addr: usize) -> bool {
+		addr >= 0x6000
+	}
+
+	// the offset in the PRG ROM which is mapped to the CPU address, none if it's no PRG ROM
+	fn prg_rom_offset(&self, addr: usize) -> Option<usize>;
+
+	// the offset in the CHR ROM which is mapped to the PPU address, none for CHR RAM
+	fn chr_rom_offset(&self, _addr: usize) -> Option<usize> {
+		None
+	}
+
+	// the outputs OUT0-OUT2 of the controller port latch ($4016), used by the Vs. System mapper
+	fn out_latch_write(&mut self, _val: u8) {}
+
+	// copy the trainer to $7000-$71FF of the PRG RAM, this was done by the copiers before the reset
+	fn load_trainer(&mut self, trainer: &[u8]) {
+		for (i, b) in trainer.iter().enumerate() {
+			mem::Segment::write(self, TRAINER_ADDR + i, *b);
+		}
+	}
+
 	fn restore_savestate(&mut self, savefile: &str) -> Result<(), RomErr> {
 		if !self.support_savestates() {
 			return Ok(());
@@ -60,11 +87,13 @@ pub trait LoadRom {
 #[derive(Default)]
 pub struct CartridgeInfo {
 	pub mapper_id: u8,
+	pub submapper_id: u8,
 	pub prg_rom_cnt: usize,
 	pub prg_ram_cnt: usize,
 	pub chr_rom_cnt: usize,
 	pub battery_ram: bool,
 	pub ppu_mirror: PpuMirror,
+	pub bus_conflicts: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -100,10 +129,27 @@ pub fn load(data: &[u8], info: &CartridgeInfo) -> Result<Box<dyn Cartridge>, Car
 		2 => Ok(uxrom::UxRom::load(data, info)),
 		3 => Ok(cnrom::CNRom::load(data, info)),
 		4 => Ok(mmc3::Mmc3::load(data, info)),
+		7 => Ok(axrom::AxRom::load(data, info)),
+		34 => Ok(bnrom::BNRom::load(data, info)),
+		66 => Ok(gxrom::GxRom::load(data, info)),
+		99 => Ok(vs_unisystem::VsUniSystem::load(data, info)),
 		_ => Err(CartridgeErr::NotImplemented(info.mapper_id)),
 	}
 }
 
+// On boards without a write-enable for the PRG_ROM, the ROM drives the data bus too when the CPU
+// writes to a register. The written value is then ANDed with the value in the ROM. For the
+// discrete mappers the submapper tells if the board has bus conflicts (1 = no, 2 = yes), otherwise
+// the default of the board is used. The flag of iNES headers can only add bus conflicts, since it
+// is missing in most dumps of boards which have them.
+pub(crate) fn has_bus_conflicts(info: &CartridgeInfo, board_default: bool) -> bool {
+	match info.submapper_id {
+		1 => false,
+		2 => true,
+		_ => info.bus_conflicts || board_default,
+	}
+}
+
 impl Default for PpuMirror {
 	fn default() -> Self {
 		PpuMirror::Horizontal
@@ -121,3 +167,27 @@ impl std::fmt::Display for PpuMirror {
 		}
 	}
 }
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn bus_conflicts() {
+		// submapper, flag of the header, default of the board
+		for (submapper_id, bus_conflicts, board_default, expected) in [
+			(0, false, false, false),
+			(0, false, true, true),
+			(0, true, false, true),
+			(1, true, true, false),
+			(2, false, false, true),
+		] {
+			let info = CartridgeInfo {
+				submapper_id,
+				bus_conflicts,
+				..Default::default()
+			};
+			assert_eq!(has_bus_conflicts(&info, board_default), expected);
+		}
+	}
+}
@@ -1,4 +1,5 @@
 use crate::cpu::Cpu;
+use crate::mem::cdl::ReadKind;
 use crate::mem::CpuBus;
 
 pub struct Accumulator;
@@ -15,27 +16,81 @@ pub struct Indirect;
 pub struct IndirectX;
 pub struct IndirectY;
 
-fn get_op16<B: CpuBus>(cpu: &Cpu<B>, mem: &mut B) -> u16 {
-	let pc = cpu.pc + 2;
-	((CpuBus::read(mem, (pc as usize) - 1) as u16) << 8)
-		| (CpuBus::read(mem, (pc as usize) - 2) as u16)
+// Every access of the bus takes one CPU cycle, so the address modes also perform the dummy reads
+// the 6502 does while it calculates the effective address.
+
+fn get_op16<B: CpuBus>(cpu: &mut Cpu<B>, mem: &mut B) -> u16 {
+	let l = cpu.fetch(mem, cpu.pc as usize) as u16;
+	let h = cpu.fetch(mem, cpu.pc.wrapping_add(1) as usize) as u16;
+	(h << 8) | l
+}
+
+fn get_op8<B: CpuBus>(cpu: &mut Cpu<B>, mem: &mut B) -> u8 {
+	cpu.fetch(mem, cpu.pc as usize)
+}
+
+// Indexed addressing first accesses the address without the carry into the high byte. Reading
+// instructions only need this dummy read if a page-boundary is crossed, writing and RMW
+// instructions always do it.
+fn indexed<B: CpuBus>(
+	cpu: &mut Cpu<B>,
+	mem: &mut B,
+	addr_base: u16,
+	idx: u8,
+	access: Access,
+) -> (usize, bool) {
+	let addr_real = addr_base.wrapping_add(idx as u16);
+	let boundary = (addr_base >> 8) != (addr_real >> 8);
+
+	if boundary || access != Access::Read {
+		let addr_dummy = (addr_base & 0xFF00) | (addr_real & 0x00FF);
+		cpu.dummy_read(mem, addr_dummy as usize);
+	}
+
+	(addr_real as usize, boundary)
+}
+
+// the address modes as values, e.g. for the disassembler
+#[derive(Clone, Copy, PartialEq)]
+pub enum Mode {
+	Imp,
+	Acc,
+	Imm,
+	Rel,
+	Zpg,
+	Zpx,
+	Zpy,
+	Abs,
+	Abx,
+	Aby,
+	Ind,
+	Izx,
+	Izy,
 }
 
-fn get_op8<B: CpuBus>(cpu: &Cpu<B>, mem: &mut B) -> u8 {
-	let pc = cpu.pc + 1;
-	CpuBus::read(mem, (pc as usize) - 1)
+#[derive(Clone, Copy, PartialEq)]
+pub enum Access {
+	Read,
+	Write,
+	ReadModifyWrite,
 }
 
 pub trait AddressMode<B: CpuBus> {
+	const MODE: Mode;
+
 	// return address and true if page-boundary was crossed, otherwise false
-	fn get_address(cpu: &mut Cpu<B>, mem: &mut B) -> (Option<usize>, bool);
+	fn get_address(cpu: &mut Cpu<B>, mem: &mut B, access: Access) -> (Option<usize>, bool);
 
 	// return the number of operand-bytes
 	fn operand_bytes() -> u16;
 }
 
 impl<B: CpuBus> AddressMode<B> for Accumulator {
-	fn get_address(_cpu: &mut Cpu<B>, _mem: &mut B) -> (Option<usize>, bool) {
+	const MODE: Mode = Mode::Acc;
+
+	fn get_address(cpu: &mut Cpu<B>, mem: &mut B, _access: Access) -> (Option<usize>, bool) {
+		// the byte after the op-code is read but ignored
+		cpu.dummy_read(mem, cpu.pc as usize);
 		(None, false)
 	}
 	fn operand_bytes() -> u16 {
@@ -44,7 +99,11 @@ impl<B: CpuBus> AddressMode<B> for Accumulator {
 }
 
 impl<B: CpuBus> AddressMode<B> for Implied {
-	fn get_address(_cpu: &mut Cpu<B>, _mem: &mut B) -> (Option<usize>, bool) {
+	const MODE: Mode = Mode::Imp;
+
+	fn get_address(cpu: &mut Cpu<B>, mem: &mut B, _access: Access) -> (Option<usize>, bool) {
+		// the byte after the op-code is read but ignored
+		cpu.dummy_read(mem, cpu.pc as usize);
 		(None, false)
 	}
 
@@ -54,7 +113,9 @@ impl<B: CpuBus> AddressMode<B> for Implied {
 }
 
 impl<B: CpuBus> AddressMode<B> for Immediate {
-	fn get_address(cpu: &mut Cpu<B>, _mem: &mut B) -> (Option<usize>, bool) {
+	const MODE: Mode = Mode::Imm;
+
+	fn get_address(cpu: &mut Cpu<B>, _mem: &mut B, _access: Access) -> (Option<usize>, bool) {
 		(Some(cpu.pc as usize), false)
 	}
 
@@ -64,7 +125,9 @@ impl<B: CpuBus> AddressMode<B> for Immediate {
 }
 
 impl<B: CpuBus> AddressMode<B> for Relative {
-	fn get_address(cpu: &mut Cpu<B>, mem: &mut B) -> (Option<usize>, bool) {
+	const MODE: Mode = Mode::Rel;
+
+	fn get_address(cpu: &mut Cpu<B>, mem: &mut B, _access: Access) -> (Option<usize>, bool) {
 		(Some(get_op8(cpu, mem) as usize), false)
 	}
 
@@ -74,7 +137,9 @@ impl<B: CpuBus> AddressMode<B> for Relative {
 }
 
 impl<B: CpuBus> AddressMode<B> for Zeropage {
-	fn get_address(cpu: &mut Cpu<B>, mem: &mut B) -> (Option<usize>, bool) {
+	const MODE: Mode = Mode::Zpg;
+
+	fn get_address(cpu: &mut Cpu<B>, mem: &mut B, _access: Access) -> (Option<usize>, bool) {
 		(Some(get_op8(cpu, mem) as usize), false)
 	}
 
@@ -84,8 +149,12 @@ impl<B: CpuBus> AddressMode<B> for Zeropage {
 }
 
 impl<B: CpuBus> AddressMode<B> for ZeropageX {
-	fn get_address(cpu: &mut Cpu<B>, mem: &mut B) -> (Option<usize>, bool) {
-		(Some(get_op8(cpu, mem).wrapping_add(cpu.x) as usize), false)
+	const MODE: Mode = Mode::Zpx;
+
+	fn get_address(cpu: &mut Cpu<B>, mem: &mut B, _access: Access) -> (Option<usize>, bool) {
+		let addr_base = get_op8(cpu, mem);
+		cpu.read(mem, addr_base as usize); // dummy read while adding the index
+		(Some(addr_base.wrapping_add(cpu.x) as usize), false)
 	}
 
 	fn operand_bytes() -> u16 {
@@ -94,8 +163,12 @@ impl<B: CpuBus> AddressMode<B> for ZeropageX {
 }
 
 impl<B: CpuBus> AddressMode<B> for ZeropageY {
-	fn get_address(cpu: &mut Cpu<B>, mem: &mut B) -> (Option<usize>, bool) {
-		(Some(get_op8(cpu, mem).wrapping_add(cpu.y) as usize), false)
+	const MODE: Mode = Mode::Zpy;
+
+	fn get_address(cpu: &mut Cpu<B>, mem: &mut B, _access: Access) -> (Option<usize>, bool) {
+		let addr_base = get_op8(cpu, mem);
+		cpu.read(mem, addr_base as usize); // dummy read while adding the index
+		(Some(addr_base.wrapping_add(cpu.y) as usize), false)
 	}
 
 	fn operand_bytes() -> u16 {
@@ -104,7 +177,9 @@ impl<B: CpuBus> AddressMode<B> for ZeropageY {
 }
 
 impl<B: CpuBus> AddressMode<B> for Absolute {
-	fn get_address(cpu: &mut Cpu<B>, mem: &mut B) -> (Option<usize>, bool) {
+	const MODE: Mode = Mode::Abs;
+
+	fn get_address(cpu: &mut Cpu<B>, mem: &mut B, _access: Access) -> (Option<usize>, bool) {
 		(Some(get_op16(cpu, mem) as usize), false)
 	}
 
@@ -114,16 +189,13 @@ impl<B: CpuBus> AddressMode<B> for Absolute {
 }
 
 impl<B: CpuBus> AddressMode<B> for AbsoluteX {
-	fn get_address(cpu: &mut Cpu<B>, mem: &mut B) -> (Option<usize>, bool) {
-		let addr_base = get_op16(cpu, mem);
-		let addr_real = addr_base.wrapping_add(cpu.x as u16);
-		let boundary = if (addr_base >> 8) == (addr_real >> 8) {
-			false
-		} else {
-			true
-		};
+	const MODE: Mode = Mode::Abx;
 
-		(Some(addr_real as usize), boundary)
+	fn get_address(cpu: &mut Cpu<B>, mem: &mut B, access: Access) -> (Option<usize>, bool) {
+		let addr_base = get_op16(cpu, mem);
+		let idx = cpu.x;
+		let (addr_real, boundary) = indexed(cpu, mem, addr_base, idx, access);
+		(Some(addr_real), boundary)
 	}
 
 	fn operand_bytes() -> u16 {
@@ -132,16 +204,13 @@ impl<B: CpuBus> AddressMode<B> for AbsoluteX {
 }
 
 impl<B: CpuBus> AddressMode<B> for AbsoluteY {
-	fn get_address(cpu: &mut Cpu<B>, mem: &mut B) -> (Option<usize>, bool) {
-		let addr_base = get_op16(cpu, mem);
-		let addr_real = addr_base.wrapping_add(cpu.y as u16);
-		let boundary = if (addr_base >> 8) == (addr_real >> 8) {
-			false
-		} else {
-			true
-		};
+	const MODE: Mode = Mode::Aby;
 
-		(Some(addr_real as usize), boundary)
+	fn get_address(cpu: &mut Cpu<B>, mem: &mut B, access: Access) -> (Option<usize>, bool) {
+		let addr_base = get_op16(cpu, mem);
+		let idx = cpu.y;
+		let (addr_real, boundary) = indexed(cpu, mem, addr_base, idx, access);
+		(Some(addr_real), boundary)
 	}
 
 	fn operand_bytes() -> u16 {
@@ -150,16 +219,18 @@ impl<B: CpuBus> AddressMode<B> for AbsoluteY {
 }
 
 impl<B: CpuBus> AddressMode<B> for Indirect {
-	fn get_address(cpu: &mut Cpu<B>, mem: &mut B) -> (Option<usize>, bool) {
+	const MODE: Mode = Mode::Ind;
+
+	fn get_address(cpu: &mut Cpu<B>, mem: &mut B, _access: Access) -> (Option<usize>, bool) {
 		let addr_l = get_op16(cpu, mem) as usize;
 
 		// if a page-overflow occurs, the address wraps back around to the SAME
 		// page as the first address was, the next address is NOT located on the
 		// next page, but always on the SAME!!!
 		let addr_h = ((addr_l as u8).wrapping_add(1) as usize) | (addr_l & 0xFF00);
-		let addr_real =
-			((CpuBus::read(mem, addr_h) as u16) << 8) | (CpuBus::read(mem, addr_l) as u16);
-		(Some(addr_real as usize), false)
+		let l = cpu.read(mem, addr_l) as u16;
+		let h = cpu.read(mem, addr_h) as u16;
+		(Some(((h << 8) | l) as usize), false)
 	}
 
 	fn operand_bytes() -> u16 {
@@ -168,12 +239,16 @@ impl<B: CpuBus> AddressMode<B> for Indirect {
 }
 
 impl<B: CpuBus> AddressMode<B> for IndirectX {
-	fn get_address(cpu: &mut Cpu<B>, mem: &mut B) -> (Option<usize>, bool) {
-		let addr1 = get_op8(cpu, mem).wrapping_add(cpu.x);
+	const MODE: Mode = Mode::Izx;
+
+	fn get_address(cpu: &mut Cpu<B>, mem: &mut B, _access: Access) -> (Option<usize>, bool) {
+		let addr_base = get_op8(cpu, mem);
+		cpu.read(mem, addr_base as usize); // dummy read while adding the index
+		let addr1 = addr_base.wrapping_add(cpu.x);
 		let addr2 = addr1.wrapping_add(1);
-		let addr_real = ((CpuBus::read(mem, addr2 as usize) as u16) << 8)
-			| (CpuBus::read(mem, addr1 as usize) as u16);
-		(Some(addr_real as usize), false)
+		let l = cpu.read(mem, addr1 as usize) as u16;
+		let h = cpu.read(mem, addr2 as usize) as u16;
+		(Some(((h << 8) | l) as usize), false)
 	}
 
 	fn operand_bytes() -> u16 {
@@ -182,19 +257,17 @@ impl<B: CpuBus> AddressMode<B> for IndirectX {
 }
 
 impl<B: CpuBus> AddressMode<B> for IndirectY {
-	fn get_address(cpu: &mut Cpu<B>, mem: &mut B) -> (Option<usize>, bool) {
+	const MODE: Mode = Mode::Izy;
+
+	fn get_address(cpu: &mut Cpu<B>, mem: &mut B, access: Access) -> (Option<usize>, bool) {
 		let addr_start1 = get_op8(cpu, mem);
 		let addr_start2 = addr_start1.wrapping_add(1);
-		let addr_next = ((CpuBus::read(mem, addr_start2 as usize) as u16) << 8)
-			| (CpuBus::read(mem, addr_start1 as usize) as u16);
-		let addr_real = addr_next.wrapping_add(cpu.y as u16);
-		let boundary = if (addr_next >> 8) == (addr_real >> 8) {
-			false
-		} else {
-			true
-		};
+		let l = cpu.read(mem, addr_start1 as usize) as u16;
+		let h = cpu.read(mem, addr_start2 as usize) as u16;
+		let idx = cpu.y;
+		let (addr_real, boundary) = indexed(cpu, mem, (h << 8) | l, idx, access);
 
-		(Some(addr_real as usize), boundary)
+		(Some(addr_real), boundary)
 	}
 
 	fn operand_bytes() -> u16 {
@@ -203,6 +276,11 @@ impl<B: CpuBus> AddressMode<B> for IndirectY {
 }
 
 pub trait Operation<B: CpuBus> {
+	const MNEMONIC: &'static str;
+
+	// how the instruction accesses its operand, only relevant for the indexed address modes
+	const ACCESS: Access = Access::Read;
+
 	fn exec(cpu: &mut Cpu<B>, mem: &mut B, addr: Option<usize>) -> Option<usize>;
 }
 
@@ -210,9 +288,16 @@ pub trait AddressOperation<B: CpuBus, A: AddressMode<B>>: Operation<B> {
 	fn cycles(extra_cycles: usize, boundary: bool) -> usize;
 
 	fn do_instruction(cpu: &mut Cpu<B>, mem: &mut B) -> usize {
-		let (addr, boundary) = A::get_address(cpu, mem);
+		let (addr, boundary) = A::get_address(cpu, mem, Self::ACCESS);
 		cpu.pc = cpu.pc.wrapping_add(A::operand_bytes()); // increment pc correctly
 
+		// the operand is read by the instruction
+		match A::MODE {
+			Mode::Imm => mem.set_read_kind(ReadKind::Code),
+			Mode::Izx | Mode::Izy => mem.set_read_kind(ReadKind::IndirectData),
+			_ => (),
+		}
+
 		// run the actual instruction
 		if let Some(ex_c) = Self::exec(cpu, mem, addr) {
 			Self::cycles(ex_c, boundary)
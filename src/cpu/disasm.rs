@@ -0,0 +1,90 @@
+// Static disassembly of the memory as seen by the CPU, i.e. with the PRG banks which are mapped
+// right now. Unlike the trace it doesn't depend on the registers, e.g.
+// C72D  20 84 C7  JSR test_branches
+// Addresses with a label show its name instead.
+
+use super::opcodes::{opcode, Mode::*};
+use crate::mem::CpuBus;
+
+// the instruction at the address and the address of the next one, the memory is only peeked
+pub fn disassemble<B: CpuBus>(
+	mem: &B,
+	addr: u16,
+	label: impl Fn(u16) -> Option<String>,
+) -> (String, u16) {
+	let op = mem.peek(addr as usize);
+	let info = opcode(op);
+	let op8 = mem.peek(addr.wrapping_add(1) as usize);
+	let op16 = ((mem.peek(addr.wrapping_add(2) as usize) as u16) << 8) | (op8 as u16);
+
+	let mut bytes = format!("{:02X}", op);
+	for i in 1..info.bytes {
+		bytes.push_str(&format!(" {:02X}", mem.peek(addr.wrapping_add(i) as usize)));
+	}
+
+	let zp = |addr: u8| label(addr as u16).unwrap_or_else(|| format!("${:02X}", addr));
+	let abs = |addr: u16| label(addr).unwrap_or_else(|| format!("${:04X}", addr));
+	let operand = match info.mode {
+		Imp => String::new(),
+		Acc => String::from("A"),
+		Imm => format!("#${:02X}", op8),
+		Rel => abs(addr.wrapping_add(2).wrapping_add(op8 as i8 as u16)),
+		Zpg => zp(op8),
+		Zpx => format!("{},X", zp(op8)),
+		Zpy => format!("{},Y", zp(op8)),
+		Abs => abs(op16),
+		Abx => format!("{},X", abs(op16)),
+		Aby => format!("{},Y", abs(op16)),
+		Ind => format!("({})", abs(op16)),
+		Izx => format!("({},X)", zp(op8)),
+		Izy => format!("({}),Y", zp(op8)),
+	};
+
+	// undocumented op-codes are marked like in the trace
+	let mark = if info.undocumented {
+		'*'
+	} else {
+		' '
+	};
+	let line = format!("{:04X}  {:<8} {}{} {}", addr, bytes, mark, info.mnemonic, operand);
+	(String::from(line.trim_end()), addr.wrapping_add(info.bytes))
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::mem::FlatBus;
+
+	#[test]
+	fn listing() {
+		let mut mem = FlatBus::new();
+		let code = [0x20, 0x84, 0xC7, 0xB1, 0x10, 0xD0, 0xF9, 0x04, 0x10];
+		for (i, b) in code.iter().enumerate() {
+			mem.poke(0xC72D + i, *b);
+		}
+
+		let label = |addr: u16| match addr {
+			0xC784 => Some(String::from("test_branches")),
+			0xC72D => Some(String::from("loop")),
+			_ => None,
+		};
+
+		let mut lines = Vec::new();
+		let mut addr = 0xC72D;
+		for _ in 0..4 {
+			let (line, next) = disassemble(&mem, addr, label);
+			lines.push(line);
+			addr = next;
+		}
+
+		assert_eq!(
+			lines,
+			[
+				"C72D  20 84 C7  JSR test_branches",
+				"C730  B1 10     LDA ($10),Y",
+				"C732  D0 F9     BNE loop",
+				"C734  04 10    *NOP $10",
+			]
+		);
+	}
+}
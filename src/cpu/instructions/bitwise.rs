@@ -22,8 +22,10 @@ pub struct Ror<A>(PhantomData<A>);
 // AND
 
 impl<B: CpuBus, A: AddressMode<B>> Operation<B> for And<A> {
+	const MNEMONIC: &'static str = "AND";
+
 	fn exec(cpu: &mut Cpu<B>, mem: &mut B, addr: Option<usize>) -> Option<usize> {
-		let op = CpuBus::read(mem, addr.expect("AND requires address"));
+		let op = cpu.read(mem, addr.expect("AND requires address"));
 		cpu.a &= op;
 		cpu.set_zero(cpu.a);
 		cpu.set_negative(cpu.a);
@@ -95,6 +97,8 @@ impl<B: CpuBus> AddressOperation<B, IndirectY> for And<IndirectY> {
 // ASL accumulator
 
 impl<B: CpuBus, A: AddressMode<B>> Operation<B> for AslA<A> {
+	const MNEMONIC: &'static str = "ASL";
+
 	fn exec(cpu: &mut Cpu<B>, _mem: &mut B, _addr: Option<usize>) -> Option<usize> {
 		cpu.set_carry(cpu.a & 0x80 > 0);
 		cpu.a <<= 1;
@@ -114,16 +118,20 @@ impl<B: CpuBus> AddressOperation<B, Accumulator> for AslA<Accumulator> {
 // ASL
 
 impl<B: CpuBus, A: AddressMode<B>> Operation<B> for Asl<A> {
+	const MNEMONIC: &'static str = "ASL";
+	const ACCESS: Access = Access::ReadModifyWrite;
+
 	fn exec(cpu: &mut Cpu<B>, mem: &mut B, addr: Option<usize>) -> Option<usize> {
 		let a = addr.expect("ASL pre_exec() requires an address");
 
-		let mut op = CpuBus::read(mem, a);
+		let mut op = cpu.read(mem, a);
+		cpu.write(mem, a, op); // the unmodified value is written back first
 		cpu.set_carry(op & 0x80 > 0);
 		op <<= 1;
 		cpu.set_zero(op);
 		cpu.set_negative(op);
 
-		CpuBus::write(mem, a, op);
+		cpu.write(mem, a, op);
 
 		None
 	}
@@ -156,8 +164,10 @@ impl<B: CpuBus> AddressOperation<B, AbsoluteX> for Asl<AbsoluteX> {
 // BIT
 
 impl<B: CpuBus, A: AddressMode<B>> Operation<B> for Bit<A> {
+	const MNEMONIC: &'static str = "BIT";
+
 	fn exec(cpu: &mut Cpu<B>, mem: &mut B, addr: Option<usize>) -> Option<usize> {
-		let op = CpuBus::read(mem, addr.expect("BIT pre_exec() requires an address"));
+		let op = cpu.read(mem, addr.expect("BIT pre_exec() requires an address"));
 		cpu.set_statusbit(op & (1 << 7) > 0, 7); // copy negative bit into statusregister
 		cpu.set_statusbit(op & (1 << 6) > 0, 6); // copy overflow bit into statusregister
 		cpu.set_zero(op & cpu.a);
@@ -181,8 +191,10 @@ impl<B: CpuBus> AddressOperation<B, Absolute> for Bit<Absolute> {
 // EOR
 
 impl<B: CpuBus, A: AddressMode<B>> Operation<B> for Eor<A> {
+	const MNEMONIC: &'static str = "EOR";
+
 	fn exec(cpu: &mut Cpu<B>, mem: &mut B, addr: Option<usize>) -> Option<usize> {
-		let op = CpuBus::read(mem, addr.expect("EOR pre_exec() requires an address"));
+		let op = cpu.read(mem, addr.expect("EOR pre_exec() requires an address"));
 		cpu.a ^= op;
 		cpu.set_negative(cpu.a);
 		cpu.set_zero(cpu.a);
@@ -254,6 +266,8 @@ impl<B: CpuBus> AddressOperation<B, IndirectY> for Eor<IndirectY> {
 // LSR accumulator
 
 impl<B: CpuBus, A: AddressMode<B>> Operation<B> for LsrA<A> {
+	const MNEMONIC: &'static str = "LSR";
+
 	fn exec(cpu: &mut Cpu<B>, _mem: &mut B, _addr: Option<usize>) -> Option<usize> {
 		cpu.set_carry(cpu.a & 0x01 > 0);
 		cpu.a >>= 1;
@@ -273,16 +287,20 @@ impl<B: CpuBus> AddressOperation<B, Accumulator> for LsrA<Accumulator> {
 // LSR
 
 impl<B: CpuBus, A: AddressMode<B>> Operation<B> for Lsr<A> {
+	const MNEMONIC: &'static str = "LSR";
+	const ACCESS: Access = Access::ReadModifyWrite;
+
 	fn exec(cpu: &mut Cpu<B>, mem: &mut B, addr: Option<usize>) -> Option<usize> {
 		let a = addr.expect("LSR pre_exec() requires an address");
 
-		let mut op = CpuBus::read(mem, a);
+		let mut op = cpu.read(mem, a);
+		cpu.write(mem, a, op); // the unmodified value is written back first
 		cpu.set_carry(op & 0x01 > 0);
 		op >>= 1;
 		cpu.set_zero(op);
 		cpu.set_negative(op);
 
-		CpuBus::write(mem, a, op);
+		cpu.write(mem, a, op);
 		None
 	}
 }
@@ -314,8 +332,10 @@ impl<B: CpuBus> AddressOperation<B, AbsoluteX> for Lsr<AbsoluteX> {
 // ORA
 
 impl<B: CpuBus, A: AddressMode<B>> Operation<B> for Ora<A> {
+	const MNEMONIC: &'static str = "ORA";
+
 	fn exec(cpu: &mut Cpu<B>, mem: &mut B, addr: Option<usize>) -> Option<usize> {
-		let op = CpuBus::read(mem, addr.expect("ORA requires address"));
+		let op = cpu.read(mem, addr.expect("ORA requires address"));
 		cpu.a |= op;
 		cpu.set_zero(cpu.a);
 		cpu.set_negative(cpu.a);
@@ -387,6 +407,8 @@ impl<B: CpuBus> AddressOperation<B, IndirectY> for Ora<IndirectY> {
 // ROL accumulator
 
 impl<B: CpuBus, A: AddressMode<B>> Operation<B> for RolA<A> {
+	const MNEMONIC: &'static str = "ROL";
+
 	fn exec(cpu: &mut Cpu<B>, _mem: &mut B, _addr: Option<usize>) -> Option<usize> {
 		let carry = cpu.is_carry() as u8;
 
@@ -409,10 +431,14 @@ impl<B: CpuBus> AddressOperation<B, Accumulator> for RolA<Accumulator> {
 // ROL
 
 impl<B: CpuBus, A: AddressMode<B>> Operation<B> for Rol<A> {
+	const MNEMONIC: &'static str = "ROL";
+	const ACCESS: Access = Access::ReadModifyWrite;
+
 	fn exec(cpu: &mut Cpu<B>, mem: &mut B, addr: Option<usize>) -> Option<usize> {
 		let a = addr.expect("ROL pre_exec() requires an address");
 
-		let mut op = CpuBus::read(mem, a);
+		let mut op = cpu.read(mem, a);
+		cpu.write(mem, a, op); // the unmodified value is written back first
 		let carry = cpu.is_carry() as u8;
 		cpu.set_carry(op & 0x80 > 0);
 		op <<= 1;
@@ -420,7 +446,7 @@ impl<B: CpuBus, A: AddressMode<B>> Operation<B> for Rol<A> {
 		cpu.set_zero(op);
 		cpu.set_negative(op);
 
-		CpuBus::write(mem, a, op);
+		cpu.write(mem, a, op);
 		None
 	}
 }
@@ -452,6 +478,8 @@ impl<B: CpuBus> AddressOperation<B, AbsoluteX> for Rol<AbsoluteX> {
 // ROR accumulator
 
 impl<B: CpuBus, A: AddressMode<B>> Operation<B> for RorA<A> {
+	const MNEMONIC: &'static str = "ROR";
+
 	fn exec(cpu: &mut Cpu<B>, _mem: &mut B, _addr: Option<usize>) -> Option<usize> {
 		let carry = cpu.is_carry() as u8;
 
@@ -474,10 +502,14 @@ impl<B: CpuBus> AddressOperation<B, Accumulator> for RorA<Accumulator> {
 // ROL
 
 impl<B: CpuBus, A: AddressMode<B>> Operation<B> for Ror<A> {
+	const MNEMONIC: &'static str = "ROR";
+	const ACCESS: Access = Access::ReadModifyWrite;
+
 	fn exec(cpu: &mut Cpu<B>, mem: &mut B, addr: Option<usize>) -> Option<usize> {
 		let a = addr.expect("ROR pre_exec() requires an address");
 
-		let mut op = CpuBus::read(mem, a);
+		let mut op = cpu.read(mem, a);
+		cpu.write(mem, a, op); // the unmodified value is written back first
 		let carry = cpu.is_carry() as u8;
 		cpu.set_carry(op & 0x01 > 0);
 		op >>= 1;
@@ -485,7 +517,7 @@ impl<B: CpuBus, A: AddressMode<B>> Operation<B> for Ror<A> {
 		cpu.set_zero(op);
 		cpu.set_negative(op);
 
-		CpuBus::write(mem, a, op);
+		cpu.write(mem, a, op);
 		None
 	}
 }
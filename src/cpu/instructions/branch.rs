@@ -8,9 +8,11 @@ macro_rules! branch_instruction_set {
 		pub struct $instr<A>(PhantomData<A>);
 
 		impl<B: CpuBus, A: AddressMode<B>> Operation<B> for $instr<A> {
-			fn exec(cpu: &mut Cpu<B>, _mem: &mut B, addr: Option<usize>) -> Option<usize> {
+			const MNEMONIC: &'static str = $instr_name;
+
+			fn exec(cpu: &mut Cpu<B>, mem: &mut B, addr: Option<usize>) -> Option<usize> {
 				let op = addr.expect(concat!($instr_name, "requires an address")) as i8;
-				Some(branch(cpu, op, cpu.$bit_fn()))
+				Some(branch(cpu, mem, op, cpu.$bit_fn()))
 			}
 		}
 
@@ -27,9 +29,11 @@ macro_rules! branch_instruction_clear {
 		pub struct $instr<A>(PhantomData<A>);
 
 		impl<B: CpuBus, A: AddressMode<B>> Operation<B> for $instr<A> {
-			fn exec(cpu: &mut Cpu<B>, _mem: &mut B, addr: Option<usize>) -> Option<usize> {
+			const MNEMONIC: &'static str = $instr_name;
+
+			fn exec(cpu: &mut Cpu<B>, mem: &mut B, addr: Option<usize>) -> Option<usize> {
 				let op = addr.expect(concat!($instr_name, "requires an address")) as i8;
-				Some(branch(cpu, op, !cpu.$bit_fn()))
+				Some(branch(cpu, mem, op, !cpu.$bit_fn()))
 			}
 		}
 
@@ -51,17 +55,26 @@ branch_instruction_clear!(Bne, is_zero, "BNE");
 branch_instruction_clear!(Bpl, is_negative, "BPL");
 branch_instruction_clear!(Bvc, is_overflow, "BVC");
 
-fn branch<B: CpuBus>(cpu: &mut Cpu<B>, op: i8, flag: bool) -> usize {
+fn branch<B: CpuBus>(cpu: &mut Cpu<B>, mem: &mut B, op: i8, flag: bool) -> usize {
 	if flag {
+		// A taken branch without crossing a page doesn't poll the interrupts on its last cycle, so
+		// an IRQ which occurred during the 2nd cycle is delayed by one instruction.
+		if cpu.irq.run_irq && !cpu.irq.prev_run_irq {
+			cpu.irq.run_irq = false;
+		}
+
+		// the next op-code is read while the offset is added
+		cpu.dummy_read(mem, cpu.pc as usize);
+
 		let pc_old = cpu.pc;
-		let pc_new = (cpu.pc as i16) + (op as i16);
-		cpu.pc = pc_new as u16;
+		cpu.pc = cpu.pc.wrapping_add(op as i16 as u16);
 
 		if (pc_old >> 8) == (cpu.pc >> 8) {
 			// no page-boundary crossed
 			1
 		} else {
-			// page-boundary crossed
+			// page-boundary crossed, the high byte is fixed in an additional cycle
+			cpu.dummy_read(mem, ((pc_old & 0xFF00) | (cpu.pc & 0x00FF)) as usize);
 			2
 		}
 	} else {
@@ -1,5 +1,5 @@
 use super::addressing::*;
-use crate::cpu::Cpu;
+use crate::cpu::{Cpu, Flow};
 use crate::mem::CpuBus;
 use std::marker::PhantomData;
 
@@ -9,6 +9,8 @@ pub struct Rti<A>(PhantomData<A>);
 pub struct Rts<A>(PhantomData<A>);
 
 impl<B: CpuBus, A: AddressMode<B>> Operation<B> for Jmp<A> {
+	const MNEMONIC: &'static str = "JMP";
+
 	fn exec(cpu: &mut Cpu<B>, _mem: &mut B, addr: Option<usize>) -> Option<usize> {
 		let op = addr.expect("JMP requires an address") as u16;
 		cpu.pc = op;
@@ -29,14 +31,11 @@ impl<B: CpuBus> AddressOperation<B, Indirect> for Jmp<Indirect> {
 }
 
 impl<B: CpuBus, A: AddressMode<B>> Operation<B> for Jsr<A> {
-	fn exec(cpu: &mut Cpu<B>, mem: &mut B, addr: Option<usize>) -> Option<usize> {
-		let op = addr.expect("JSR requires an address") as u16;
-
-		// because pc was already incremented and the address
-		// of the last JSR instruction byte must be pushed
-		cpu.push16(mem, cpu.pc - 1);
-		cpu.pc = op;
+	const MNEMONIC: &'static str = "JSR";
 
+	fn exec(cpu: &mut Cpu<B>, _mem: &mut B, addr: Option<usize>) -> Option<usize> {
+		cpu.pc = addr.expect("JSR requires an address") as u16;
+		cpu.flow = Flow::Call;
 		None
 	}
 }
@@ -45,12 +44,32 @@ impl<B: CpuBus> AddressOperation<B, Absolute> for Jsr<Absolute> {
 	fn cycles(_extra_cycles: usize, _boundary: bool) -> usize {
 		6
 	}
+
+	// JSR pushes the return address between reading the low and the high byte of the address
+	fn do_instruction(cpu: &mut Cpu<B>, mem: &mut B) -> usize {
+		let l = cpu.fetch(mem, cpu.pc as usize) as u16;
+		cpu.pc = cpu.pc.wrapping_add(1);
+		cpu.read(mem, (cpu.sp as usize) + 0x100); // dummy read of the stack
+
+		// the address of the last JSR instruction byte is pushed
+		cpu.push16(mem, cpu.pc);
+		let h = cpu.fetch(mem, cpu.pc as usize) as u16;
+
+		Self::exec(cpu, mem, Some(((h << 8) | l) as usize));
+		<Self as AddressOperation<B, Absolute>>::cycles(0, false)
+	}
 }
 
 impl<B: CpuBus, A: AddressMode<B>> Operation<B> for Rti<A> {
+	const MNEMONIC: &'static str = "RTI";
+
 	fn exec(cpu: &mut Cpu<B>, mem: &mut B, _addr: Option<usize>) -> Option<usize> {
+		cpu.read(mem, (cpu.sp as usize) + 0x100); // dummy read of the stack
 		cpu.p = cpu.pop8(mem);
+		cpu.set_break(false);
+		cpu.set_unused(true);
 		cpu.pc = cpu.pop16(mem);
+		cpu.flow = Flow::Return;
 		None
 	}
 }
@@ -62,8 +81,16 @@ impl<B: CpuBus> AddressOperation<B, Implied> for Rti<Implied> {
 }
 
 impl<B: CpuBus, A: AddressMode<B>> Operation<B> for Rts<A> {
+	const MNEMONIC: &'static str = "RTS";
+
 	fn exec(cpu: &mut Cpu<B>, mem: &mut B, _addr: Option<usize>) -> Option<usize> {
-		cpu.pc = cpu.pop16(mem) + 1; // op points to the last byte of the JSR instruction
+		cpu.read(mem, (cpu.sp as usize) + 0x100); // dummy read of the stack
+		cpu.pc = cpu.pop16(mem);
+
+		// pc points to the last byte of the JSR instruction which is read again
+		cpu.dummy_read(mem, cpu.pc as usize);
+		cpu.pc = cpu.pc.wrapping_add(1);
+		cpu.flow = Flow::Return;
 		None
 	}
 }
@@ -15,8 +15,10 @@ pub struct Sbc<A>(PhantomData<A>);
 // ADC
 
 impl<B: CpuBus, A: AddressMode<B>> Operation<B> for Adc<A> {
+	const MNEMONIC: &'static str = "ADC";
+
 	fn exec(cpu: &mut Cpu<B>, mem: &mut B, addr: Option<usize>) -> Option<usize> {
-		let op = CpuBus::read(mem, addr.expect("ADC requires an address"));
+		let op = cpu.read(mem, addr.expect("ADC requires an address"));
 
 		let res16 = (cpu.a as u16) + (op as u16) + (cpu.is_carry() as u16);
 		cpu.set_overflow(cpu.a, op, res16 as u8); // set the bit before the actual operation
@@ -93,15 +95,19 @@ impl<B: CpuBus> AddressOperation<B, IndirectY> for Adc<IndirectY> {
 // DEC
 
 impl<B: CpuBus, A: AddressMode<B>> Operation<B> for Dec<A> {
+	const MNEMONIC: &'static str = "DEC";
+	const ACCESS: Access = Access::ReadModifyWrite;
+
 	fn exec(cpu: &mut Cpu<B>, mem: &mut B, addr: Option<usize>) -> Option<usize> {
 		let a = addr.expect("DEC requires an address");
 
-		let mut op = CpuBus::read(mem, a);
+		let mut op = cpu.read(mem, a);
+		cpu.write(mem, a, op); // the unmodified value is written back first
 		op = op.wrapping_sub(1);
 		cpu.set_negative(op);
 		cpu.set_zero(op);
 
-		CpuBus::write(mem, a, op);
+		cpu.write(mem, a, op);
 		None
 	}
 }
@@ -133,6 +139,8 @@ impl<B: CpuBus> AddressOperation<B, AbsoluteX> for Dec<AbsoluteX> {
 // DEX
 
 impl<B: CpuBus, A: AddressMode<B>> Operation<B> for Dex<A> {
+	const MNEMONIC: &'static str = "DEX";
+
 	fn exec(cpu: &mut Cpu<B>, _mem: &mut B, _addr: Option<usize>) -> Option<usize> {
 		cpu.x = cpu.x.wrapping_sub(1);
 
@@ -151,6 +159,8 @@ impl<B: CpuBus> AddressOperation<B, Implied> for Dex<Implied> {
 // DEY
 
 impl<B: CpuBus, A: AddressMode<B>> Operation<B> for Dey<A> {
+	const MNEMONIC: &'static str = "DEY";
+
 	fn exec(cpu: &mut Cpu<B>, _mem: &mut B, _addr: Option<usize>) -> Option<usize> {
 		cpu.y = cpu.y.wrapping_sub(1);
 
@@ -169,15 +179,19 @@ impl<B: CpuBus> AddressOperation<B, Implied> for Dey<Implied> {
 // INC
 
 impl<B: CpuBus, A: AddressMode<B>> Operation<B> for Inc<A> {
+	const MNEMONIC: &'static str = "INC";
+	const ACCESS: Access = Access::ReadModifyWrite;
+
 	fn exec(cpu: &mut Cpu<B>, mem: &mut B, addr: Option<usize>) -> Option<usize> {
 		let a = addr.expect("INC requires an address");
 
-		let mut op = CpuBus::read(mem, a);
+		let mut op = cpu.read(mem, a);
+		cpu.write(mem, a, op); // the unmodified value is written back first
 		op = op.wrapping_add(1);
 		cpu.set_negative(op);
 		cpu.set_zero(op);
 
-		CpuBus::write(mem, a, op);
+		cpu.write(mem, a, op);
 		None
 	}
 }
@@ -209,6 +223,8 @@ impl<B: CpuBus> AddressOperation<B, AbsoluteX> for Inc<AbsoluteX> {
 // INX
 
 impl<B: CpuBus, A: AddressMode<B>> Operation<B> for Inx<A> {
+	const MNEMONIC: &'static str = "INX";
+
 	fn exec(cpu: &mut Cpu<B>, _mem: &mut B, _addr: Option<usize>) -> Option<usize> {
 		cpu.x = cpu.x.wrapping_add(1);
 		cpu.set_negative(cpu.x);
@@ -227,6 +243,8 @@ impl<B: CpuBus> AddressOperation<B, Implied> for Inx<Implied> {
 // INY
 
 impl<B: CpuBus, A: AddressMode<B>> Operation<B> for Iny<A> {
+	const MNEMONIC: &'static str = "INY";
+
 	fn exec(cpu: &mut Cpu<B>, _mem: &mut B, _addr: Option<usize>) -> Option<usize> {
 		cpu.y = cpu.y.wrapping_add(1);
 
@@ -245,13 +263,14 @@ impl<B: CpuBus> AddressOperation<B, Implied> for Iny<Implied> {
 // SBC
 
 impl<B: CpuBus, A: AddressMode<B>> Operation<B> for Sbc<A> {
+	const MNEMONIC: &'static str = "SBC";
+
 	fn exec(cpu: &mut Cpu<B>, mem: &mut B, addr: Option<usize>) -> Option<usize> {
-		let op = CpuBus::read(mem, addr.expect("SBC requires an address"));
+		let op = cpu.read(mem, addr.expect("SBC requires an address"));
 
 		let res16 = (cpu.a as i16) - (op as i16) - (!cpu.is_carry() as i16);
-		let conv_op = ((op as i16) * -1) as u8;
-
-		cpu.set_overflow(cpu.a, conv_op, res16 as u8); // set the bit before the actual operation
+		// A - M - !C equals A + !M + C, so the overflow is calculated like for ADC
+		cpu.set_overflow(cpu.a, !op, res16 as u8); // set the bit before the actual operation
 		cpu.a = res16 as u8;
 
 		cpu.set_negative(cpu.a);
@@ -4,10 +4,12 @@ use crate::mem::CpuBus;
 use std::marker::PhantomData;
 
 macro_rules! register_instruction_set {
-	($instr:ident, $bit:ident, $state:ident) => {
+	($instr:ident, $bit:ident, $state:ident, $name:expr) => {
 		pub struct $instr<A>(PhantomData<A>);
 
 		impl<B: CpuBus, A: AddressMode<B>> Operation<B> for $instr<A> {
+			const MNEMONIC: &'static str = $name;
+
 			fn exec(cpu: &mut Cpu<B>, _mem: &mut B, _addr: Option<usize>) -> Option<usize> {
 				cpu.set_statusbit($state, $bit);
 				None
@@ -27,8 +29,10 @@ macro_rules! register_instruction_compare {
 		pub struct $instr<A>(PhantomData<A>);
 
 		impl<B: CpuBus, A: AddressMode<B>> Operation<B> for $instr<A> {
+			const MNEMONIC: &'static str = $name;
+
 			fn exec(cpu: &mut Cpu<B>, mem: &mut B, addr: Option<usize>) -> Option<usize> {
-				let op = CpuBus::read(mem, addr.expect(concat!($name, " requires an address")));
+				let op = cpu.read(mem, addr.expect(concat!($name, " requires an address")));
 				let res = cpu.$register.wrapping_sub(op);
 				cpu.set_carry(cpu.$register >= op);
 				cpu.set_negative(res);
@@ -58,14 +62,14 @@ macro_rules! register_instruction_compare {
 	};
 }
 
-register_instruction_set!(Clc, CARRY_IDX, false);
-register_instruction_set!(Cld, DECIMAL_IDX, false);
-register_instruction_set!(Cli, INTERRUPT_IDX, false);
-register_instruction_set!(Clv, OVERFLOW_IDX, false);
+register_instruction_set!(Clc, CARRY_IDX, false, "CLC");
+register_instruction_set!(Cld, DECIMAL_IDX, false, "CLD");
+register_instruction_set!(Cli, INTERRUPT_IDX, false, "CLI");
+register_instruction_set!(Clv, OVERFLOW_IDX, false, "CLV");
 
-register_instruction_set!(Sec, CARRY_IDX, true);
-register_instruction_set!(Sed, DECIMAL_IDX, true);
-register_instruction_set!(Sei, INTERRUPT_IDX, true);
+register_instruction_set!(Sec, CARRY_IDX, true, "SEC");
+register_instruction_set!(Sed, DECIMAL_IDX, true, "SED");
+register_instruction_set!(Sei, INTERRUPT_IDX, true, "SEI");
 
 register_instruction_compare!(Cpx, x, "CPX");
 register_instruction_compare!(Cpy, y, "CPY");
@@ -1,5 +1,5 @@
 use super::addressing::*;
-use crate::cpu::Cpu;
+use crate::cpu::{Cpu, BRK_IDX, UNUSED_IDX};
 use crate::mem::CpuBus;
 use std::marker::PhantomData;
 
@@ -9,7 +9,10 @@ pub struct Pla<A>(PhantomData<A>);
 pub struct Pha<A>(PhantomData<A>);
 
 impl<B: CpuBus, A: AddressMode<B>> Operation<B> for Pla<A> {
+	const MNEMONIC: &'static str = "PLA";
+
 	fn exec(cpu: &mut Cpu<B>, mem: &mut B, _addr: Option<usize>) -> Option<usize> {
+		cpu.read(mem, (cpu.sp as usize) + 0x100); // dummy read of the stack
 		cpu.a = cpu.pop8(mem);
 		cpu.set_negative(cpu.a);
 		cpu.set_zero(cpu.a);
@@ -19,14 +22,22 @@ impl<B: CpuBus, A: AddressMode<B>> Operation<B> for Pla<A> {
 }
 
 impl<B: CpuBus, A: AddressMode<B>> Operation<B> for Plp<A> {
+	const MNEMONIC: &'static str = "PLP";
+
 	fn exec(cpu: &mut Cpu<B>, mem: &mut B, _addr: Option<usize>) -> Option<usize> {
+		cpu.read(mem, (cpu.sp as usize) + 0x100); // dummy read of the stack
 		cpu.p = cpu.pop8(mem);
+
+		// the B flag only exists on the stack and the unused bit always reads as 1
 		cpu.set_break(false);
+		cpu.set_unused(true);
 		None
 	}
 }
 
 impl<B: CpuBus, A: AddressMode<B>> Operation<B> for Pha<A> {
+	const MNEMONIC: &'static str = "PHA";
+
 	fn exec(cpu: &mut Cpu<B>, mem: &mut B, _addr: Option<usize>) -> Option<usize> {
 		cpu.push8(mem, cpu.a);
 		None
@@ -34,13 +45,11 @@ impl<B: CpuBus, A: AddressMode<B>> Operation<B> for Pha<A> {
 }
 
 impl<B: CpuBus, A: AddressMode<B>> Operation<B> for Php<A> {
-	fn exec(cpu: &mut Cpu<B>, mem: &mut B, _addr: Option<usize>) -> Option<usize> {
-		cpu.push8(mem, cpu.p);
-		cpu.set_unused(true);
+	const MNEMONIC: &'static str = "PHP";
 
-		// According to the spec this should be done but in the logfile of the test-rom it's not
-		// done so lets also don't do it.
-		// cpu.set_break(true);
+	fn exec(cpu: &mut Cpu<B>, mem: &mut B, _addr: Option<usize>) -> Option<usize> {
+		// the B flag is pushed as 1 like it's done by BRK, the register itself has no B flag
+		cpu.push8(mem, cpu.p | (1 << BRK_IDX) | (1 << UNUSED_IDX));
 		None
 	}
 }
@@ -8,8 +8,10 @@ macro_rules! load_instruction {
 		pub struct $instr<A>(PhantomData<A>);
 
 		impl<B: CpuBus, A: AddressMode<B>> Operation<B> for $instr<A> {
+			const MNEMONIC: &'static str = $name;
+
 			fn exec(cpu: &mut Cpu<B>, mem: &mut B, addr: Option<usize>) -> Option<usize> {
-				let op = CpuBus::read(mem, addr.expect(concat!($name, " requires an address")));
+				let op = cpu.read(mem, addr.expect(concat!($name, " requires an address")));
 				cpu.$register = op;
 
 				cpu.set_negative(cpu.$register);
@@ -44,9 +46,12 @@ macro_rules! store_instruction {
 		pub struct $instr<A>(PhantomData<A>);
 
 		impl<B: CpuBus, A: AddressMode<B>> Operation<B> for $instr<A> {
+			const MNEMONIC: &'static str = $name;
+			const ACCESS: Access = Access::Write;
+
 			fn exec(cpu: &mut Cpu<B>, mem: &mut B, addr: Option<usize>) -> Option<usize> {
 				let a = addr.expect(concat!($name, " requires an address"));
-				CpuBus::write(mem, a, cpu.$register);
+				cpu.write(mem, a, cpu.$register);
 
 				None
 			}
@@ -71,6 +76,8 @@ macro_rules! transfer_instruction {
 		pub struct $instr<A>(PhantomData<A>);
 
 		impl<B: CpuBus, A: AddressMode<B>> Operation<B> for $instr<A> {
+			const MNEMONIC: &'static str = $name;
+
 			fn exec(cpu: &mut Cpu<B>, _mem: &mut B, _addr: Option<usize>) -> Option<usize> {
 				cpu.$dest_reg = cpu.$src_reg;
 
@@ -92,6 +99,8 @@ macro_rules! transfer_instruction {
 pub struct Txs<A>(PhantomData<A>);
 
 impl<B: CpuBus, A: AddressMode<B>> Operation<B> for Txs<A> {
+	const MNEMONIC: &'static str = "TXS";
+
 	fn exec(cpu: &mut Cpu<B>, _mem: &mut B, _addr: Option<usize>) -> Option<usize> {
 		cpu.sp = cpu.x;
 		None
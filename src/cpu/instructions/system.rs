@@ -7,7 +7,13 @@ pub struct Nop<A>(PhantomData<A>);
 pub struct Brk<A>(PhantomData<A>);
 
 impl<B: CpuBus, A: AddressMode<B>> Operation<B> for Nop<A> {
-	fn exec(_cpu: &mut Cpu<B>, _mem: &mut B, _addr: Option<usize>) -> Option<usize> {
+	const MNEMONIC: &'static str = "NOP";
+
+	fn exec(cpu: &mut Cpu<B>, mem: &mut B, addr: Option<usize>) -> Option<usize> {
+		// the undocumented NOPs with an operand still read it
+		if let Some(a) = addr {
+			cpu.read(mem, a);
+		}
 		None
 	}
 }
@@ -54,9 +60,12 @@ impl<B: CpuBus> AddressOperation<B, AbsoluteX> for Nop<AbsoluteX> {
 }
 
 impl<B: CpuBus, A: AddressMode<B>> Operation<B> for Brk<A> {
+	const MNEMONIC: &'static str = "BRK";
+
 	fn exec(cpu: &mut Cpu<B>, mem: &mut B, _addr: Option<usize>) -> Option<usize> {
-		cpu.assert_interrupt(InterruptSource::BRK);
-		cpu.interrupt(mem);
+		// the byte after BRK is skipped, so the pushed address points behind it
+		cpu.pc = cpu.pc.wrapping_add(1);
+		cpu.interrupt_sequence(mem, InterruptSource::BRK);
 		None
 	}
 }
@@ -9,6 +9,7 @@ pub struct Ane<A>(PhantomData<A>);
 pub struct Arr<A>(PhantomData<A>);
 pub struct Dcp<A>(PhantomData<A>);
 pub struct Isc<A>(PhantomData<A>);
+pub struct Jam<A>(PhantomData<A>);
 pub struct Las<A>(PhantomData<A>);
 pub struct Lax<A>(PhantomData<A>);
 pub struct Lxa<A>(PhantomData<A>);
@@ -23,9 +24,27 @@ pub struct Slo<A>(PhantomData<A>);
 pub struct Sre<A>(PhantomData<A>);
 pub struct Tas<A>(PhantomData<A>);
 
+// The unstable stores AND the value with the high byte of the base address + 1. If the indexing
+// crossed a page, the value also replaces the high byte of the target address.
+// See https://www.masswerk.at/6502/6502_instruction_set.html#SHA
+fn unstable_store<B: CpuBus>(cpu: &mut Cpu<B>, mem: &mut B, address: usize, idx: u8, val: u8) {
+	let boundary = (address & 0xFF) < idx as usize;
+	let h = ((address >> 8) as u8).wrapping_add(!boundary as u8);
+	let val = val & h;
+
+	let address = if boundary {
+		((val as usize) << 8) | (address & 0xFF)
+	} else {
+		address
+	};
+	cpu.write(mem, address, val);
+}
+
 impl<B: CpuBus, A: AddressMode<B>> Operation<B> for Alr<A> {
+	const MNEMONIC: &'static str = "ALR";
+
 	fn exec(cpu: &mut Cpu<B>, mem: &mut B, addr: Option<usize>) -> Option<usize> {
-		let op = CpuBus::read(mem, addr.expect("ALR requires an address"));
+		let op = cpu.read(mem, addr.expect("ALR requires an address"));
 		cpu.a &= op;
 		cpu.set_carry(cpu.a & 0x01 > 0);
 		cpu.a >>= 1;
@@ -44,8 +63,10 @@ impl<B: CpuBus> AddressOperation<B, Immediate> for Alr<Immediate> {
 }
 
 impl<B: CpuBus, A: AddressMode<B>> Operation<B> for Anc<A> {
+	const MNEMONIC: &'static str = "ANC";
+
 	fn exec(cpu: &mut Cpu<B>, mem: &mut B, addr: Option<usize>) -> Option<usize> {
-		let op = CpuBus::read(mem, addr.expect("ANC requires an address"));
+		let op = cpu.read(mem, addr.expect("ANC requires an address"));
 		cpu.a &= op;
 
 		cpu.set_zero(cpu.a);
@@ -63,13 +84,15 @@ impl<B: CpuBus> AddressOperation<B, Immediate> for Anc<Immediate> {
 }
 
 impl<B: CpuBus, A: AddressMode<B>> Operation<B> for Ane<A> {
+	const MNEMONIC: &'static str = "ANE";
+
 	fn exec(cpu: &mut Cpu<B>, mem: &mut B, addr: Option<usize>) -> Option<usize> {
 		// This operation is highly unstable, the constant below depends on temperature, series and
-		// maybe other factors of the chip. We set it to 0xFF here.
-		// See https://www.masswerk.at/6502/6502_instruction_set.html#LAS
-		const CONSTANT: u8 = 0xFF;
+		// maybe other factors of the chip. We use 0xEE, which most chips show.
+		// See https://www.masswerk.at/6502/6502_instruction_set.html#ANE
+		const CONSTANT: u8 = 0xEE;
 
-		let op = CpuBus::read(mem, addr.expect("ANE requires an address"));
+		let op = cpu.read(mem, addr.expect("ANE requires an address"));
 
 		cpu.a = (cpu.a | CONSTANT) & cpu.x & op;
 
@@ -87,8 +110,10 @@ impl<B: CpuBus> AddressOperation<B, Immediate> for Ane<Immediate> {
 }
 
 impl<B: CpuBus, A: AddressMode<B>> Operation<B> for Arr<A> {
+	const MNEMONIC: &'static str = "ARR";
+
 	fn exec(cpu: &mut Cpu<B>, mem: &mut B, addr: Option<usize>) -> Option<usize> {
-		let op = CpuBus::read(mem, addr.expect("ARR requires an address"));
+		let op = cpu.read(mem, addr.expect("ARR requires an address"));
 
 		cpu.a &= op;
 		cpu.a = ((cpu.a & 0x01) << 7) | (cpu.a >> 1);
@@ -128,12 +153,16 @@ impl<B: CpuBus> AddressOperation<B, Immediate> for Arr<Immediate> {
 }
 
 impl<B: CpuBus, A: AddressMode<B>> Operation<B> for Dcp<A> {
+	const MNEMONIC: &'static str = "DCP";
+	const ACCESS: Access = Access::ReadModifyWrite;
+
 	fn exec(cpu: &mut Cpu<B>, mem: &mut B, addr: Option<usize>) -> Option<usize> {
 		let address = addr.expect("DCP requires an address");
-		let mut op = CpuBus::read(mem, address);
+		let mut op = cpu.read(mem, address);
+		cpu.write(mem, address, op); // the unmodified value is written back first
 
 		op = op.wrapping_sub(1);
-		CpuBus::write(mem, address, op);
+		cpu.write(mem, address, op);
 
 		let res = cpu.a.wrapping_sub(op);
 		cpu.set_carry(cpu.a >= op);
@@ -187,17 +216,20 @@ impl<B: CpuBus> AddressOperation<B, IndirectY> for Dcp<IndirectY> {
 }
 
 impl<B: CpuBus, A: AddressMode<B>> Operation<B> for Isc<A> {
+	const MNEMONIC: &'static str = "ISB";
+	const ACCESS: Access = Access::ReadModifyWrite;
+
 	fn exec(cpu: &mut Cpu<B>, mem: &mut B, addr: Option<usize>) -> Option<usize> {
 		let address = addr.expect("ISC requires an address");
-		let mut op = CpuBus::read(mem, address);
+		let mut op = cpu.read(mem, address);
+		cpu.write(mem, address, op); // the unmodified value is written back first
 
 		op = op.wrapping_add(1);
-		CpuBus::write(mem, address, op);
+		cpu.write(mem, address, op);
 
 		let res16 = (cpu.a as i16) - (op as i16) - (!cpu.is_carry() as i16);
-		let conv_op = ((op as i16) * -1) as u8;
-
-		cpu.set_overflow(cpu.a, conv_op, res16 as u8); // set the bit before the actual operation
+		// A - M - !C equals A + !M + C, so the overflow is calculated like for ADC
+		cpu.set_overflow(cpu.a, !op, res16 as u8); // set the bit before the actual operation
 		cpu.a = res16 as u8;
 
 		cpu.set_negative(cpu.a);
@@ -250,9 +282,29 @@ impl<B: CpuBus> AddressOperation<B, IndirectY> for Isc<IndirectY> {
 	}
 }
 
+impl<B: CpuBus, A: AddressMode<B>> Operation<B> for Jam<A> {
+	const MNEMONIC: &'static str = "JAM";
+
+	fn exec(cpu: &mut Cpu<B>, _mem: &mut B, _addr: Option<usize>) -> Option<usize> {
+		// the CPU stops fetching instructions until the next reset, pc stays at the op-code
+		cpu.pc = cpu.pc.wrapping_sub(1);
+		cpu.jammed = true;
+
+		None
+	}
+}
+
+impl<B: CpuBus> AddressOperation<B, Implied> for Jam<Implied> {
+	fn cycles(_extra_cycles: usize, _boundary: bool) -> usize {
+		2
+	}
+}
+
 impl<B: CpuBus, A: AddressMode<B>> Operation<B> for Las<A> {
+	const MNEMONIC: &'static str = "LAS";
+
 	fn exec(cpu: &mut Cpu<B>, mem: &mut B, addr: Option<usize>) -> Option<usize> {
-		let res = cpu.sp & CpuBus::read(mem, addr.expect("LAS requires an address"));
+		let res = cpu.sp & cpu.read(mem, addr.expect("LAS requires an address"));
 
 		cpu.a = res;
 		cpu.x = res;
@@ -275,8 +327,10 @@ impl<B: CpuBus> AddressOperation<B, AbsoluteY> for Las<AbsoluteY> {
 }
 
 impl<B: CpuBus, A: AddressMode<B>> Operation<B> for Lax<A> {
+	const MNEMONIC: &'static str = "LAX";
+
 	fn exec(cpu: &mut Cpu<B>, mem: &mut B, addr: Option<usize>) -> Option<usize> {
-		let op = CpuBus::read(mem, addr.expect("LAX requires an address"));
+		let op = cpu.read(mem, addr.expect("LAX requires an address"));
 
 		cpu.a = op;
 		cpu.x = op;
@@ -333,13 +387,15 @@ impl<B: CpuBus> AddressOperation<B, IndirectY> for Lax<IndirectY> {
 }
 
 impl<B: CpuBus, A: AddressMode<B>> Operation<B> for Lxa<A> {
+	const MNEMONIC: &'static str = "LXA";
+
 	fn exec(cpu: &mut Cpu<B>, mem: &mut B, addr: Option<usize>) -> Option<usize> {
 		// This operation is highly unstable, the constant below depends on temperature, series and
-		// maybe other factors of the chip. We set it to 0xFF here.
+		// maybe other factors of the chip. We use 0xEE, which most chips show.
 		// See https://www.masswerk.at/6502/6502_instruction_set.html#LXA
-		const CONSTANT: u8 = 0xFF;
+		const CONSTANT: u8 = 0xEE;
 
-		let mut op = CpuBus::read(mem, addr.expect("LXA requires an address"));
+		let mut op = cpu.read(mem, addr.expect("LXA requires an address"));
 		op = (cpu.a | CONSTANT) & op;
 
 		cpu.a = op;
@@ -359,15 +415,19 @@ impl<B: CpuBus> AddressOperation<B, Immediate> for Lxa<Immediate> {
 }
 
 impl<B: CpuBus, A: AddressMode<B>> Operation<B> for Rla<A> {
+	const MNEMONIC: &'static str = "RLA";
+	const ACCESS: Access = Access::ReadModifyWrite;
+
 	fn exec(cpu: &mut Cpu<B>, mem: &mut B, addr: Option<usize>) -> Option<usize> {
 		let address = addr.expect("RLA requires an address");
-		let mut op = CpuBus::read(mem, address);
+		let mut op = cpu.read(mem, address);
+		cpu.write(mem, address, op); // the unmodified value is written back first
 		let carry = (op & 0x80) > 0;
 
 		op <<= 1;
 		op |= cpu.is_carry() as u8;
 		cpu.a &= op;
-		CpuBus::write(mem, address, op);
+		cpu.write(mem, address, op);
 
 		cpu.set_carry(carry);
 		cpu.set_zero(cpu.a);
@@ -420,14 +480,18 @@ impl<B: CpuBus> AddressOperation<B, IndirectY> for Rla<IndirectY> {
 }
 
 impl<B: CpuBus, A: AddressMode<B>> Operation<B> for Rra<A> {
+	const MNEMONIC: &'static str = "RRA";
+	const ACCESS: Access = Access::ReadModifyWrite;
+
 	fn exec(cpu: &mut Cpu<B>, mem: &mut B, addr: Option<usize>) -> Option<usize> {
 		let address = addr.expect("RRA requires an address");
-		let mut m = CpuBus::read(mem, address);
+		let mut m = cpu.read(mem, address);
+		cpu.write(mem, address, m); // the unmodified value is written back first
 
 		let carry = m & 0x01;
 		m >>= 1;
 		m |= (cpu.is_carry() as u8) << 7;
-		CpuBus::write(mem, address, m);
+		cpu.write(mem, address, m);
 
 		let res16 = (cpu.a as u16) + (m as u16) + (carry as u16);
 		cpu.set_overflow(cpu.a, m, res16 as u8); // set the bit before the actual operation
@@ -484,8 +548,11 @@ impl<B: CpuBus> AddressOperation<B, IndirectY> for Rra<IndirectY> {
 }
 
 impl<B: CpuBus, A: AddressMode<B>> Operation<B> for Sax<A> {
+	const MNEMONIC: &'static str = "SAX";
+	const ACCESS: Access = Access::Write;
+
 	fn exec(cpu: &mut Cpu<B>, mem: &mut B, addr: Option<usize>) -> Option<usize> {
-		CpuBus::write(mem, addr.expect("SAX requires an address"), cpu.a & cpu.x);
+		cpu.write(mem, addr.expect("SAX requires an address"), cpu.a & cpu.x);
 
 		None
 	}
@@ -516,12 +583,16 @@ impl<B: CpuBus> AddressOperation<B, IndirectX> for Sax<IndirectX> {
 }
 
 impl<B: CpuBus, A: AddressMode<B>> Operation<B> for Sbx<A> {
+	const MNEMONIC: &'static str = "SBX";
+
 	fn exec(cpu: &mut Cpu<B>, mem: &mut B, addr: Option<usize>) -> Option<usize> {
-		let op = CpuBus::read(mem, addr.expect("SBX requires an address"));
+		let op = cpu.read(mem, addr.expect("SBX requires an address"));
 
-		let res = (cpu.a & cpu.x).wrapping_sub(op);
+		let ax = cpu.a & cpu.x;
+		let res = ax.wrapping_sub(op);
 
-		cpu.set_carry(res > cpu.x);
+		// like CMP, the carry is set if there is no borrow
+		cpu.set_carry(ax >= op);
 		cpu.set_zero(res);
 		cpu.set_negative(res);
 		cpu.x = res;
@@ -537,13 +608,15 @@ impl<B: CpuBus> AddressOperation<B, Immediate> for Sbx<Immediate> {
 }
 
 impl<B: CpuBus, A: AddressMode<B>> Operation<B> for Sha<A> {
+	const MNEMONIC: &'static str = "SHA";
+
 	// unstable
 
+	const ACCESS: Access = Access::Write;
+
 	fn exec(cpu: &mut Cpu<B>, mem: &mut B, addr: Option<usize>) -> Option<usize> {
 		let address = addr.expect("SHA requires an address");
-		let h = (((address + 1) >> 8) & 0xFF) as u8;
-
-		CpuBus::write(mem, address, cpu.a & cpu.x & h);
+		unstable_store(cpu, mem, address, cpu.y, cpu.a & cpu.x);
 
 		None
 	}
@@ -562,13 +635,15 @@ impl<B: CpuBus> AddressOperation<B, IndirectY> for Sha<IndirectY> {
 }
 
 impl<B: CpuBus, A: AddressMode<B>> Operation<B> for Shx<A> {
+	const MNEMONIC: &'static str = "SHX";
+
 	// unstable
 
+	const ACCESS: Access = Access::Write;
+
 	fn exec(cpu: &mut Cpu<B>, mem: &mut B, addr: Option<usize>) -> Option<usize> {
 		let address = addr.expect("SHX requires an address");
-		let h = (((address + 1) >> 8) & 0xFF) as u8;
-
-		CpuBus::write(mem, address, cpu.x & h);
+		unstable_store(cpu, mem, address, cpu.y, cpu.x);
 
 		None
 	}
@@ -581,13 +656,15 @@ impl<B: CpuBus> AddressOperation<B, AbsoluteY> for Shx<AbsoluteY> {
 }
 
 impl<B: CpuBus, A: AddressMode<B>> Operation<B> for Shy<A> {
+	const MNEMONIC: &'static str = "SHY";
+
 	// unstable
 
+	const ACCESS: Access = Access::Write;
+
 	fn exec(cpu: &mut Cpu<B>, mem: &mut B, addr: Option<usize>) -> Option<usize> {
 		let address = addr.expect("SHY requires an address");
-		let h = (((address + 1) >> 8) & 0xFF) as u8;
-
-		CpuBus::write(mem, address, cpu.y & h);
+		unstable_store(cpu, mem, address, cpu.x, cpu.y);
 
 		None
 	}
@@ -600,14 +677,18 @@ impl<B: CpuBus> AddressOperation<B, AbsoluteX> for Shy<AbsoluteX> {
 }
 
 impl<B: CpuBus, A: AddressMode<B>> Operation<B> for Slo<A> {
+	const MNEMONIC: &'static str = "SLO";
+	const ACCESS: Access = Access::ReadModifyWrite;
+
 	fn exec(cpu: &mut Cpu<B>, mem: &mut B, addr: Option<usize>) -> Option<usize> {
 		let address = addr.expect("SLO requires an address");
 
-		let mut m = CpuBus::read(mem, address);
+		let mut m = cpu.read(mem, address);
+		cpu.write(mem, address, m); // the unmodified value is written back first
 		cpu.set_carry((m & 0x80) > 0);
 		m <<= 1;
 		cpu.a |= m;
-		CpuBus::write(mem, address, m);
+		cpu.write(mem, address, m);
 
 		cpu.set_zero(cpu.a);
 		cpu.set_negative(cpu.a);
@@ -659,14 +740,18 @@ impl<B: CpuBus> AddressOperation<B, IndirectY> for Slo<IndirectY> {
 }
 
 impl<B: CpuBus, A: AddressMode<B>> Operation<B> for Sre<A> {
+	const MNEMONIC: &'static str = "SRE";
+	const ACCESS: Access = Access::ReadModifyWrite;
+
 	fn exec(cpu: &mut Cpu<B>, mem: &mut B, addr: Option<usize>) -> Option<usize> {
 		let address = addr.expect("SRE requires an address");
 
-		let mut m = CpuBus::read(mem, address);
+		let mut m = cpu.read(mem, address);
+		cpu.write(mem, address, m); // the unmodified value is written back first
 		cpu.set_carry((m & 0x01) > 0);
 		m >>= 1;
 		cpu.a ^= m;
-		CpuBus::write(mem, address, m);
+		cpu.write(mem, address, m);
 
 		cpu.set_zero(cpu.a);
 		cpu.set_negative(cpu.a);
@@ -718,14 +803,17 @@ impl<B: CpuBus> AddressOperation<B, IndirectY> for Sre<IndirectY> {
 }
 
 impl<B: CpuBus, A: AddressMode<B>> Operation<B> for Tas<A> {
+	const MNEMONIC: &'static str = "TAS";
+
 	// unstable
 
+	const ACCESS: Access = Access::Write;
+
 	fn exec(cpu: &mut Cpu<B>, mem: &mut B, addr: Option<usize>) -> Option<usize> {
 		let address = addr.expect("TAS requires an address");
-		let h = (((address + 1) >> 8) & 0xFF) as u8;
 
 		cpu.sp = cpu.a & cpu.x;
-		CpuBus::write(mem, address, cpu.a & cpu.x & h);
+		unstable_store(cpu, mem, address, cpu.y, cpu.sp);
 
 		None
 	}
@@ -1,12 +1,22 @@
 mod addressing;
+mod disasm;
 mod instructions;
+#[cfg(test)]
+mod nestest;
+pub mod opcodes;
+#[cfg(test)]
+mod single_step_tests;
+mod trace;
 
 use std::marker::PhantomData;
 
+use crate::mem::cdl::ReadKind;
 use crate::mem::CpuBus;
 
 use addressing::*;
+pub use disasm::disassemble;
 use instructions::*;
+use opcodes::Opcode;
 
 // interrupt-vector locations
 const IRQ_VEC: u16 = 0xFFFE;
@@ -24,19 +34,64 @@ const UNUSED_IDX: u8 = 5;
 const OVERFLOW_IDX: u8 = 6;
 const NEGATIVE_IDX: u8 = 7;
 
-#[derive(PartialEq)]
-pub enum InterruptSource {
+// Every op-code with its instruction and address mode, undocumented ones are marked. The list
+// generates the dispatch of exec_instruction() and the op-code table, so they can't diverge.
+macro_rules! instruction_set {
+	($($op:literal => $module:ident::$instr:ident<$mode:ident> $($flag:ident)?,)*) => {
+		fn exec_instruction(&mut self, mem: &mut B) -> usize {
+			let instr = self.fetch(mem, self.pc as usize);
+			self.pc += 1;
+
+			match instr {
+				$($op => $module::$instr::<$mode>::do_instruction(self, mem),)*
+			}
+		}
+
+		pub(crate) fn opcode_info(op: u8) -> Opcode {
+			match op {
+				$($op => Opcode {
+					mnemonic: <$module::$instr<$mode> as Operation<B>>::MNEMONIC,
+					mode: <$mode as AddressMode<B>>::MODE,
+					bytes: 1 + <$mode as AddressMode<B>>::operand_bytes(),
+					cycles: <$module::$instr<$mode> as AddressOperation<B, $mode>>::cycles(0, false),
+					undocumented: is_undocumented!($module $($flag)?),
+				},)*
+			}
+		}
+	};
+}
+
+macro_rules! is_undocumented {
+	(undocumented) => {
+		true
+	};
+	($module:ident undocumented) => {
+		true
+	};
+	($module:ident) => {
+		false
+	};
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum InterruptSource {
 	RESET,
 	NMI,
 	IRQ,
 	BRK,
-	NONE,
 }
 
+// NMI is edge-triggered and IRQ level-triggered. The lines are sampled at the end of every cycle,
+// but at the end of an instruction the values of the previous cycle are used, so the interrupts
+// are effectively polled on the second-to-last cycle.
 #[derive(Default)]
 pub struct Irq {
-	pending: bool,
-	src: InterruptSource,
+	reset: bool,        // reset requested
+	nmi_line: bool,     // level of the NMI line in the last cycle
+	nmi_pending: bool,  // rising edge on the NMI line which wasn't serviced yet
+	prev_nmi: bool,     // nmi_pending of the previous cycle
+	run_irq: bool,      // IRQ line asserted and not masked by the I flag
+	prev_run_irq: bool, // run_irq of the previous cycle
 }
 
 #[derive(Default)]
@@ -46,6 +101,32 @@ pub struct CpuStat {
 	instr_cnt: u64,
 }
 
+// change of the control flow by the last step, observed by the profiler
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum Flow {
+	#[default]
+	Sequential,
+	// JSR
+	Call,
+	// the handlers of the interrupts, BRK uses the one of IRQ
+	Nmi,
+	Irq,
+	// RTS and RTI
+	Return,
+	Reset,
+}
+
+// copy of the registers, e.g. for the debugger
+#[derive(Clone, Copy, Default)]
+pub struct Registers {
+	pub pc: u16,
+	pub sp: u8,
+	pub a: u8,
+	pub x: u8,
+	pub y: u8,
+	pub p: u8,
+}
+
 #[derive(Default)]
 pub struct Cpu<B: CpuBus> {
 	pc: u16, // program counter
@@ -55,19 +136,14 @@ pub struct Cpu<B: CpuBus> {
 	y: u8,   // index register y
 	p: u8,   // processor status
 
-	skip_cycles: usize, // cycles to skip from last opcode
-	irq: Irq,           // interrupt-infos
-	stat: CpuStat,      // some debug-info
+	irq: Irq,      // interrupt-infos
+	stat: CpuStat, // some debug-info
+	jammed: bool,  // halted by a JAM op-code until the next reset
+	flow: Flow,    // of the last step
 
 	_phantom: PhantomData<B>,
 }
 
-impl Default for InterruptSource {
-	fn default() -> Self {
-		InterruptSource::NONE
-	}
-}
-
 impl<B: CpuBus> Cpu<B> {
 	pub fn new() -> Self {
 		Self {
@@ -77,30 +153,60 @@ impl<B: CpuBus> Cpu<B> {
 			x: 0,
 			y: 0,
 			p: 0,
-			skip_cycles: 0,
 			irq: Default::default(),
 			stat: Default::default(),
+			jammed: false,
+			flow: Flow::Sequential,
 			_phantom: PhantomData,
 		}
 	}
 
-	pub fn assert_interrupt(&mut self, src: InterruptSource) {
-		self.irq.pending = true;
-		self.irq.src = src;
+	pub fn assert_reset(&mut self) {
+		self.irq.reset = true;
+	}
+
+	pub fn is_jammed(&self) -> bool {
+		self.jammed
 	}
 
-	// only for testing-reasons!!!
+	// starts at the address without the reset sequence, e.g. for the automation mode of nestest
 	pub fn set_pc(&mut self, addr: u16) {
+		self.reset();
 		self.pc = addr;
 		self.stat.cycle_cnt = 7;
 	}
 
-	pub fn log_cpu_stats(&self, mem: &mut B) -> String {
-		let op = CpuBus::read(mem, self.pc as usize);
-		String::from(format!(
-			"{:04X}  {:02X}  A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X} CYC:{}",
-			self.pc, op, self.a, self.x, self.y, self.p, self.sp, self.stat.cycle_cnt
-		))
+	pub fn cycles(&self) -> u64 {
+		self.stat.cycle_cnt
+	}
+
+	pub fn flow(&self) -> Flow {
+		self.flow
+	}
+
+	// false if the next step services an interrupt or the CPU is jammed
+	pub fn at_instruction(&self) -> bool {
+		!(self.irq.reset || self.jammed || self.irq.prev_nmi || self.irq.prev_run_irq)
+	}
+
+	pub fn registers(&self) -> Registers {
+		Registers {
+			pc: self.pc,
+			sp: self.sp,
+			a: self.a,
+			x: self.x,
+			y: self.y,
+			p: self.p,
+		}
+	}
+
+	pub fn set_registers(&mut self, regs: Registers) {
+		self.pc = regs.pc;
+		self.sp = regs.sp;
+		self.a = regs.a;
+		self.x = regs.x;
+		self.y = regs.y;
+		self.p = regs.p;
 	}
 
 	fn reset(&mut self) {
@@ -110,21 +216,58 @@ impl<B: CpuBus> Cpu<B> {
 		self.x = 0;
 		self.y = 0;
 		self.p = (1 << UNUSED_IDX) | (1 << INTERRUPT_IDX); // set interrupt-disable bit on startup
-		self.irq.pending = false;
-		self.irq.src = InterruptSource::NONE;
+		self.irq = Default::default();
+		self.jammed = false;
 		self.stat.interrupt_cnt = 0;
 		self.stat.cycle_cnt = 0;
 		self.stat.instr_cnt = 0;
 	}
 
-	fn read16(mem: &mut B, addr: usize) -> u16 {
-		let l = CpuBus::read(mem, addr) as u16;
-		let h = CpuBus::read(mem, addr + 1) as u16;
+	// every access of the bus is one cycle, the interrupt lines are sampled at the end of it
+	fn read(&mut self, mem: &mut B, addr: usize) -> u8 {
+		let val = CpuBus::read(mem, addr);
+		self.poll_interrupts(mem);
+		val
+	}
+
+	// reads an op-code or operand, the bus is told for the code/data logger
+	fn fetch(&mut self, mem: &mut B, addr: usize) -> u8 {
+		mem.set_read_kind(ReadKind::Code);
+		self.read(mem, addr)
+	}
+
+	// a read whose value isn't used
+	fn dummy_read(&mut self, mem: &mut B, addr: usize) {
+		mem.set_read_kind(ReadKind::Dummy);
+		self.read(mem, addr);
+	}
+
+	fn write(&mut self, mem: &mut B, addr: usize, val: u8) {
+		CpuBus::write(mem, addr, val);
+		self.poll_interrupts(mem);
+	}
+
+	fn poll_interrupts(&mut self, mem: &mut B) {
+		self.irq.prev_nmi = self.irq.nmi_pending;
+
+		let nmi_line = mem.nmi_line();
+		if nmi_line && !self.irq.nmi_line {
+			self.irq.nmi_pending = true;
+		}
+		self.irq.nmi_line = nmi_line;
+
+		self.irq.prev_run_irq = self.irq.run_irq;
+		self.irq.run_irq = mem.irq_line() && !self.get_statusbit(INTERRUPT_IDX);
+	}
+
+	fn read16(&mut self, mem: &mut B, addr: usize) -> u16 {
+		let l = self.read(mem, addr) as u16;
+		let h = self.read(mem, addr + 1) as u16;
 		(h << 8) | l
 	}
 
 	fn push8(&mut self, mem: &mut B, val: u8) {
-		CpuBus::write(mem, (self.sp as usize) + 0x100, val);
+		self.write(mem, (self.sp as usize) + 0x100, val);
 		self.sp = self.sp.wrapping_sub(1);
 	}
 
@@ -135,7 +278,7 @@ impl<B: CpuBus> Cpu<B> {
 
 	fn pop8(&mut self, mem: &mut B) -> u8 {
 		self.sp = self.sp.wrapping_add(1);
-		CpuBus::read(mem, (self.sp as usize) + 0x100)
+		self.read(mem, (self.sp as usize) + 0x100)
 	}
 
 	fn pop16(&mut self, mem: &mut B) -> u16 {
@@ -202,337 +345,345 @@ impl<B: CpuBus> Cpu<B> {
 		self.get_statusbit(CARRY_IDX)
 	}
 
-	fn interrupt(&mut self, mem: &mut B) -> bool {
-		if !self.irq.pending {
-			return false;
-		}
-		self.irq.pending = false;
+	fn interrupt(&mut self, mem: &mut B, src: InterruptSource) {
+		// the op-code fetch is replaced by two reads of pc which are ignored
+		self.dummy_read(mem, self.pc as usize);
+		self.dummy_read(mem, self.pc as usize);
 
-		match &self.irq.src {
-			InterruptSource::RESET => {
-				self.reset();
-				self.pc = Self::read16(mem, RESET_VEC as usize);
-			}
-			InterruptSource::NMI => {
-				self.push16(mem, self.pc);
-				let mut flags = self.p | (1 << UNUSED_IDX);
-				flags &= !(1 << BRK_IDX);
-				self.push8(mem, flags);
+		self.interrupt_sequence(mem, src);
+	}
 
-				self.pc = Self::read16(mem, NMI_VEC as usize);
-			}
-			InterruptSource::IRQ => {
-				self.push16(mem, self.pc);
-				let mut flags = self.p | (1 << UNUSED_IDX);
-				flags &= !(1 << BRK_IDX);
-				self.push8(mem, flags);
-
-				self.set_interrupt(true);
-				self.pc = Self::read16(mem, IRQ_VEC as usize);
+	// the last 5 cycles of an interrupt, shared with BRK
+	fn interrupt_sequence(&mut self, mem: &mut B, src: InterruptSource) {
+		if src == InterruptSource::RESET {
+			// the stack is accessed as for the other interrupts, but it's read instead of written
+			for _ in 0..3 {
+				self.read(mem, (self.sp as usize) + 0x100);
+				self.sp = self.sp.wrapping_sub(1);
 			}
-			InterruptSource::BRK => {
-				self.push16(mem, self.pc);
-				let flags = self.p | (1 << UNUSED_IDX) | (1 << BRK_IDX);
-				self.push8(mem, flags);
 
-				self.set_interrupt(true);
-				self.pc = Self::read16(mem, BRK_VEC as usize);
-			}
-			_ => {}
+			self.reset();
+			self.pc = self.read16(mem, RESET_VEC as usize);
+			self.flow = Flow::Reset;
+			return;
 		}
 
-		self.irq.src = InterruptSource::NONE;
-		true
-	}
+		self.push16(mem, self.pc);
 
-	pub fn dma_transaction_occurred(&mut self) {
-		self.skip_cycles += if (self.stat.cycle_cnt & 0x01) > 0 {
-			// if cycle count is odd, the CPU is stalled for 1 additional cycle
-			514
+		// an NMI which occurs until now hijacks IRQ and BRK, the B flag of BRK is still pushed
+		let vec = if self.irq.nmi_pending {
+			self.irq.nmi_pending = false;
+			self.flow = Flow::Nmi;
+			NMI_VEC
+		} else if src == InterruptSource::BRK {
+			self.flow = Flow::Irq;
+			BRK_VEC
 		} else {
-			513
+			self.flow = Flow::Irq;
+			IRQ_VEC
+		};
+
+		let mut flags = self.p | (1 << UNUSED_IDX);
+		if src == InterruptSource::BRK {
+			flags |= 1 << BRK_IDX;
+		} else {
+			flags &= !(1 << BRK_IDX);
 		}
+		self.push8(mem, flags);
+
+		self.set_interrupt(true);
+		self.pc = self.read16(mem, vec as usize);
+
+		// the sequence doesn't poll, so the first instruction of the handler is always executed
+		self.irq.prev_nmi = false;
 	}
 
-	// cpu execution
-	fn exec_instruction(&mut self, mem: &mut B) -> usize {
-		let instr = CpuBus::read(mem, self.pc as usize);
-		self.pc += 1;
-
-		match instr {
-			0x00 => system::Brk::<Implied>::do_instruction(self, mem),
-			0x01 => bitwise::Ora::<IndirectX>::do_instruction(self, mem),
-			0x03 => undocumented::Slo::<IndirectX>::do_instruction(self, mem),
-			0x04 => system::Nop::<Zeropage>::do_instruction(self, mem), // undocumented
-			0x05 => bitwise::Ora::<Zeropage>::do_instruction(self, mem),
-			0x06 => bitwise::Asl::<Zeropage>::do_instruction(self, mem),
-			0x07 => undocumented::Slo::<Zeropage>::do_instruction(self, mem),
-			0x08 => stack::Php::<Implied>::do_instruction(self, mem),
-			0x09 => bitwise::Ora::<Immediate>::do_instruction(self, mem),
-			0x0A => bitwise::AslA::<Accumulator>::do_instruction(self, mem),
-			0x0B => undocumented::Anc::<Immediate>::do_instruction(self, mem),
-			0x0C => system::Nop::<Absolute>::do_instruction(self, mem), // undocumented
-			0x0D => bitwise::Ora::<Absolute>::do_instruction(self, mem),
-			0x0E => bitwise::Asl::<Absolute>::do_instruction(self, mem),
-			0x0F => undocumented::Slo::<Absolute>::do_instruction(self, mem),
-			0x10 => branch::Bpl::<Relative>::do_instruction(self, mem),
-			0x11 => bitwise::Ora::<IndirectY>::do_instruction(self, mem),
-			0x13 => undocumented::Slo::<IndirectY>::do_instruction(self, mem),
-			0x14 => system::Nop::<ZeropageX>::do_instruction(self, mem), // undocumented
-			0x15 => bitwise::Ora::<ZeropageX>::do_instruction(self, mem),
-			0x16 => bitwise::Asl::<ZeropageX>::do_instruction(self, mem),
-			0x17 => undocumented::Slo::<ZeropageX>::do_instruction(self, mem), // undocumented
-			0x18 => registers::Clc::<Implied>::do_instruction(self, mem),
-			0x19 => bitwise::Ora::<AbsoluteY>::do_instruction(self, mem),
-			0x1A => system::Nop::<Implied>::do_instruction(self, mem), // undocumented
-			0x1B => undocumented::Slo::<AbsoluteY>::do_instruction(self, mem),
-			0x1C => system::Nop::<AbsoluteX>::do_instruction(self, mem), // undocumented
-			0x1D => bitwise::Ora::<AbsoluteX>::do_instruction(self, mem),
-			0x1E => bitwise::Asl::<AbsoluteX>::do_instruction(self, mem),
-			0x1F => undocumented::Slo::<AbsoluteX>::do_instruction(self, mem),
-			0x20 => jump::Jsr::<Absolute>::do_instruction(self, mem),
-			0x21 => bitwise::And::<IndirectX>::do_instruction(self, mem),
-			0x23 => undocumented::Rla::<IndirectX>::do_instruction(self, mem),
-			0x24 => bitwise::Bit::<Zeropage>::do_instruction(self, mem),
-			0x25 => bitwise::And::<Zeropage>::do_instruction(self, mem),
-			0x26 => bitwise::Rol::<Zeropage>::do_instruction(self, mem),
-			0x27 => undocumented::Rla::<Zeropage>::do_instruction(self, mem),
-			0x28 => stack::Plp::<Implied>::do_instruction(self, mem),
-			0x29 => bitwise::And::<Immediate>::do_instruction(self, mem),
-			0x2A => bitwise::RolA::<Accumulator>::do_instruction(self, mem),
-			0x2B => undocumented::Anc::<Immediate>::do_instruction(self, mem),
-			0x2C => bitwise::Bit::<Absolute>::do_instruction(self, mem),
-			0x2D => bitwise::And::<Absolute>::do_instruction(self, mem),
-			0x2E => bitwise::Rol::<Absolute>::do_instruction(self, mem),
-			0x2F => undocumented::Rla::<Absolute>::do_instruction(self, mem),
-			0x30 => branch::Bmi::<Relative>::do_instruction(self, mem),
-			0x31 => bitwise::And::<IndirectY>::do_instruction(self, mem),
-			0x33 => undocumented::Rla::<IndirectY>::do_instruction(self, mem),
-			0x34 => system::Nop::<ZeropageX>::do_instruction(self, mem), // undocumented
-			0x35 => bitwise::And::<ZeropageX>::do_instruction(self, mem),
-			0x36 => bitwise::Rol::<ZeropageX>::do_instruction(self, mem),
-			0x37 => undocumented::Rla::<ZeropageX>::do_instruction(self, mem),
-			0x38 => registers::Sec::<Implied>::do_instruction(self, mem),
-			0x39 => bitwise::And::<AbsoluteY>::do_instruction(self, mem),
-			0x3A => system::Nop::<Implied>::do_instruction(self, mem), // undocumented
-			0x3C => system::Nop::<AbsoluteX>::do_instruction(self, mem), // undocumented
-			0x3B => undocumented::Rla::<AbsoluteY>::do_instruction(self, mem),
-			0x3D => bitwise::And::<AbsoluteX>::do_instruction(self, mem),
-			0x3E => bitwise::Rol::<AbsoluteX>::do_instruction(self, mem),
-			0x3F => undocumented::Rla::<AbsoluteX>::do_instruction(self, mem),
-			0x40 => jump::Rti::<Implied>::do_instruction(self, mem),
-			0x41 => bitwise::Eor::<IndirectX>::do_instruction(self, mem),
-			0x43 => undocumented::Sre::<IndirectX>::do_instruction(self, mem),
-			0x44 => system::Nop::<Zeropage>::do_instruction(self, mem), // undocumented
-			0x45 => bitwise::Eor::<Zeropage>::do_instruction(self, mem),
-			0x46 => bitwise::Lsr::<Zeropage>::do_instruction(self, mem),
-			0x47 => undocumented::Sre::<Zeropage>::do_instruction(self, mem),
-			0x48 => stack::Pha::<Implied>::do_instruction(self, mem),
-			0x49 => bitwise::Eor::<Immediate>::do_instruction(self, mem),
-			0x4A => bitwise::LsrA::<Accumulator>::do_instruction(self, mem),
-			0x4B => undocumented::Alr::<Immediate>::do_instruction(self, mem),
-			0x4C => jump::Jmp::<Absolute>::do_instruction(self, mem),
-			0x4D => bitwise::Eor::<Absolute>::do_instruction(self, mem),
-			0x4E => bitwise::Lsr::<Absolute>::do_instruction(self, mem),
-			0x4F => undocumented::Sre::<Absolute>::do_instruction(self, mem),
-			0x50 => branch::Bvc::<Relative>::do_instruction(self, mem),
-			0x51 => bitwise::Eor::<IndirectY>::do_instruction(self, mem),
-			0x53 => undocumented::Sre::<IndirectY>::do_instruction(self, mem),
-			0x54 => system::Nop::<ZeropageX>::do_instruction(self, mem), // undocumented
-			0x55 => bitwise::Eor::<ZeropageX>::do_instruction(self, mem),
-			0x56 => bitwise::Lsr::<ZeropageX>::do_instruction(self, mem),
-			0x57 => undocumented::Sre::<ZeropageX>::do_instruction(self, mem),
-			0x58 => registers::Cli::<Implied>::do_instruction(self, mem),
-			0x59 => bitwise::Eor::<AbsoluteY>::do_instruction(self, mem),
-			0x5A => system::Nop::<Implied>::do_instruction(self, mem), // undocumented
-			0x5B => undocumented::Sre::<AbsoluteY>::do_instruction(self, mem),
-			0x5C => system::Nop::<AbsoluteX>::do_instruction(self, mem), // undocumented
-			0x5D => bitwise::Eor::<AbsoluteX>::do_instruction(self, mem),
-			0x5E => bitwise::Lsr::<AbsoluteX>::do_instruction(self, mem),
-			0x5F => undocumented::Sre::<AbsoluteX>::do_instruction(self, mem),
-			0x60 => jump::Rts::<Implied>::do_instruction(self, mem),
-			0x61 => math::Adc::<IndirectX>::do_instruction(self, mem),
-			0x63 => undocumented::Rra::<IndirectX>::do_instruction(self, mem),
-			0x64 => system::Nop::<Zeropage>::do_instruction(self, mem), // undocumented
-			0x65 => math::Adc::<Zeropage>::do_instruction(self, mem),
-			0x66 => bitwise::Ror::<Zeropage>::do_instruction(self, mem),
-			0x67 => undocumented::Rra::<Zeropage>::do_instruction(self, mem),
-			0x68 => stack::Pla::<Implied>::do_instruction(self, mem),
-			0x69 => math::Adc::<Immediate>::do_instruction(self, mem),
-			0x6A => bitwise::RorA::<Accumulator>::do_instruction(self, mem),
-			0x6B => undocumented::Arr::<Immediate>::do_instruction(self, mem),
-			0x6C => jump::Jmp::<Indirect>::do_instruction(self, mem),
-			0x6D => math::Adc::<Absolute>::do_instruction(self, mem),
-			0x6E => bitwise::Ror::<Absolute>::do_instruction(self, mem),
-			0x6F => undocumented::Rra::<Absolute>::do_instruction(self, mem),
-			0x70 => branch::Bvs::<Relative>::do_instruction(self, mem),
-			0x71 => math::Adc::<IndirectY>::do_instruction(self, mem),
-			0x73 => undocumented::Rra::<IndirectY>::do_instruction(self, mem),
-			0x74 => system::Nop::<ZeropageX>::do_instruction(self, mem), // undocumented
-			0x75 => math::Adc::<ZeropageX>::do_instruction(self, mem),
-			0x76 => bitwise::Ror::<ZeropageX>::do_instruction(self, mem),
-			0x77 => undocumented::Rra::<ZeropageX>::do_instruction(self, mem),
-			0x78 => registers::Sei::<Implied>::do_instruction(self, mem),
-			0x79 => math::Adc::<AbsoluteY>::do_instruction(self, mem),
-			0x7A => system::Nop::<Implied>::do_instruction(self, mem), // undocumented
-			0x7B => undocumented::Rra::<AbsoluteY>::do_instruction(self, mem),
-			0x7C => system::Nop::<AbsoluteX>::do_instruction(self, mem), // undocumented
-			0x7D => math::Adc::<AbsoluteX>::do_instruction(self, mem),
-			0x7E => bitwise::Ror::<AbsoluteX>::do_instruction(self, mem),
-			0x7F => undocumented::Rra::<AbsoluteX>::do_instruction(self, mem),
-			0x80 => system::Nop::<Immediate>::do_instruction(self, mem), // undocumented
-			0x81 => storage::Sta::<IndirectX>::do_instruction(self, mem),
-			0x82 => system::Nop::<Immediate>::do_instruction(self, mem), // undocumented
-			0x83 => undocumented::Sax::<IndirectX>::do_instruction(self, mem),
-			0x84 => storage::Sty::<Zeropage>::do_instruction(self, mem),
-			0x85 => storage::Sta::<Zeropage>::do_instruction(self, mem),
-			0x86 => storage::Stx::<Zeropage>::do_instruction(self, mem),
-			0x87 => undocumented::Sax::<Zeropage>::do_instruction(self, mem),
-			0x88 => math::Dey::<Implied>::do_instruction(self, mem),
-			0x89 => system::Nop::<Immediate>::do_instruction(self, mem), // undocumented
-			0x8A => storage::Txa::<Implied>::do_instruction(self, mem),
-			0x8B => undocumented::Ane::<Immediate>::do_instruction(self, mem),
-			0x8C => storage::Sty::<Absolute>::do_instruction(self, mem),
-			0x8D => storage::Sta::<Absolute>::do_instruction(self, mem),
-			0x8E => storage::Stx::<Absolute>::do_instruction(self, mem),
-			0x8F => undocumented::Sax::<Absolute>::do_instruction(self, mem),
-			0x90 => branch::Bcc::<Relative>::do_instruction(self, mem),
-			0x91 => storage::Sta::<IndirectY>::do_instruction(self, mem),
-			0x93 => undocumented::Sha::<IndirectY>::do_instruction(self, mem),
-			0x94 => storage::Sty::<ZeropageX>::do_instruction(self, mem),
-			0x95 => storage::Sta::<ZeropageX>::do_instruction(self, mem),
-			0x96 => storage::Stx::<ZeropageY>::do_instruction(self, mem),
-			0x97 => undocumented::Sax::<ZeropageY>::do_instruction(self, mem),
-			0x98 => storage::Tya::<Implied>::do_instruction(self, mem),
-			0x99 => storage::Sta::<AbsoluteY>::do_instruction(self, mem),
-			0x9A => storage::Txs::<Implied>::do_instruction(self, mem),
-			0x9B => undocumented::Tas::<AbsoluteY>::do_instruction(self, mem),
-			0x9C => undocumented::Shy::<AbsoluteX>::do_instruction(self, mem),
-			0x9D => storage::Sta::<AbsoluteX>::do_instruction(self, mem),
-			0x9E => undocumented::Shx::<AbsoluteY>::do_instruction(self, mem),
-			0x9F => undocumented::Sha::<AbsoluteY>::do_instruction(self, mem),
-			0xA0 => storage::Ldy::<Immediate>::do_instruction(self, mem),
-			0xA1 => storage::Lda::<IndirectX>::do_instruction(self, mem),
-			0xA2 => storage::Ldx::<Immediate>::do_instruction(self, mem),
-			0xA3 => undocumented::Lax::<IndirectX>::do_instruction(self, mem),
-			0xA4 => storage::Ldy::<Zeropage>::do_instruction(self, mem),
-			0xA5 => storage::Lda::<Zeropage>::do_instruction(self, mem),
-			0xA6 => storage::Ldx::<Zeropage>::do_instruction(self, mem),
-			0xA7 => undocumented::Lax::<Zeropage>::do_instruction(self, mem),
-			0xA8 => storage::Tay::<Implied>::do_instruction(self, mem),
-			0xA9 => storage::Lda::<Immediate>::do_instruction(self, mem),
-			0xAA => storage::Tax::<Implied>::do_instruction(self, mem),
-			0xAB => undocumented::Lxa::<Immediate>::do_instruction(self, mem),
-			0xAC => storage::Ldy::<Absolute>::do_instruction(self, mem),
-			0xAD => storage::Lda::<Absolute>::do_instruction(self, mem),
-			0xAE => storage::Ldx::<Absolute>::do_instruction(self, mem),
-			0xAF => undocumented::Lax::<Absolute>::do_instruction(self, mem),
-			0xB0 => branch::Bcs::<Relative>::do_instruction(self, mem),
-			0xB1 => storage::Lda::<IndirectY>::do_instruction(self, mem),
-			0xB3 => undocumented::Lax::<IndirectY>::do_instruction(self, mem),
-			0xB4 => storage::Ldy::<ZeropageX>::do_instruction(self, mem),
-			0xB5 => storage::Lda::<ZeropageX>::do_instruction(self, mem),
-			0xB6 => storage::Ldx::<ZeropageY>::do_instruction(self, mem),
-			0xB7 => undocumented::Lax::<ZeropageY>::do_instruction(self, mem),
-			0xB8 => registers::Clv::<Implied>::do_instruction(self, mem),
-			0xB9 => storage::Lda::<AbsoluteY>::do_instruction(self, mem),
-			0xBA => storage::Tsx::<Implied>::do_instruction(self, mem),
-			0xBC => storage::Ldy::<AbsoluteX>::do_instruction(self, mem),
-			0xBB => undocumented::Las::<AbsoluteY>::do_instruction(self, mem),
-			0xBD => storage::Lda::<AbsoluteX>::do_instruction(self, mem),
-			0xBE => storage::Ldx::<AbsoluteY>::do_instruction(self, mem),
-			0xBF => undocumented::Lax::<AbsoluteY>::do_instruction(self, mem),
-			0xC0 => registers::Cpy::<Immediate>::do_instruction(self, mem),
-			0xC1 => registers::Cmp::<IndirectX>::do_instruction(self, mem),
-			0xC2 => system::Nop::<Immediate>::do_instruction(self, mem), // undocumented
-			0xC3 => undocumented::Dcp::<IndirectX>::do_instruction(self, mem),
-			0xC4 => registers::Cpy::<Zeropage>::do_instruction(self, mem),
-			0xC5 => registers::Cmp::<Zeropage>::do_instruction(self, mem),
-			0xC6 => math::Dec::<Zeropage>::do_instruction(self, mem),
-			0xC7 => undocumented::Dcp::<Zeropage>::do_instruction(self, mem),
-			0xC8 => math::Iny::<Implied>::do_instruction(self, mem),
-			0xC9 => registers::Cmp::<Immediate>::do_instruction(self, mem),
-			0xCA => math::Dex::<Implied>::do_instruction(self, mem),
-			0xCB => undocumented::Sbx::<Immediate>::do_instruction(self, mem),
-			0xCC => registers::Cpy::<Absolute>::do_instruction(self, mem),
-			0xCD => registers::Cmp::<Absolute>::do_instruction(self, mem),
-			0xCE => math::Dec::<Absolute>::do_instruction(self, mem),
-			0xCF => undocumented::Dcp::<Absolute>::do_instruction(self, mem),
-			0xD0 => branch::Bne::<Relative>::do_instruction(self, mem),
-			0xD1 => registers::Cmp::<IndirectY>::do_instruction(self, mem),
-			0xD3 => undocumented::Dcp::<IndirectY>::do_instruction(self, mem),
-			0xD4 => system::Nop::<ZeropageX>::do_instruction(self, mem), // undocumented
-			0xD5 => registers::Cmp::<ZeropageX>::do_instruction(self, mem),
-			0xD6 => math::Dec::<ZeropageX>::do_instruction(self, mem),
-			0xD7 => undocumented::Dcp::<ZeropageX>::do_instruction(self, mem),
-			0xD8 => registers::Cld::<Implied>::do_instruction(self, mem),
-			0xD9 => registers::Cmp::<AbsoluteY>::do_instruction(self, mem),
-			0xDA => system::Nop::<Implied>::do_instruction(self, mem), // undocumented
-			0xDB => undocumented::Dcp::<AbsoluteY>::do_instruction(self, mem),
-			0xDC => system::Nop::<AbsoluteX>::do_instruction(self, mem), // undocumented
-			0xDD => registers::Cmp::<AbsoluteX>::do_instruction(self, mem),
-			0xDE => math::Dec::<AbsoluteX>::do_instruction(self, mem),
-			0xDF => undocumented::Dcp::<AbsoluteX>::do_instruction(self, mem),
-			0xE0 => registers::Cpx::<Immediate>::do_instruction(self, mem),
-			0xE1 => math::Sbc::<IndirectX>::do_instruction(self, mem),
-			0xE2 => system::Nop::<Immediate>::do_instruction(self, mem), // undocumented
-			0xE3 => undocumented::Isc::<IndirectX>::do_instruction(self, mem),
-			0xE4 => registers::Cpx::<Zeropage>::do_instruction(self, mem),
-			0xE5 => math::Sbc::<Zeropage>::do_instruction(self, mem),
-			0xE6 => math::Inc::<Zeropage>::do_instruction(self, mem),
-			0xE7 => undocumented::Isc::<Zeropage>::do_instruction(self, mem),
-			0xE8 => math::Inx::<Implied>::do_instruction(self, mem),
-			0xE9 => math::Sbc::<Immediate>::do_instruction(self, mem),
-			0xEA => system::Nop::<Implied>::do_instruction(self, mem),
-			0xEB => math::Sbc::<Immediate>::do_instruction(self, mem), // undocumented
-			0xEC => registers::Cpx::<Absolute>::do_instruction(self, mem),
-			0xED => math::Sbc::<Absolute>::do_instruction(self, mem),
-			0xEE => math::Inc::<Absolute>::do_instruction(self, mem),
-			0xEF => undocumented::Isc::<Absolute>::do_instruction(self, mem),
-			0xF0 => branch::Beq::<Relative>::do_instruction(self, mem),
-			0xF1 => math::Sbc::<IndirectY>::do_instruction(self, mem),
-			0xF3 => undocumented::Isc::<IndirectY>::do_instruction(self, mem),
-			0xF4 => system::Nop::<ZeropageX>::do_instruction(self, mem), // undocumented
-			0xF5 => math::Sbc::<ZeropageX>::do_instruction(self, mem),
-			0xF6 => math::Inc::<ZeropageX>::do_instruction(self, mem),
-			0xF7 => undocumented::Isc::<ZeropageX>::do_instruction(self, mem),
-			0xF8 => registers::Sed::<Implied>::do_instruction(self, mem),
-			0xF9 => math::Sbc::<AbsoluteY>::do_instruction(self, mem),
-			0xFA => system::Nop::<Implied>::do_instruction(self, mem), // undocumented
-			0xFB => undocumented::Isc::<AbsoluteY>::do_instruction(self, mem),
-			0xFC => system::Nop::<AbsoluteX>::do_instruction(self, mem), // undocumented
-			0xFD => math::Sbc::<AbsoluteX>::do_instruction(self, mem),
-			0xFE => math::Inc::<AbsoluteX>::do_instruction(self, mem),
-			0xFF => undocumented::Isc::<AbsoluteX>::do_instruction(self, mem),
-			_ => {
-				println!("reset op-code: 0x{:x}", instr);
-				self.reset();
-				0
-			}
-		}
+	instruction_set! {
+		0x00 => system::Brk<Implied>,
+		0x01 => bitwise::Ora<IndirectX>,
+		0x02 => undocumented::Jam<Implied>,
+		0x03 => undocumented::Slo<IndirectX>,
+		0x04 => system::Nop<Zeropage> undocumented,
+		0x05 => bitwise::Ora<Zeropage>,
+		0x06 => bitwise::Asl<Zeropage>,
+		0x07 => undocumented::Slo<Zeropage>,
+		0x08 => stack::Php<Implied>,
+		0x09 => bitwise::Ora<Immediate>,
+		0x0A => bitwise::AslA<Accumulator>,
+		0x0B => undocumented::Anc<Immediate>,
+		0x0C => system::Nop<Absolute> undocumented,
+		0x0D => bitwise::Ora<Absolute>,
+		0x0E => bitwise::Asl<Absolute>,
+		0x0F => undocumented::Slo<Absolute>,
+		0x10 => branch::Bpl<Relative>,
+		0x11 => bitwise::Ora<IndirectY>,
+		0x12 => undocumented::Jam<Implied>,
+		0x13 => undocumented::Slo<IndirectY>,
+		0x14 => system::Nop<ZeropageX> undocumented,
+		0x15 => bitwise::Ora<ZeropageX>,
+		0x16 => bitwise::Asl<ZeropageX>,
+		0x17 => undocumented::Slo<ZeropageX>,
+		0x18 => registers::Clc<Implied>,
+		0x19 => bitwise::Ora<AbsoluteY>,
+		0x1A => system::Nop<Implied> undocumented,
+		0x1B => undocumented::Slo<AbsoluteY>,
+		0x1C => system::Nop<AbsoluteX> undocumented,
+		0x1D => bitwise::Ora<AbsoluteX>,
+		0x1E => bitwise::Asl<AbsoluteX>,
+		0x1F => undocumented::Slo<AbsoluteX>,
+		0x20 => jump::Jsr<Absolute>,
+		0x21 => bitwise::And<IndirectX>,
+		0x22 => undocumented::Jam<Implied>,
+		0x23 => undocumented::Rla<IndirectX>,
+		0x24 => bitwise::Bit<Zeropage>,
+		0x25 => bitwise::And<Zeropage>,
+		0x26 => bitwise::Rol<Zeropage>,
+		0x27 => undocumented::Rla<Zeropage>,
+		0x28 => stack::Plp<Implied>,
+		0x29 => bitwise::And<Immediate>,
+		0x2A => bitwise::RolA<Accumulator>,
+		0x2B => undocumented::Anc<Immediate>,
+		0x2C => bitwise::Bit<Absolute>,
+		0x2D => bitwise::And<Absolute>,
+		0x2E => bitwise::Rol<Absolute>,
+		0x2F => undocumented::Rla<Absolute>,
+		0x30 => branch::Bmi<Relative>,
+		0x31 => bitwise::And<IndirectY>,
+		0x32 => undocumented::Jam<Implied>,
+		0x33 => undocumented::Rla<IndirectY>,
+		0x34 => system::Nop<ZeropageX> undocumented,
+		0x35 => bitwise::And<ZeropageX>,
+		0x36 => bitwise::Rol<ZeropageX>,
+		0x37 => undocumented::Rla<ZeropageX>,
+		0x38 => registers::Sec<Implied>,
+		0x39 => bitwise::And<AbsoluteY>,
+		0x3A => system::Nop<Implied> undocumented,
+		0x3B => undocumented::Rla<AbsoluteY>,
+		0x3C => system::Nop<AbsoluteX> undocumented,
+		0x3D => bitwise::And<AbsoluteX>,
+		0x3E => bitwise::Rol<AbsoluteX>,
+		0x3F => undocumented::Rla<AbsoluteX>,
+		0x40 => jump::Rti<Implied>,
+		0x41 => bitwise::Eor<IndirectX>,
+		0x42 => undocumented::Jam<Implied>,
+		0x43 => undocumented::Sre<IndirectX>,
+		0x44 => system::Nop<Zeropage> undocumented,
+		0x45 => bitwise::Eor<Zeropage>,
+		0x46 => bitwise::Lsr<Zeropage>,
+		0x47 => undocumented::Sre<Zeropage>,
+		0x48 => stack::Pha<Implied>,
+		0x49 => bitwise::Eor<Immediate>,
+		0x4A => bitwise::LsrA<Accumulator>,
+		0x4B => undocumented::Alr<Immediate>,
+		0x4C => jump::Jmp<Absolute>,
+		0x4D => bitwise::Eor<Absolute>,
+		0x4E => bitwise::Lsr<Absolute>,
+		0x4F => undocumented::Sre<Absolute>,
+		0x50 => branch::Bvc<Relative>,
+		0x51 => bitwise::Eor<IndirectY>,
+		0x52 => undocumented::Jam<Implied>,
+		0x53 => undocumented::Sre<IndirectY>,
+		0x54 => system::Nop<ZeropageX> undocumented,
+		0x55 => bitwise::Eor<ZeropageX>,
+		0x56 => bitwise::Lsr<ZeropageX>,
+		0x57 => undocumented::Sre<ZeropageX>,
+		0x58 => registers::Cli<Implied>,
+		0x59 => bitwise::Eor<AbsoluteY>,
+		0x5A => system::Nop<Implied> undocumented,
+		0x5B => undocumented::Sre<AbsoluteY>,
+		0x5C => system::Nop<AbsoluteX> undocumented,
+		0x5D => bitwise::Eor<AbsoluteX>,
+		0x5E => bitwise::Lsr<AbsoluteX>,
+		0x5F => undocumented::Sre<AbsoluteX>,
+		0x60 => jump::Rts<Implied>,
+		0x61 => math::Adc<IndirectX>,
+		0x62 => undocumented::Jam<Implied>,
+		0x63 => undocumented::Rra<IndirectX>,
+		0x64 => system::Nop<Zeropage> undocumented,
+		0x65 => math::Adc<Zeropage>,
+		0x66 => bitwise::Ror<Zeropage>,
+		0x67 => undocumented::Rra<Zeropage>,
+		0x68 => stack::Pla<Implied>,
+		0x69 => math::Adc<Immediate>,
+		0x6A => bitwise::RorA<Accumulator>,
+		0x6B => undocumented::Arr<Immediate>,
+		0x6C => jump::Jmp<Indirect>,
+		0x6D => math::Adc<Absolute>,
+		0x6E => bitwise::Ror<Absolute>,
+		0x6F => undocumented::Rra<Absolute>,
+		0x70 => branch::Bvs<Relative>,
+		0x71 => math::Adc<IndirectY>,
+		0x72 => undocumented::Jam<Implied>,
+		0x73 => undocumented::Rra<IndirectY>,
+		0x74 => system::Nop<ZeropageX> undocumented,
+		0x75 => math::Adc<ZeropageX>,
+		0x76 => bitwise::Ror<ZeropageX>,
+		0x77 => undocumented::Rra<ZeropageX>,
+		0x78 => registers::Sei<Implied>,
+		0x79 => math::Adc<AbsoluteY>,
+		0x7A => system::Nop<Implied> undocumented,
+		0x7B => undocumented::Rra<AbsoluteY>,
+		0x7C => system::Nop<AbsoluteX> undocumented,
+		0x7D => math::Adc<AbsoluteX>,
+		0x7E => bitwise::Ror<AbsoluteX>,
+		0x7F => undocumented::Rra<AbsoluteX>,
+		0x80 => system::Nop<Immediate> undocumented,
+		0x81 => storage::Sta<IndirectX>,
+		0x82 => system::Nop<Immediate> undocumented,
+		0x83 => undocumented::Sax<IndirectX>,
+		0x84 => storage::Sty<Zeropage>,
+		0x85 => storage::Sta<Zeropage>,
+		0x86 => storage::Stx<Zeropage>,
+		0x87 => undocumented::Sax<Zeropage>,
+		0x88 => math::Dey<Implied>,
+		0x89 => system::Nop<Immediate> undocumented,
+		0x8A => storage::Txa<Implied>,
+		0x8B => undocumented::Ane<Immediate>,
+		0x8C => storage::Sty<Absolute>,
+		0x8D => storage::Sta<Absolute>,
+		0x8E => storage::Stx<Absolute>,
+		0x8F => undocumented::Sax<Absolute>,
+		0x90 => branch::Bcc<Relative>,
+		0x91 => storage::Sta<IndirectY>,
+		0x92 => undocumented::Jam<Implied>,
+		0x93 => undocumented::Sha<IndirectY>,
+		0x94 => storage::Sty<ZeropageX>,
+		0x95 => storage::Sta<ZeropageX>,
+		0x96 => storage::Stx<ZeropageY>,
+		0x97 => undocumented::Sax<ZeropageY>,
+		0x98 => storage::Tya<Implied>,
+		0x99 => storage::Sta<AbsoluteY>,
+		0x9A => storage::Txs<Implied>,
+		0x9B => undocumented::Tas<AbsoluteY>,
+		0x9C => undocumented::Shy<AbsoluteX>,
+		0x9D => storage::Sta<AbsoluteX>,
+		0x9E => undocumented::Shx<AbsoluteY>,
+		0x9F => undocumented::Sha<AbsoluteY>,
+		0xA0 => storage::Ldy<Immediate>,
+		0xA1 => storage::Lda<IndirectX>,
+		0xA2 => storage::Ldx<Immediate>,
+		0xA3 => undocumented::Lax<IndirectX>,
+		0xA4 => storage::Ldy<Zeropage>,
+		0xA5 => storage::Lda<Zeropage>,
+		0xA6 => storage::Ldx<Zeropage>,
+		0xA7 => undocumented::Lax<Zeropage>,
+		0xA8 => storage::Tay<Implied>,
+		0xA9 => storage::Lda<Immediate>,
+		0xAA => storage::Tax<Implied>,
+		0xAB => undocumented::Lxa<Immediate>,
+		0xAC => storage::Ldy<Absolute>,
+		0xAD => storage::Lda<Absolute>,
+		0xAE => storage::Ldx<Absolute>,
+		0xAF => undocumented::Lax<Absolute>,
+		0xB0 => branch::Bcs<Relative>,
+		0xB1 => storage::Lda<IndirectY>,
+		0xB2 => undocumented::Jam<Implied>,
+		0xB3 => undocumented::Lax<IndirectY>,
+		0xB4 => storage::Ldy<ZeropageX>,
+		0xB5 => storage::Lda<ZeropageX>,
+		0xB6 => storage::Ldx<ZeropageY>,
+		0xB7 => undocumented::Lax<ZeropageY>,
+		0xB8 => registers::Clv<Implied>,
+		0xB9 => storage::Lda<AbsoluteY>,
+		0xBA => storage::Tsx<Implied>,
+		0xBB => undocumented::Las<AbsoluteY>,
+		0xBC => storage::Ldy<AbsoluteX>,
+		0xBD => storage::Lda<AbsoluteX>,
+		0xBE => storage::Ldx<AbsoluteY>,
+		0xBF => undocumented::Lax<AbsoluteY>,
+		0xC0 => registers::Cpy<Immediate>,
+		0xC1 => registers::Cmp<IndirectX>,
+		0xC2 => system::Nop<Immediate> undocumented,
+		0xC3 => undocumented::Dcp<IndirectX>,
+		0xC4 => registers::Cpy<Zeropage>,
+		0xC5 => registers::Cmp<Zeropage>,
+		0xC6 => math::Dec<Zeropage>,
+		0xC7 => undocumented::Dcp<Zeropage>,
+		0xC8 => math::Iny<Implied>,
+		0xC9 => registers::Cmp<Immediate>,
+		0xCA => math::Dex<Implied>,
+		0xCB => undocumented::Sbx<Immediate>,
+		0xCC => registers::Cpy<Absolute>,
+		0xCD => registers::Cmp<Absolute>,
+		0xCE => math::Dec<Absolute>,
+		0xCF => undocumented::Dcp<Absolute>,
+		0xD0 => branch::Bne<Relative>,
+		0xD1 => registers::Cmp<IndirectY>,
+		0xD2 => undocumented::Jam<Implied>,
+		0xD3 => undocumented::Dcp<IndirectY>,
+		0xD4 => system::Nop<ZeropageX> undocumented,
+		0xD5 => registers::Cmp<ZeropageX>,
+		0xD6 => math::Dec<ZeropageX>,
+		0xD7 => undocumented::Dcp<ZeropageX>,
+		0xD8 => registers::Cld<Implied>,
+		0xD9 => registers::Cmp<AbsoluteY>,
+		0xDA => system::Nop<Implied> undocumented,
+		0xDB => undocumented::Dcp<AbsoluteY>,
+		0xDC => system::Nop<AbsoluteX> undocumented,
+		0xDD => registers::Cmp<AbsoluteX>,
+		0xDE => math::Dec<AbsoluteX>,
+		0xDF => undocumented::Dcp<AbsoluteX>,
+		0xE0 => registers::Cpx<Immediate>,
+		0xE1 => math::Sbc<IndirectX>,
+		0xE2 => system::Nop<Immediate> undocumented,
+		0xE3 => undocumented::Isc<IndirectX>,
+		0xE4 => registers::Cpx<Zeropage>,
+		0xE5 => math::Sbc<Zeropage>,
+		0xE6 => math::Inc<Zeropage>,
+		0xE7 => undocumented::Isc<Zeropage>,
+		0xE8 => math::Inx<Implied>,
+		0xE9 => math::Sbc<Immediate>,
+		0xEA => system::Nop<Implied>,
+		0xEB => math::Sbc<Immediate> undocumented,
+		0xEC => registers::Cpx<Absolute>,
+		0xED => math::Sbc<Absolute>,
+		0xEE => math::Inc<Absolute>,
+		0xEF => undocumented::Isc<Absolute>,
+		0xF0 => branch::Beq<Relative>,
+		0xF1 => math::Sbc<IndirectY>,
+		0xF2 => undocumented::Jam<Implied>,
+		0xF3 => undocumented::Isc<IndirectY>,
+		0xF4 => system::Nop<ZeropageX> undocumented,
+		0xF5 => math::Sbc<ZeropageX>,
+		0xF6 => math::Inc<ZeropageX>,
+		0xF7 => undocumented::Isc<ZeropageX>,
+		0xF8 => registers::Sed<Implied>,
+		0xF9 => math::Sbc<AbsoluteY>,
+		0xFA => system::Nop<Implied> undocumented,
+		0xFB => undocumented::Isc<AbsoluteY>,
+		0xFC => system::Nop<AbsoluteX> undocumented,
+		0xFD => math::Sbc<AbsoluteX>,
+		0xFE => math::Inc<AbsoluteX>,
+		0xFF => undocumented::Isc<AbsoluteX>,
 	}
 
+	// Executes a whole instruction or interrupt. Every access of the bus takes one cycle, so the
+	// bus is responsible for clocking the rest of the system before each access.
 	pub fn step(&mut self, mem: &mut B) {
-		self.stat.cycle_cnt += 1;
-
-		// TODO:
-		// For now we execute at the 1st cycle and skip the remaining ones. In order to do it
-		// correct, we probably have to do it vice-versa -> skip first cycles and execute at the
-		// last ones.
-		if self.skip_cycles > 0 {
-			self.skip_cycles -= 1;
-			return;
-		}
-
-		let cycles = if self.interrupt(mem) {
+		self.flow = Flow::Sequential;
+		let cycles = if self.irq.reset {
+			self.irq.reset = false;
+			self.interrupt(mem, InterruptSource::RESET);
+			self.stat.interrupt_cnt += 1;
+			7
+		} else if self.jammed {
+			// the bus keeps being read but interrupts aren't serviced anymore
+			self.dummy_read(mem, 0xFFFF);
+			1
+		} else if self.irq.prev_nmi || self.irq.prev_run_irq {
+			let src = if self.irq.prev_nmi {
+				InterruptSource::NMI
+			} else {
+				InterruptSource::IRQ
+			};
+			self.interrupt(mem, src);
 			self.stat.interrupt_cnt += 1;
-			7 // not sure if this is correct
+			7
 		} else {
 			self.stat.instr_cnt += 1;
 			self.exec_instruction(mem)
 		};
 
-		// we already executed one of the needed cycles
-		self.skip_cycles = cycles - 1;
+		self.stat.cycle_cnt += cycles as u64;
 	}
 }
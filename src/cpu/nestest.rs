@@ -0,0 +1,24 @@
+// Runs nestest in its automation mode, which starts at $C000 and doesn't need the PPU, and compares
+// the trace with the log of Nintendulator, which covers the official and undocumented op-codes.
+
+use crate::nes::{LoadOptions, Nes};
+use std::fs;
+
+const ROM: &str = "rom/test/nestest.nes";
+const LOG: &str = "doc/misc/nestest.log";
+
+#[test]
+fn nestest() {
+	let log = fs::read_to_string(LOG).expect("nestest.log not found");
+	let mut nes = Nes::new(ROM, &LoadOptions::default()).expect("nestest.nes not found");
+	nes.start_at(0xC000);
+
+	for (i, expected) in log.lines().enumerate() {
+		let line = nes.trace_line().expect("no interrupt expected");
+		assert_eq!(line, expected.trim_end(), "first divergence in line {}", i + 1);
+		nes.step();
+	}
+
+	// error codes of the official and the undocumented op-codes
+	assert_eq!((nes.peek(0x02), nes.peek(0x03)), (0x00, 0x00));
+}
@@ -0,0 +1,62 @@
+// Table of all op-codes, generated from the instruction and address mode types of the CPU by
+// instruction_set!, see cpu/mod.rs.
+
+use super::Cpu;
+use crate::mem::MemoryMap;
+use std::sync::OnceLock;
+
+pub use super::addressing::Mode;
+
+#[derive(Clone, Copy)]
+pub struct Opcode {
+	pub mnemonic: &'static str,
+	pub mode: Mode,
+	pub bytes: u16,    // including the op-code
+	pub cycles: usize, // without the extra cycles of page crossings and taken branches
+	pub undocumented: bool,
+}
+
+pub fn opcode(op: u8) -> &'static Opcode {
+	static TABLE: OnceLock<[Opcode; 256]> = OnceLock::new();
+
+	// the table doesn't depend on the bus, any one will do
+	let table =
+		TABLE.get_or_init(|| std::array::from_fn(|op| Cpu::<MemoryMap>::opcode_info(op as u8)));
+	&table[op as usize]
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	// cycles of the NMOS 6502 without page crossings, the JAMs don't finish
+	#[rustfmt::skip]
+	const CYCLES: [usize; 256] = [
+		7, 6, 0, 8, 3, 3, 5, 5, 3, 2, 2, 2, 4, 4, 6, 6,
+		2, 5, 0, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7,
+		6, 6, 0, 8, 3, 3, 5, 5, 4, 2, 2, 2, 4, 4, 6, 6,
+		2, 5, 0, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7,
+		6, 6, 0, 8, 3, 3, 5, 5, 3, 2, 2, 2, 3, 4, 6, 6,
+		2, 5, 0, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7,
+		6, 6, 0, 8, 3, 3, 5, 5, 4, 2, 2, 2, 5, 4, 6, 6,
+		2, 5, 0, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7,
+		2, 6, 2, 6, 3, 3, 3, 3, 2, 2, 2, 2, 4, 4, 4, 4,
+		2, 6, 0, 6, 4, 4, 4, 4, 2, 5, 2, 5, 5, 5, 5, 5,
+		2, 6, 2, 6, 3, 3, 3, 3, 2, 2, 2, 2, 4, 4, 4, 4,
+		2, 5, 0, 5, 4, 4, 4, 4, 2, 4, 2, 4, 4, 4, 4, 4,
+		2, 6, 2, 8, 3, 3, 5, 5, 2, 2, 2, 2, 4, 4, 6, 6,
+		2, 5, 0, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7,
+		2, 6, 2, 8, 3, 3, 5, 5, 2, 2, 2, 2, 4, 4, 6, 6,
+		2, 5, 0, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7,
+	];
+
+	#[test]
+	fn cycles() {
+		for op in 0..=0xFFu8 {
+			let info = opcode(op);
+			if info.mnemonic != "JAM" {
+				assert_eq!(info.cycles, CYCLES[op as usize], "op-code 0x{:02X}", op);
+			}
+		}
+	}
+}
@@ -0,0 +1,145 @@
+// Runs the per op-code test vectors of SingleStepTests/ProcessorTests (nes6502) against the CPU,
+// see https://github.com/SingleStepTests/65x02. The vectors aren't part of the repository, so the
+// test is skipped if they can't be found. The directory with the 00.json ... ff.json files can be
+// set via SINGLE_STEP_TESTS_DIR. If SINGLE_STEP_TESTS_CYCLES is set, the bus activity of every
+// cycle is compared too.
+// A small sample in the same format is part of the repository and always runs including the
+// cycles. Its vectors were written by hand, mainly for the illegal op-codes.
+
+use super::Cpu;
+use crate::mem::{CpuBus, FlatBus};
+use serde_json::Value;
+use std::{env, fs, path::Path};
+
+const DEFAULT_DIR: &str = "rom/test/nes6502/v1";
+const SAMPLE_DIR: &str = "rom/test/nes6502/sample";
+const MAX_REPORTED_FAILURES: usize = 20;
+
+// the CPU stays halted on these, while the vectors expect it to continue reading
+const JAM_OPS: [u8; 12] = [0x02, 0x12, 0x22, 0x32, 0x42, 0x52, 0x62, 0x72, 0x92, 0xB2, 0xD2, 0xF2];
+
+fn num(v: &Value) -> usize {
+	v.as_u64().expect("test vector: expected a number") as usize
+}
+
+fn check(what: &str, actual: usize, expected: usize) -> Result<(), String> {
+	if actual != expected {
+		return Err(format!("{}: got 0x{:02X}, expected 0x{:02X}", what, actual, expected));
+	}
+
+	Ok(())
+}
+
+fn run_vector(vector: &Value, check_cycles: bool) -> Result<(), String> {
+	let mut cpu = Cpu::<FlatBus>::new();
+	let mut bus = FlatBus::new();
+
+	let initial = &vector["initial"];
+	cpu.pc = num(&initial["pc"]) as u16;
+	cpu.sp = num(&initial["s"]) as u8;
+	cpu.a = num(&initial["a"]) as u8;
+	cpu.x = num(&initial["x"]) as u8;
+	cpu.y = num(&initial["y"]) as u8;
+	cpu.p = num(&initial["p"]) as u8;
+	for entry in initial["ram"].as_array().unwrap() {
+		bus.poke(num(&entry[0]), num(&entry[1]) as u8);
+	}
+
+	cpu.step(&mut bus);
+
+	let expected = &vector["final"];
+	check("pc", cpu.pc as usize, num(&expected["pc"]))?;
+	check("s", cpu.sp as usize, num(&expected["s"]))?;
+	check("a", cpu.a as usize, num(&expected["a"]))?;
+	check("x", cpu.x as usize, num(&expected["x"]))?;
+	check("y", cpu.y as usize, num(&expected["y"]))?;
+	check("p", cpu.p as usize, num(&expected["p"]))?;
+	for entry in expected["ram"].as_array().unwrap() {
+		let addr = num(&entry[0]);
+		check(&format!("ram[0x{:04X}]", addr), bus.peek(addr) as usize, num(&entry[1]))?;
+	}
+
+	if check_cycles {
+		let cycles = vector["cycles"].as_array().unwrap();
+		check("cycle count", bus.accesses.len(), cycles.len())?;
+
+		for (i, (cycle, &(addr, val, write))) in cycles.iter().zip(bus.accesses.iter()).enumerate()
+		{
+			let expected_write = cycle[2].as_str() == Some("write");
+			if (addr, val as usize, write) != (num(&cycle[0]), num(&cycle[1]), expected_write) {
+				return Err(format!(
+					"cycle {}: got {} 0x{:04X} = 0x{:02X}, expected {}",
+					i,
+					if write {
+						"write"
+					} else {
+						"read"
+					},
+					addr,
+					val,
+					cycle
+				));
+			}
+		}
+	}
+
+	Ok(())
+}
+
+// returns the number of vectors and the failures
+fn run_dir(dir: &str, check_cycles: bool) -> (usize, Vec<String>) {
+	let mut failures = Vec::new();
+	let mut cnt = 0;
+	for op in 0x00..=0xFFu8 {
+		if JAM_OPS.contains(&op) {
+			continue;
+		}
+
+		let file = Path::new(dir).join(format!("{:02x}.json", op));
+		let json = match fs::read_to_string(&file) {
+			Ok(json) => json,
+			Err(_) => continue,
+		};
+		let vectors: Value = serde_json::from_str(&json).expect("invalid test vector file");
+
+		for vector in vectors.as_array().unwrap() {
+			cnt += 1;
+			if let Err(e) = run_vector(vector, check_cycles) {
+				let name = vector["name"].as_str().unwrap_or("");
+				failures.push(format!("op 0x{:02X} \"{}\": {}", op, name, e));
+			}
+		}
+	}
+
+	(cnt, failures)
+}
+
+fn assert_passed(cnt: usize, failures: &[String]) {
+	assert!(
+		failures.is_empty(),
+		"{} of {} vectors failed:\n{}",
+		failures.len(),
+		cnt,
+		failures.iter().take(MAX_REPORTED_FAILURES).cloned().collect::<Vec<_>>().join("\n")
+	);
+}
+
+#[test]
+fn single_step_tests_sample() {
+	let (cnt, failures) = run_dir(SAMPLE_DIR, true);
+	assert!(cnt > 0, "no vectors found in {}", SAMPLE_DIR);
+	assert_passed(cnt, &failures);
+}
+
+#[test]
+fn single_step_tests() {
+	let dir = env::var("SINGLE_STEP_TESTS_DIR").unwrap_or(String::from(DEFAULT_DIR));
+	if !Path::new(&dir).is_dir() {
+		println!("SingleStepTests not found in {}, skipped", dir);
+		return;
+	}
+	let check_cycles = env::var("SINGLE_STEP_TESTS_CYCLES").is_ok();
+
+	let (cnt, failures) = run_dir(&dir, check_cycles);
+	assert_passed(cnt, &failures);
+}
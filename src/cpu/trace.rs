@@ -0,0 +1,102 @@
+// Trace of the executed instructions in the format of Nintendulator, e.g.
+// C000  4C F5 C5  JMP $C5F5                       A:00 X:00 Y:00 P:24 SP:FD
+// The operand shows the effective address and the value stored there, the caller appends the
+// position of the PPU and the cycle count. Addresses with a label show its name instead.
+
+use super::opcodes::{opcode, Mode::*};
+use super::Cpu;
+use crate::mem::CpuBus;
+
+// the pointers of the indirect modes wrap around inside the zeropage
+fn peek16_zp<B: CpuBus>(mem: &B, addr: u8) -> u16 {
+	let l = mem.peek(addr as usize) as u16;
+	let h = mem.peek(addr.wrapping_add(1) as usize) as u16;
+	(h << 8) | l
+}
+
+impl<B: CpuBus> Cpu<B> {
+	// The next instruction as a line of the trace, the memory is only peeked. Returns none if the
+	// next step services an interrupt or the CPU is jammed.
+	pub fn trace(&self, mem: &B, label: impl Fn(u16) -> Option<String>) -> Option<String> {
+		if !self.at_instruction() {
+			return None;
+		}
+
+		let op = mem.peek(self.pc as usize);
+		let info = opcode(op);
+		let mode = info.mode;
+		let op8 = mem.peek(self.pc.wrapping_add(1) as usize);
+		let op16 = ((mem.peek(self.pc.wrapping_add(2) as usize) as u16) << 8) | (op8 as u16);
+
+		let mut bytes = format!("{:02X}", op);
+		for i in 1..info.bytes {
+			bytes.push_str(&format!(" {:02X}", mem.peek(self.pc.wrapping_add(i) as usize)));
+		}
+
+		let val = |addr: u16| mem.peek(addr as usize);
+		let zp = |addr: u8| label(addr as u16).unwrap_or_else(|| format!("${:02X}", addr));
+		let abs = |addr: u16| label(addr).unwrap_or_else(|| format!("${:04X}", addr));
+		let operand = match mode {
+			Imp => String::new(),
+			Acc => String::from("A"),
+			Imm => format!("#${:02X}", op8),
+			Rel => abs(self.pc.wrapping_add(2).wrapping_add(op8 as i8 as u16)),
+			Zpg => format!("{} = {:02X}", zp(op8), val(op8 as u16)),
+			Zpx | Zpy => {
+				let (reg, idx) = if let Zpx = mode {
+					('X', self.x)
+				} else {
+					('Y', self.y)
+				};
+				let addr = op8.wrapping_add(idx);
+				format!("{},{} @ {:02X} = {:02X}", zp(op8), reg, addr, val(addr as u16))
+			}
+			// jumps show only the target
+			Abs if op == 0x4C || op == 0x20 => abs(op16),
+			Abs => format!("{} = {:02X}", abs(op16), val(op16)),
+			Abx | Aby => {
+				let (reg, idx) = if let Abx = mode {
+					('X', self.x)
+				} else {
+					('Y', self.y)
+				};
+				let addr = op16.wrapping_add(idx as u16);
+				format!("{},{} @ {:04X} = {:02X}", abs(op16), reg, addr, val(addr))
+			}
+			Ind => {
+				// the high byte of the target is read from the same page
+				let h = val((op16 & 0xFF00) | (op16.wrapping_add(1) & 0x00FF)) as u16;
+				format!("({}) = {:04X}", abs(op16), (h << 8) | val(op16) as u16)
+			}
+			Izx => {
+				let ptr = op8.wrapping_add(self.x);
+				let addr = peek16_zp(mem, ptr);
+				format!("({},X) @ {:02X} = {:04X} = {:02X}", zp(op8), ptr, addr, val(addr))
+			}
+			Izy => {
+				let base = peek16_zp(mem, op8);
+				let addr = base.wrapping_add(self.y as u16);
+				format!("({}),Y = {:04X} @ {:04X} = {:02X}", zp(op8), base, addr, val(addr))
+			}
+		};
+
+		// undocumented op-codes replace the space in front of the mnemonic by their '*'
+		let mnemonic = if info.undocumented {
+			format!("*{}", info.mnemonic)
+		} else {
+			format!(" {}", info.mnemonic)
+		};
+
+		Some(format!(
+			"{:04X}  {:<8} {:<33}A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X}",
+			self.pc,
+			bytes,
+			format!("{} {}", mnemonic, operand).trim_end(),
+			self.a,
+			self.x,
+			self.y,
+			self.p,
+			self.sp
+		))
+	}
+}
@@ -0,0 +1,405 @@
+// Commands of the debugger REPL. Addresses and values are hexadecimal, with an optional '$' or
+// "0x" prefix, counts and scanlines are decimal.
+
+use crate::cpu::Registers;
+use crate::mem::watch::{AddrSpace, Watchpoint};
+use std::fmt;
+
+const DEFAULT_DUMP_LEN: usize = 0x40;
+const DEFAULT_DISASM_CNT: usize = 10;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Reg {
+	A,
+	X,
+	Y,
+	P,
+	Sp,
+	Pc,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum CmpOp {
+	Eq,
+	Ne,
+	Lt,
+	Le,
+	Gt,
+	Ge,
+	And, // at least one of the bits is set
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct Condition {
+	pub reg: Reg,
+	pub op: CmpOp,
+	pub val: u16,
+}
+
+// breaks at the address if the condition holds, without an address it breaks as soon as the
+// condition becomes true
+#[derive(Clone, Copy, PartialEq)]
+pub struct Breakpoint {
+	pub addr: Option<u16>,
+	pub cond: Option<Condition>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Command {
+	Continue,
+	Step(usize),
+	Next,
+	Finish,
+	Scanline(usize),
+	Break(Breakpoint),
+	Delete(usize),
+	Watch(Watchpoint),
+	Unwatch(usize),
+	List,
+	Registers,
+	Memory(AddrSpace, usize, usize),
+	// start (PC if none) and number of instructions
+	Disassemble(Option<u16>, usize),
+	Help,
+	Quit,
+}
+
+#[derive(PartialEq)]
+pub enum ParseErr {
+	UnknownCommand(String),
+	MissingArgument(&'static str),
+	InvalidNumber(String),
+	InvalidRegister(String),
+	InvalidOperator(String),
+	InvalidAccess(String),
+}
+
+impl fmt::Debug for ParseErr {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match &self {
+			Self::UnknownCommand(c) => write!(f, "unknown command: {}, see help", c),
+			Self::MissingArgument(a) => write!(f, "missing argument: {}", a),
+			Self::InvalidNumber(n) => write!(f, "invalid number: {}", n),
+			Self::InvalidRegister(r) => write!(f, "invalid register: {}", r),
+			Self::InvalidOperator(o) => write!(f, "invalid operator: {}", o),
+			Self::InvalidAccess(a) => write!(f, "invalid access: {}, expected r, w and/or x", a),
+		}
+	}
+}
+
+impl fmt::Debug for Reg {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match &self {
+			Self::A => write!(f, "A"),
+			Self::X => write!(f, "X"),
+			Self::Y => write!(f, "Y"),
+			Self::P => write!(f, "P"),
+			Self::Sp => write!(f, "SP"),
+			Self::Pc => write!(f, "PC"),
+		}
+	}
+}
+
+impl fmt::Debug for Condition {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let op = match self.op {
+			CmpOp::Eq => "==",
+			CmpOp::Ne => "!=",
+			CmpOp::Lt => "<",
+			CmpOp::Le => "<=",
+			CmpOp::Gt => ">",
+			CmpOp::Ge => ">=",
+			CmpOp::And => "&",
+		};
+		write!(f, "{:?} {} ${:02X}", self.reg, op, self.val)
+	}
+}
+
+impl fmt::Debug for Breakpoint {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match (self.addr, self.cond) {
+			(Some(addr), Some(cond)) => write!(f, "${:04X} if {:?}", addr, cond),
+			(Some(addr), None) => write!(f, "${:04X}", addr),
+			(None, Some(cond)) => write!(f, "if {:?}", cond),
+			(None, None) => write!(f, "never"),
+		}
+	}
+}
+
+impl Condition {
+	pub fn eval(&self, regs: &Registers) -> bool {
+		let reg = match self.reg {
+			Reg::A => regs.a as u16,
+			Reg::X => regs.x as u16,
+			Reg::Y => regs.y as u16,
+			Reg::P => regs.p as u16,
+			Reg::Sp => regs.sp as u16,
+			Reg::Pc => regs.pc,
+		};
+
+		match self.op {
+			CmpOp::Eq => reg == self.val,
+			CmpOp::Ne => reg != self.val,
+			CmpOp::Lt => reg < self.val,
+			CmpOp::Le => reg <= self.val,
+			CmpOp::Gt => reg > self.val,
+			CmpOp::Ge => reg >= self.val,
+			CmpOp::And => (reg & self.val) != 0,
+		}
+	}
+}
+
+fn parse_hex(s: &str) -> Result<usize, ParseErr> {
+	let digits = s.trim_start_matches('$').trim_start_matches("0x");
+	usize::from_str_radix(digits, 16).or(Err(ParseErr::InvalidNumber(String::from(s))))
+}
+
+fn parse_dec(s: &str) -> Result<usize, ParseErr> {
+	s.parse().or(Err(ParseErr::InvalidNumber(String::from(s))))
+}
+
+fn parse_addr(s: &str) -> Result<u16, ParseErr> {
+	match parse_hex(s)? {
+		addr if addr <= 0xFFFF => Ok(addr as u16),
+		_ => Err(ParseErr::InvalidNumber(String::from(s))),
+	}
+}
+
+// e.g. "a == 10", "x>=$80" or "p & 01"
+fn parse_condition(s: &str) -> Result<Condition, ParseErr> {
+	let op_start = s.find(|c| "=!<>&".contains(c)).ok_or(ParseErr::MissingArgument("operator"))?;
+	let op_end = s[op_start..].find(|c| !"=!<>&".contains(c)).map_or(s.len(), |i| op_start + i);
+
+	let reg = match s[..op_start].trim().to_lowercase().as_str() {
+		"a" => Reg::A,
+		"x" => Reg::X,
+		"y" => Reg::Y,
+		"p" => Reg::P,
+		"sp" => Reg::Sp,
+		"pc" => Reg::Pc,
+		r => return Err(ParseErr::InvalidRegister(String::from(r))),
+	};
+
+	let op = match &s[op_start..op_end] {
+		"==" | "=" => CmpOp::Eq,
+		"!=" => CmpOp::Ne,
+		"<" => CmpOp::Lt,
+		"<=" => CmpOp::Le,
+		">" => CmpOp::Gt,
+		">=" => CmpOp::Ge,
+		"&" => CmpOp::And,
+		o => return Err(ParseErr::InvalidOperator(String::from(o))),
+	};
+
+	let val = s[op_end..].trim();
+	if val.is_empty() {
+		return Err(ParseErr::MissingArgument("value"));
+	}
+
+	Ok(Condition {
+		reg,
+		op,
+		val: parse_addr(val)?,
+	})
+}
+
+// "<addr> [if <condition>]" or "if <condition>"
+fn parse_breakpoint(args: &str) -> Result<Breakpoint, ParseErr> {
+	let (addr, cond) = match args.find("if") {
+		Some(i) => (args[..i].trim(), Some(args[i + 2..].trim())),
+		None => (args.trim(), None),
+	};
+
+	if addr.is_empty() && cond.is_none() {
+		return Err(ParseErr::MissingArgument("address or condition"));
+	}
+
+	Ok(Breakpoint {
+		addr: if addr.is_empty() {
+			None
+		} else {
+			Some(parse_addr(addr)?)
+		},
+		cond: cond.map(parse_condition).transpose()?,
+	})
+}
+
+// "<start>[-<end>] [r|w|x|rw|...] [ppu]", reads and writes are watched by default
+fn parse_watchpoint(args: &[&str]) -> Result<Watchpoint, ParseErr> {
+	let range = args.first().ok_or(ParseErr::MissingArgument("address range"))?;
+	let (start, end) = match range.split_once('-') {
+		Some((start, end)) => (parse_addr(start)?, parse_addr(end)?),
+		None => (parse_addr(range)?, parse_addr(range)?),
+	};
+
+	let mut wp = Watchpoint {
+		space: AddrSpace::Cpu,
+		start: start.min(end) as usize,
+		end: start.max(end) as usize,
+		read: true,
+		write: true,
+		execute: false,
+	};
+
+	for arg in &args[1..] {
+		if arg.eq_ignore_ascii_case("ppu") {
+			wp.space = AddrSpace::Ppu;
+			continue;
+		}
+
+		if arg.is_empty() || !arg.chars().all(|c| "rwx".contains(c)) {
+			return Err(ParseErr::InvalidAccess(String::from(*arg)));
+		}
+		wp.read = arg.contains('r');
+		wp.write = arg.contains('w');
+		wp.execute = arg.contains('x');
+	}
+
+	// the PPU doesn't execute anything
+	if wp.space == AddrSpace::Ppu && wp.execute {
+		return Err(ParseErr::InvalidAccess(String::from("x")));
+	}
+
+	Ok(wp)
+}
+
+fn parse_memory(space: AddrSpace, args: &[&str]) -> Result<Command, ParseErr> {
+	let addr = parse_addr(args.first().ok_or(ParseErr::MissingArgument("address"))?)?;
+	let len = match args.get(1) {
+		Some(len) => parse_hex(len)?,
+		None => DEFAULT_DUMP_LEN,
+	};
+
+	Ok(Command::Memory(space, addr as usize, len))
+}
+
+// "[addr] [count]"
+fn parse_disassemble(args: &[&str]) -> Result<Command, ParseErr> {
+	let addr = args.first().map(|a| parse_addr(a)).transpose()?;
+	let cnt = match args.get(1) {
+		Some(cnt) => parse_dec(cnt)?,
+		None => DEFAULT_DISASM_CNT,
+	};
+
+	Ok(Command::Disassemble(addr, cnt))
+}
+
+pub fn parse(line: &str) -> Result<Command, ParseErr> {
+	let line = line.trim();
+	let (cmd, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+	let args: Vec<&str> = rest.split_whitespace().collect();
+	let index = || parse_dec(args.first().ok_or(ParseErr::MissingArgument("index"))?);
+
+	match cmd {
+		"c" | "continue" => Ok(Command::Continue),
+		"s" | "step" => match args.first() {
+			Some(cnt) => Ok(Command::Step(parse_dec(cnt)?)),
+			None => Ok(Command::Step(1)),
+		},
+		"n" | "next" => Ok(Command::Next),
+		"f" | "finish" => Ok(Command::Finish),
+		"sl" | "scanline" => {
+			let sl = args.first().ok_or(ParseErr::MissingArgument("scanline"))?;
+			Ok(Command::Scanline(parse_dec(sl)?))
+		}
+		"b" | "break" => Ok(Command::Break(parse_breakpoint(rest)?)),
+		"d" | "delete" => Ok(Command::Delete(index()?)),
+		"w" | "watch" => Ok(Command::Watch(parse_watchpoint(&args)?)),
+		"u" | "unwatch" => Ok(Command::Unwatch(index()?)),
+		"l" | "list" => Ok(Command::List),
+		"r" | "regs" => Ok(Command::Registers),
+		"m" | "mem" => parse_memory(AddrSpace::Cpu, &args),
+		"pm" | "ppumem" => parse_memory(AddrSpace::Ppu, &args),
+		"x" | "disasm" => parse_disassemble(&args),
+		"h" | "help" => Ok(Command::Help),
+		"q" | "quit" => Ok(Command::Quit),
+		c => Err(ParseErr::UnknownCommand(String::from(c))),
+	}
+}
+
+pub const HELP: &str = "\
+c, continue                  run until the next break
+s, step [n]                  execute n instructions (default 1)
+n, next                      step over a JSR
+f, finish                    run until the current subroutine returns
+sl, scanline <n>             run until the PPU reaches the scanline
+b, break <addr> [if <cond>]  break at the address, e.g. \"b c000 if x >= 10\"
+b, break if <cond>           break when the condition becomes true, e.g. \"b if a == 0\"
+d, delete <n>                delete breakpoint n
+w, watch <start>[-<end>] [rwx] [ppu]
+                             break on accesses of the CPU (or PPU) range, default rw
+u, unwatch <n>               delete watchpoint n
+l, list                      list the breakpoints and watchpoints
+r, regs                      show the registers
+m, mem <addr> [len]          dump CPU memory, registers of the hardware show as FF
+pm, ppumem <addr> [len]      dump PPU memory
+x, disasm [addr] [n]         disassemble n instructions (default 10) at the address or the PC
+q, quit                      quit the emulator
+Addresses, values and lengths are hexadecimal, counts are decimal, registers of conditions are a, x, y, p, sp or pc.
+An empty line repeats the last command.";
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn regs(a: u8, x: u8, pc: u16) -> Registers {
+		Registers {
+			a,
+			x,
+			pc,
+			..Default::default()
+		}
+	}
+
+	#[test]
+	fn conditions() {
+		let cond = parse_condition("x>=$80").unwrap();
+		assert!(cond.reg == Reg::X && cond.op == CmpOp::Ge && cond.val == 0x80);
+		assert!(cond.eval(&regs(0, 0x80, 0)));
+		assert!(!cond.eval(&regs(0, 0x7F, 0)));
+
+		assert!(parse_condition("p & 01").unwrap().op == CmpOp::And);
+		assert!(parse_condition("pc == c000").unwrap().eval(&regs(0, 0, 0xC000)));
+		assert!(parse_condition("q == 1") == Err(ParseErr::InvalidRegister(String::from("q"))));
+		assert!(parse_condition("a =< 1") == Err(ParseErr::InvalidOperator(String::from("=<"))));
+		assert!(parse_condition("a ==") == Err(ParseErr::MissingArgument("value")));
+	}
+
+	#[test]
+	fn commands() {
+		assert!(parse("step 10") == Ok(Command::Step(10)));
+		assert!(parse("  s ") == Ok(Command::Step(1)));
+
+		let bp = Breakpoint {
+			addr: Some(0xC5F5),
+			cond: Some(Condition {
+				reg: Reg::A,
+				op: CmpOp::Eq,
+				val: 0,
+			}),
+		};
+		assert!(parse("b $c5f5 if a == 0") == Ok(Command::Break(bp)));
+		assert!(
+			parse("b if a == 0")
+				== Ok(Command::Break(Breakpoint {
+					addr: None,
+					..bp
+				}))
+		);
+		assert!(parse("b 10000") == Err(ParseErr::InvalidNumber(String::from("10000"))));
+
+		match parse("w 2000-23ff w ppu") {
+			Ok(Command::Watch(wp)) => {
+				assert!(wp.space == AddrSpace::Ppu && (wp.start, wp.end) == (0x2000, 0x23FF));
+				assert!(!wp.read && wp.write && !wp.execute);
+			}
+			_ => panic!("watchpoint expected"),
+		}
+		assert!(parse("w 2000 x ppu") == Err(ParseErr::InvalidAccess(String::from("x"))));
+
+		assert!(parse("m 0x589a 10") == Ok(Command::Memory(AddrSpace::Cpu, 0x589A, 0x10)));
+		assert!(parse("x c000 3") == Ok(Command::Disassemble(Some(0xC000), 3)));
+		assert!(parse("x") == Ok(Command::Disassemble(None, DEFAULT_DISASM_CNT)));
+		assert!(parse("jump") == Err(ParseErr::UnknownCommand(String::from("jump"))));
+	}
+}
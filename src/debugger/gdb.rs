@@ -0,0 +1,570 @@
+// Stub of the GDB remote serial protocol, so the CPU can be debugged by gdb or any other client of
+// the protocol (e.g. VS Code or lldb of llvm-mos). Like the terminal debugger it runs the frames
+// and is asked before every instruction if it has to stop. Once stopped, it serves the packets of
+// the client until it continues or steps. While running, only Ctrl-C of the client is checked,
+// once per frame.
+// The registers are A, X, Y, P, SP (8 bit) and PC (16 bit, little endian), the client gets them
+// from target.xml. Software and hardware breakpoints are the same, watchpoints use the ones of the
+// memory map.
+
+use crate::cpu::Registers;
+use crate::mem::watch::{Access, AddrSpace, Watchpoint};
+use crate::nes::{Nes, RunEvent};
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+
+const INTERRUPT: u8 = 0x03;
+const SIGTRAP: &str = "S05";
+const PACKET_SIZE: usize = 0x4000;
+
+const TARGET_XML: &str = "<?xml version=\"1.0\"?>\
+<!DOCTYPE target SYSTEM \"gdb-target.dtd\">\
+<target version=\"1.0\">\
+<feature name=\"org.gnu.gdb.m6502.core\">\
+<reg name=\"a\" bitsize=\"8\" regnum=\"0\"/>\
+<reg name=\"x\" bitsize=\"8\"/>\
+<reg name=\"y\" bitsize=\"8\"/>\
+<reg name=\"p\" bitsize=\"8\"/>\
+<reg name=\"sp\" bitsize=\"8\" type=\"data_ptr\"/>\
+<reg name=\"pc\" bitsize=\"16\" type=\"code_ptr\"/>\
+</feature>\
+</target>";
+
+#[derive(Clone, Copy, PartialEq)]
+enum RunMode {
+	Stop,
+	Continue,
+	Step,
+}
+
+pub struct GdbStub {
+	listener: TcpListener,
+	client: Option<TcpStream>,
+	breakpoints: Vec<u16>,
+	mode: RunMode,
+	resumed: bool, // the first instruction after stopping doesn't break again
+	stop_reason: String,
+	no_ack: bool,
+	quit: bool,
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+	bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(s: &str) -> Option<Vec<u8>> {
+	if !s.len().is_multiple_of(2) {
+		return None;
+	}
+
+	(0..s.len()).step_by(2).map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok()).collect()
+}
+
+fn parse_num(s: &str) -> Option<usize> {
+	usize::from_str_radix(s, 16).ok()
+}
+
+// "<addr>,<len>"
+fn parse_range(s: &str) -> Option<(usize, usize)> {
+	let (addr, len) = s.split_once(',')?;
+	Some((parse_num(addr)?, parse_num(len)?))
+}
+
+// "<addr>,<len>" of the CPU address space, none if the range doesn't fit into the 64KB
+fn parse_mem_range(s: &str) -> Option<(usize, usize)> {
+	let (addr, len) = parse_range(s)?;
+	mem_range(addr, len)
+}
+
+fn mem_range(addr: usize, len: usize) -> Option<(usize, usize)> {
+	match addr.checked_add(len) {
+		Some(end) if end <= 0x10000 => Some((addr, len)),
+		_ => None,
+	}
+}
+
+fn checksum(data: &str) -> u8 {
+	data.bytes().fold(0, |sum, b| sum.wrapping_add(b))
+}
+
+impl GdbStub {
+	// only binds the port, the client is waited for by accept()
+	pub fn listen(addr: &str) -> io::Result<Self> {
+		Ok(Self {
+			listener: TcpListener::bind(addr)?,
+			client: None,
+			breakpoints: Vec::new(),
+			mode: RunMode::Stop,
+			resumed: false,
+			stop_reason: String::from(SIGTRAP),
+			no_ack: false,
+			quit: false,
+		})
+	}
+
+	pub fn local_addr(&self) -> io::Result<SocketAddr> {
+		self.listener.local_addr()
+	}
+
+	// blocks until a client connects, the emulation stops at the next instruction
+	pub fn accept(&mut self) -> io::Result<()> {
+		let (stream, _) = self.listener.accept()?;
+		stream.set_nodelay(true)?;
+		self.client = Some(stream);
+		self.mode = RunMode::Stop;
+		self.no_ack = false;
+		Ok(())
+	}
+
+	pub fn quit_requested(&self) -> bool {
+		self.quit
+	}
+
+	// same as Nes::run_frame(), but stops at breaks and serves the client
+	pub fn run_frame(&mut self, nes: &mut Nes) -> bool {
+		if self.mode != RunMode::Stop && self.interrupted() {
+			self.mode = RunMode::Stop;
+		}
+
+		loop {
+			match nes.run_until(|nes| self.check(nes)) {
+				RunEvent::Break => {
+					self.serve(nes);
+					if self.quit {
+						return false;
+					}
+				}
+				ev => return ev == RunEvent::FbReady,
+			}
+		}
+	}
+
+	// called before every step, true if the emulation has to stop
+	fn check(&mut self, nes: &mut Nes) -> bool {
+		if self.client.is_none() || !nes.at_instruction() {
+			return false;
+		}
+
+		let mut stop = false;
+		if let Some(hit) = nes.watchpoints().take_hits().first() {
+			let kind = match hit.access {
+				Access::Write => "watch",
+				_ => "rwatch",
+			};
+			self.stop_reason = format!("T05{}:{:04x};", kind, hit.addr);
+			stop = true;
+		}
+
+		if self.resumed {
+			self.resumed = false;
+			return stop;
+		}
+
+		let pc = nes.registers().pc;
+		if !stop && (self.mode != RunMode::Continue || self.breakpoints.contains(&pc)) {
+			self.stop_reason = String::from(SIGTRAP);
+			stop = true;
+		}
+
+		stop
+	}
+
+	fn resume(&mut self, nes: &mut Nes, mode: RunMode, args: &str) -> Option<String> {
+		// "c <addr>" and "s <addr>" continue at the address
+		if let Some(addr) = parse_num(args) {
+			let mut regs = nes.registers();
+			regs.pc = addr as u16;
+			nes.set_registers(regs);
+		}
+
+		self.mode = mode;
+		self.resumed = true;
+		None
+	}
+
+	// answers the packets until the client continues, steps or leaves
+	fn serve(&mut self, nes: &mut Nes) {
+		// the client waits for the reply of "c" or "s"
+		if self.mode != RunMode::Stop {
+			self.mode = RunMode::Stop;
+			let reason = self.stop_reason.clone();
+			self.send(&reason);
+		}
+
+		while self.mode == RunMode::Stop && self.client.is_some() && !self.quit {
+			match self.read_packet() {
+				Some(packet) => {
+					if let Some(reply) = self.handle(nes, &packet) {
+						self.send(&reply);
+					}
+				}
+				None => self.detach(),
+			}
+		}
+	}
+
+	// the emulation runs on without the client
+	fn detach(&mut self) {
+		self.client = None;
+		self.breakpoints.clear();
+		self.mode = RunMode::Continue;
+	}
+
+	// the reply of the packet, none if there is no reply (yet)
+	fn handle(&mut self, nes: &mut Nes, packet: &str) -> Option<String> {
+		let (cmd, args) = packet.split_at(packet.chars().next().map_or(0, |c| c.len_utf8()));
+		let error = Some(String::from("E01"));
+		let ok = Some(String::from("OK"));
+
+		match cmd {
+			"?" => Some(self.stop_reason.clone()),
+			"g" => {
+				let r = nes.registers();
+				Some(to_hex(&[r.a, r.x, r.y, r.p, r.sp, r.pc as u8, (r.pc >> 8) as u8]))
+			}
+			"G" => match from_hex(args) {
+				Some(b) if b.len() == 7 => {
+					nes.set_registers(Registers {
+						a: b[0],
+						x: b[1],
+						y: b[2],
+						p: b[3],
+						sp: b[4],
+						pc: (b[6] as u16) << 8 | b[5] as u16,
+					});
+					ok
+				}
+				_ => error,
+			},
+			"p" => {
+				let r = nes.registers();
+				match parse_num(args) {
+					Some(0) => Some(to_hex(&[r.a])),
+					Some(1) => Some(to_hex(&[r.x])),
+					Some(2) => Some(to_hex(&[r.y])),
+					Some(3) => Some(to_hex(&[r.p])),
+					Some(4) => Some(to_hex(&[r.sp])),
+					Some(5) => Some(to_hex(&[r.pc as u8, (r.pc >> 8) as u8])),
+					_ => error,
+				}
+			}
+			"P" => {
+				let mut r = nes.registers();
+				let Some((reg, val)) = args.split_once('=') else {
+					return error;
+				};
+				let val = from_hex(val).unwrap_or_default();
+				match (parse_num(reg), val.as_slice()) {
+					(Some(0), [v]) => r.a = *v,
+					(Some(1), [v]) => r.x = *v,
+					(Some(2), [v]) => r.y = *v,
+					(Some(3), [v]) => r.p = *v,
+					(Some(4), [v]) => r.sp = *v,
+					(Some(5), [l, h]) => r.pc = (*h as u16) << 8 | *l as u16,
+					_ => return error,
+				}
+				nes.set_registers(r);
+				ok
+			}
+			"m" => match parse_mem_range(args) {
+				Some((addr, len)) if len <= PACKET_SIZE / 2 => {
+					let bytes: Vec<u8> = (addr..addr + len).map(|a| nes.peek(a)).collect();
+					Some(to_hex(&bytes))
+				}
+				_ => error,
+			},
+			"M" => {
+				let (range, data) = args.split_once(':').unwrap_or((args, ""));
+				match (parse_mem_range(range), from_hex(data)) {
+					(Some((addr, len)), Some(bytes)) if bytes.len() == len => {
+						for (i, val) in bytes.into_iter().enumerate() {
+							nes.poke(addr + i, val);
+						}
+						// the client doesn't want to stop at its own writes
+						nes.watchpoints().take_hits();
+						ok
+					}
+					_ => error,
+				}
+			}
+			"Z" | "z" => {
+				let mut fields = args.splitn(3, ',');
+				let kind = fields.next().unwrap_or("");
+				let addr = fields.next().and_then(parse_num);
+				let len = fields.next().and_then(parse_num).unwrap_or(1).max(1);
+				let Some((addr, len)) = addr.and_then(|addr| mem_range(addr, len)) else {
+					return error;
+				};
+				if self.set_breakpoint(nes, kind, addr, len, cmd == "Z") {
+					ok
+				} else {
+					// unsupported type
+					Some(String::new())
+				}
+			}
+			"c" => self.resume(nes, RunMode::Continue, args),
+			"s" => self.resume(nes, RunMode::Step, args),
+			"k" => {
+				self.quit = true;
+				None
+			}
+			"D" => {
+				self.send("OK");
+				self.detach();
+				None
+			}
+			"H" | "T" => ok,
+			"Q" if args == "StartNoAckMode" => {
+				self.send("OK");
+				self.no_ack = true;
+				None
+			}
+			"q" => self.query(args),
+			// e.g. vCont, the client falls back to the basic packets
+			_ => Some(String::new()),
+		}
+	}
+
+	fn query(&self, query: &str) -> Option<String> {
+		let reply = match query.split(':').next()? {
+			"Supported" => {
+				format!("PacketSize={:x};qXfer:features:read+;QStartNoAckMode+", PACKET_SIZE)
+			}
+			"Attached" => String::from("1"),
+			"C" => String::from("QC1"),
+			"fThreadInfo" => String::from("m1"),
+			"sThreadInfo" => String::from("l"),
+			"Xfer" => {
+				// "Xfer:features:read:target.xml:<offset>,<len>"
+				let range = query.strip_prefix("Xfer:features:read:target.xml:")?;
+				let (offset, len) = parse_range(range)?;
+				let data = TARGET_XML.get(offset.min(TARGET_XML.len())..).unwrap_or("");
+				if data.len() > len {
+					format!("m{}", &data[..len])
+				} else {
+					format!("l{}", data)
+				}
+			}
+			_ => String::new(),
+		};
+		Some(reply)
+	}
+
+	// Z0 and Z1 are breakpoints, Z2 - Z4 watchpoints on writes, reads and both
+	fn set_breakpoint(
+		&mut self,
+		nes: &mut Nes,
+		kind: &str,
+		addr: usize,
+		len: usize,
+		set: bool,
+	) -> bool {
+		let (read, write) = match kind {
+			"0" | "1" => {
+				let addr = addr as u16;
+				if set {
+					self.breakpoints.push(addr);
+				} else if let Some(i) = self.breakpoints.iter().position(|a| *a == addr) {
+					self.breakpoints.remove(i);
+				}
+				return true;
+			}
+			"2" => (false, true),
+			"3" => (true, false),
+			"4" => (true, true),
+			_ => return false,
+		};
+
+		let wp = Watchpoint {
+			space: AddrSpace::Cpu,
+			start: addr,
+			end: addr + len - 1,
+			read,
+			write,
+			execute: false,
+		};
+		if set {
+			nes.watchpoints().add(wp);
+		} else if let Some(i) = nes.watchpoints().list().iter().position(|w| *w == wp) {
+			nes.watchpoints().remove(i);
+		}
+		true
+	}
+
+	// Ctrl-C of the client while the emulation runs
+	fn interrupted(&mut self) -> bool {
+		let Some(client) = self.client.as_mut() else {
+			return false;
+		};
+
+		let mut buf = [0; 1];
+		client.set_nonblocking(true).unwrap_or(());
+		let res = client.read(&mut buf);
+		client.set_nonblocking(false).unwrap_or(());
+
+		match res {
+			Ok(1) => buf[0] == INTERRUPT,
+			// the client is gone
+			Ok(_) => {
+				self.detach();
+				false
+			}
+			Err(e) if e.kind() == ErrorKind::WouldBlock => false,
+			Err(_) => {
+				self.detach();
+				false
+			}
+		}
+	}
+
+	fn read_byte(&mut self) -> Option<u8> {
+		let mut buf = [0; 1];
+		match self.client.as_mut()?.read(&mut buf) {
+			Ok(1) => Some(buf[0]),
+			_ => None,
+		}
+	}
+
+	// "$<data>#<checksum>", none if the client is gone
+	fn read_packet(&mut self) -> Option<String> {
+		loop {
+			match self.read_byte()? {
+				b'$' => (),
+				// a stopped emulation replies to an interrupt with the stop reason
+				INTERRUPT => return Some(String::from("?")),
+				// acks of the client
+				_ => continue,
+			}
+
+			let mut data = Vec::new();
+			loop {
+				match self.read_byte()? {
+					b'#' => break,
+					b => data.push(b),
+				}
+			}
+			let sum = [self.read_byte()?, self.read_byte()?];
+			let data = String::from_utf8_lossy(&data).into_owned();
+
+			let valid = std::str::from_utf8(&sum)
+				.ok()
+				.and_then(|s| u8::from_str_radix(s, 16).ok())
+				.is_some_and(|s| s == checksum(&data));
+			if !self.no_ack {
+				self.write(if valid {
+					b"+"
+				} else {
+					b"-"
+				});
+			}
+			if valid {
+				return Some(data);
+			}
+		}
+	}
+
+	fn send(&mut self, data: &str) {
+		let packet = format!("${}#{:02x}", data, checksum(data));
+		self.write(packet.as_bytes());
+	}
+
+	fn write(&mut self, bytes: &[u8]) {
+		if let Some(client) = self.client.as_mut() {
+			if client.write_all(bytes).is_err() {
+				self.detach();
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::nes::LoadOptions;
+	use std::thread;
+
+	// a scripted client, every packet is acked and has exactly one reply
+	struct Client {
+		stream: TcpStream,
+	}
+
+	impl Client {
+		fn read_byte(&mut self) -> u8 {
+			let mut buf = [0; 1];
+			self.stream.read_exact(&mut buf).unwrap();
+			buf[0]
+		}
+
+		fn request(&mut self, data: &str) -> String {
+			write!(self.stream, "${}#{:02x}", data, checksum(data)).unwrap();
+			assert_eq!(self.read_byte(), b'+', "no ack of {}", data);
+			assert_eq!(self.read_byte(), b'$');
+
+			let mut reply = Vec::new();
+			loop {
+				match self.read_byte() {
+					b'#' => break,
+					b => reply.push(b),
+				}
+			}
+			let reply = String::from_utf8(reply).unwrap();
+			let sum = [self.read_byte(), self.read_byte()];
+			assert_eq!(std::str::from_utf8(&sum).unwrap(), format!("{:02x}", checksum(&reply)));
+			self.stream.write_all(b"+").unwrap();
+			reply
+		}
+	}
+
+	#[test]
+	fn remote_protocol() {
+		let mut stub = GdbStub::listen("127.0.0.1:0").unwrap();
+		let addr = stub.local_addr().unwrap();
+
+		let client = thread::spawn(move || {
+			let mut c = Client {
+				stream: TcpStream::connect(addr).unwrap(),
+			};
+
+			assert!(c.request("qSupported:xmlRegisters=i386").contains("qXfer:features:read+"));
+			assert!(c.request("qXfer:features:read:target.xml:0,40").starts_with("m<?xml"));
+			assert_eq!(c.request("?"), "S05");
+			// nestest in its automation mode, see doc/misc/nestest.log
+			assert_eq!(c.request("g"), "00000024fd00c0");
+			assert_eq!(c.request("mc000,3"), "4cf5c5");
+
+			assert_eq!(c.request("Z0,c5f7,1"), "OK");
+			assert_eq!(c.request("c"), "S05");
+			assert_eq!(c.request("p5"), "f7c5");
+			assert_eq!(c.request("z0,c5f7,1"), "OK");
+			// STX $00
+			assert_eq!(c.request("s"), "S05");
+			assert_eq!(c.request("p5"), "f9c5");
+
+			assert_eq!(c.request("M10,2:abcd"), "OK");
+			assert_eq!(c.request("m10,2"), "abcd");
+			assert_eq!(c.request("P0=42"), "OK");
+			assert_eq!(c.request("g"), "42000026fdf9c5");
+
+			// STX $10 at $C5F9
+			assert_eq!(c.request("Z2,10,1"), "OK");
+			assert_eq!(c.request("c"), "T05watch:0010;");
+			assert_eq!(c.request("p5"), "fbc5");
+			assert_eq!(c.request("vMustReplyEmpty"), "");
+
+			// out of the address space
+			assert_eq!(c.request("mffffffffffffffff,10"), "E01");
+			assert_eq!(c.request("mfff0,11"), "E01");
+			assert_eq!(c.request("Z2,ffffffffffffffff,2"), "E01");
+			c.stream.write_all(b"$k#6b").unwrap();
+		});
+
+		let mut nes = Nes::new("rom/test/nestest.nes", &LoadOptions::default()).unwrap();
+		nes.start_at(0xC000);
+		stub.accept().unwrap();
+		while !stub.quit_requested() && !client.is_finished() {
+			stub.run_frame(&mut nes);
+		}
+
+		client.join().unwrap();
+	}
+}
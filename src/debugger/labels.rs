@@ -0,0 +1,249 @@
+// Labels of symbol files, shown by the disassembler, the debugger and the traces. Labels of the
+// PRG ROM are stored by their offset in it, so they only apply while their bank is mapped. All
+// other labels (RAM, PRG RAM, registers) are stored by their CPU address. Supported formats:
+// - ca65 debug info (.dbg), written by ld65 --dbgfile
+// - FCEUX name lists (.nl), <rom>.nes.ram.nl for RAM and <rom>.nes.<bank>.nl per 16KB PRG bank
+// - Mesen label files (.mlb) of Mesen and Mesen2
+
+use crate::mem::CpuBus;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+const INES_HEADER_SIZE: usize = 16;
+const NL_BANK_SIZE: usize = 0x4000;
+const CPU_RAM_MIRROR: u16 = 0x07FF;
+const PRG_RAM_START: u16 = 0x6000;
+
+pub enum LabelErr {
+	FileNotFound,
+	UnknownFormat,
+	InvalidLine(usize),
+}
+
+#[derive(Default)]
+pub struct Labels {
+	prg: HashMap<usize, String>,
+	cpu: HashMap<u16, String>,
+}
+
+impl fmt::Debug for LabelErr {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match &self {
+			Self::FileNotFound => write!(f, "FileNotFound"),
+			Self::UnknownFormat => write!(f, "UnknownFormat"),
+			Self::InvalidLine(l) => write!(f, "InvalidLine({})", l),
+		}
+	}
+}
+
+fn parse_hex(s: &str) -> Option<usize> {
+	let s = s.trim().trim_start_matches('$');
+	usize::from_str_radix(s.trim_start_matches("0x"), 16).ok()
+}
+
+// "<start>" or "<start>-<end>", only the start gets the label
+fn parse_range(s: &str) -> Option<usize> {
+	parse_hex(s.split('-').next()?)
+}
+
+// the "key=value" pairs of a line of ca65 debug info, values can be quoted
+fn dbg_fields(line: &str) -> HashMap<&str, &str> {
+	let mut fields = HashMap::new();
+	let mut rest = line;
+	while let Some(eq) = rest.find('=') {
+		let key = rest[..eq].trim();
+		let val_start = eq + 1;
+		let val_end = if rest[val_start..].starts_with('"') {
+			rest[val_start + 1..].find('"').map_or(rest.len(), |i| val_start + i + 2)
+		} else {
+			rest[val_start..].find(',').map_or(rest.len(), |i| val_start + i)
+		};
+
+		fields.insert(key, rest[val_start..val_end].trim_matches('"'));
+		rest = rest[val_end..].trim_start_matches(',');
+	}
+
+	fields
+}
+
+impl Labels {
+	pub fn is_empty(&self) -> bool {
+		self.prg.is_empty() && self.cpu.is_empty()
+	}
+
+	pub fn len(&self) -> usize {
+		self.prg.len() + self.cpu.len()
+	}
+
+	// the label of the CPU address with the current mapping of the PRG ROM
+	pub fn name<B: CpuBus>(&self, mem: &B, addr: u16) -> Option<&str> {
+		if self.is_empty() {
+			return None;
+		}
+
+		let name = match mem.prg_rom_offset(addr as usize) {
+			Some(offset) => self.prg.get(&offset),
+			None if addr < 0x2000 => self.cpu.get(&(addr & CPU_RAM_MIRROR)),
+			None => self.cpu.get(&addr),
+		};
+		name.map(|n| n.as_str())
+	}
+
+	// the first label of a range wins, e.g. for the local labels of ca65
+	fn add_prg(&mut self, offset: usize, name: &str) {
+		if !name.is_empty() {
+			self.prg.entry(offset).or_insert_with(|| String::from(name));
+		}
+	}
+
+	fn add_cpu(&mut self, addr: u16, name: &str) {
+		if !name.is_empty() {
+			self.cpu.entry(addr).or_insert_with(|| String::from(name));
+		}
+	}
+
+	// the format is chosen by the file ending
+	pub fn load(&mut self, file: &str) -> Result<(), LabelErr> {
+		let text = fs::read_to_string(file).or(Err(LabelErr::FileNotFound))?;
+		let path = Path::new(file);
+
+		match path.extension().and_then(|e| e.to_str()) {
+			Some("dbg") => self.load_dbg(&text),
+			Some("mlb") => self.load_mlb(&text),
+			Some("nl") => {
+				// the bank is the second to last part of the name, e.g. game.nes.1.nl
+				let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+				let bank = match stem.rsplit('.').next() {
+					Some("ram") => None,
+					Some(b) => Some(parse_hex(b).ok_or(LabelErr::UnknownFormat)?),
+					None => return Err(LabelErr::UnknownFormat),
+				};
+				self.load_nl(&text, bank)
+			}
+			_ => Err(LabelErr::UnknownFormat),
+		}
+	}
+
+	// "$C000#name#comment", the address can have a size, e.g. "$0200/100"
+	fn load_nl(&mut self, text: &str, bank: Option<usize>) -> Result<(), LabelErr> {
+		for (i, line) in text.lines().enumerate() {
+			if !line.starts_with('$') {
+				continue;
+			}
+
+			let fields: Vec<&str> = line.splitn(3, '#').collect();
+			let addr = fields[0].split('/').next().and_then(parse_hex);
+			let addr = addr.ok_or(LabelErr::InvalidLine(i + 1))?;
+			let name = fields.get(1).map_or("", |n| n.trim());
+
+			match bank {
+				Some(bank) => self.add_prg(bank * NL_BANK_SIZE + (addr % NL_BANK_SIZE), name),
+				None => self.add_cpu(addr as u16, name),
+			}
+		}
+
+		Ok(())
+	}
+
+	// "<type>:<addr>[-<end>]:name[:comment]"
+	fn load_mlb(&mut self, text: &str) -> Result<(), LabelErr> {
+		for (i, line) in text.lines().enumerate() {
+			let fields: Vec<&str> = line.splitn(4, ':').collect();
+			if fields.len() < 3 {
+				continue;
+			}
+
+			let addr = parse_range(fields[1]).ok_or(LabelErr::InvalidLine(i + 1))?;
+			let name = fields[2].trim();
+			match fields[0] {
+				"P" | "NesPrgRom" => self.add_prg(addr, name),
+				"R" | "NesInternalRam" => self.add_cpu(addr as u16, name),
+				"S" | "W" | "NesSaveRam" | "NesWorkRam" => {
+					self.add_cpu(PRG_RAM_START + addr as u16, name)
+				}
+				"G" | "NesMemory" => self.add_cpu(addr as u16, name),
+				// e.g. CHR labels
+				_ => (),
+			}
+		}
+
+		Ok(())
+	}
+
+	// Only "seg" and "sym" lines with labels are used. The PRG ROM offset of a symbol is taken
+	// from the offset of its segment in the output file, which is assumed to be an iNES file.
+	fn load_dbg(&mut self, text: &str) -> Result<(), LabelErr> {
+		// id -> (start address, offset in the PRG ROM)
+		let mut segments: HashMap<usize, (usize, Option<usize>)> = HashMap::new();
+		let mut symbols = Vec::new();
+
+		for (i, line) in text.lines().enumerate() {
+			let (kind, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+			let fields = dbg_fields(rest);
+			let num = |key: &str| fields.get(key).and_then(|v| parse_hex(v));
+			let dec = |key: &str| fields.get(key).and_then(|v| v.parse::<usize>().ok());
+
+			match kind {
+				"seg" => {
+					let id = dec("id").ok_or(LabelErr::InvalidLine(i + 1))?;
+					let start = num("start").ok_or(LabelErr::InvalidLine(i + 1))?;
+					let offset = dec("ooffs").and_then(|o| o.checked_sub(INES_HEADER_SIZE));
+					segments.insert(id, (start, offset));
+				}
+				"sym" if fields.get("type") == Some(&"lab") => {
+					let name = fields.get("name").ok_or(LabelErr::InvalidLine(i + 1))?;
+					let val = num("val").ok_or(LabelErr::InvalidLine(i + 1))?;
+					symbols.push((String::from(*name), val, dec("seg")));
+				}
+				_ => (),
+			}
+		}
+
+		for (name, val, seg) in symbols {
+			match seg.and_then(|s| segments.get(&s)) {
+				Some(&(start, Some(offset))) => self.add_prg(offset + val - start, &name),
+				_ => self.add_cpu(val as u16, &name),
+			}
+		}
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::mem::FlatBus;
+
+	#[test]
+	fn formats() {
+		let mut labels = Labels::default();
+		labels.load_nl("$0010#counter#frames\n$C5F5#main#\n", None).unwrap();
+		labels.load_nl("$8010#bank1_init#\n", Some(1)).unwrap();
+		labels.load_mlb("P:7FFA:nmi:vector\nR:0200-02FF:oam\nW:0010:save\nbroken\n").unwrap();
+		labels
+			.load_dbg(concat!(
+				"seg\tid=0,name=\"CODE\",start=0x00C000,size=0x0100,addrsize=absolute,type=ro,",
+				"oname=\"game.nes\",ooffs=32784\n",
+				"seg\tid=1,name=\"BSS\",start=0x000300,size=0x0100,addrsize=absolute,type=rw\n",
+				"sym\tid=0,name=\"reset\",addrsize=absolute,scope=0,def=1,val=0xC010,seg=0,type=lab\n",
+				"sym\tid=1,name=\"buffer\",addrsize=absolute,scope=0,def=2,val=0x300,seg=1,type=lab\n",
+				"sym\tid=2,name=\"PPUCTRL\",addrsize=absolute,scope=0,def=3,val=0x2000,type=equ\n",
+			))
+			.unwrap();
+
+		assert_eq!(labels.prg.get(&0x4010).map(|n| n.as_str()), Some("bank1_init"));
+		assert_eq!(labels.prg.get(&0x7FFA).map(|n| n.as_str()), Some("nmi"));
+		assert_eq!(labels.prg.get(&0x8010).map(|n| n.as_str()), Some("reset"));
+		assert_eq!(labels.len(), 8);
+
+		// FlatBus maps no PRG ROM, so only the CPU labels are found
+		let bus = FlatBus::new();
+		assert_eq!(labels.name(&bus, 0x0810), Some("counter"));
+		assert_eq!(labels.name(&bus, 0x6010), Some("save"));
+		assert_eq!(labels.name(&bus, 0x0300), Some("buffer"));
+		assert_eq!(labels.name(&bus, 0x2000), None);
+	}
+}
@@ -0,0 +1,403 @@
+// Interactive debugger for the terminal. It runs the frames instead of Nes::run_frame() and is
+// asked before every instruction if it has to stop, because of a breakpoint, a watchpoint or the
+// end of a step. Once stopped, it reads commands from stdin until the emulation continues.
+// Watchpoints on reads and writes are checked by the memory map, so they stop after the
+// instruction which accessed the address.
+
+mod command;
+pub mod gdb;
+pub mod labels;
+pub mod profile;
+pub mod trace;
+
+use crate::mem::watch::{Access, AddrSpace};
+use crate::nes::{Nes, RunEvent};
+use command::{Breakpoint, Command};
+use std::io::{self, BufRead, Write};
+
+const JSR: u8 = 0x20;
+const RTS: u8 = 0x60;
+const RTI: u8 = 0x40;
+const JSR_LEN: u16 = 3;
+
+const DUMP_COLUMNS: usize = 16;
+
+#[derive(Clone, Copy)]
+enum RunMode {
+	// stop at the next instruction
+	Stop,
+	// run until a breakpoint or watchpoint is hit
+	Continue,
+	// instructions left
+	Step(usize),
+	// run until the JSR returns
+	Next {
+		ret: u16,
+		sp: u8,
+	},
+	// run until RTS or RTI leave the current stack frame
+	Finish {
+		sp: u8,
+	},
+	Scanline(usize),
+}
+
+struct BreakpointState {
+	bp: Breakpoint,
+	// the condition before the previous instruction, breaks without address are edge triggered
+	cond_held: bool,
+}
+
+pub struct Debugger {
+	breakpoints: Vec<BreakpointState>,
+	mode: RunMode,
+	resumed: bool, // the first instruction after stopping doesn't break again
+	prev_op: u8,
+	prev_scanline: usize,
+	last_line: String,
+	quit: bool,
+}
+
+impl Default for Debugger {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl Debugger {
+	// stops at the first instruction, so breakpoints can be set before the game starts
+	pub fn new() -> Self {
+		Self {
+			breakpoints: Vec::new(),
+			mode: RunMode::Stop,
+			resumed: false,
+			prev_op: 0,
+			prev_scanline: 0,
+			last_line: String::new(),
+			quit: false,
+		}
+	}
+
+	pub fn quit_requested(&self) -> bool {
+		self.quit
+	}
+
+	// same as Nes::run_frame(), but stops at breaks and runs the REPL
+	pub fn run_frame(&mut self, nes: &mut Nes) -> bool {
+		loop {
+			match nes.run_until(|nes| self.check(nes)) {
+				RunEvent::Break => {
+					self.repl(nes);
+					if self.quit {
+						return false;
+					}
+				}
+				ev => return ev == RunEvent::FbReady,
+			}
+		}
+	}
+
+	// called before every step, true if the emulation has to stop
+	fn check(&mut self, nes: &mut Nes) -> bool {
+		if !nes.at_instruction() {
+			return false;
+		}
+
+		let regs = nes.registers();
+		let (scanline, _) = nes.ppu_position();
+		let prev_op = self.prev_op;
+		let prev_scanline = self.prev_scanline;
+		self.prev_op = nes.peek(regs.pc as usize);
+		self.prev_scanline = scanline;
+
+		let mut stop = false;
+		for hit in nes.watchpoints().take_hits() {
+			println!("watchpoint: {:?}", hit);
+			stop = true;
+		}
+
+		if self.resumed {
+			self.resumed = false;
+			for state in self.breakpoints.iter_mut() {
+				state.cond_held = state.bp.cond.is_some_and(|c| c.eval(&regs));
+			}
+			return stop;
+		}
+
+		for (i, state) in self.breakpoints.iter_mut().enumerate() {
+			let cond = state.bp.cond.is_none_or(|c| c.eval(&regs));
+			let hit = match state.bp.addr {
+				Some(addr) => addr == regs.pc && cond,
+				None => cond && !state.cond_held,
+			};
+			state.cond_held = cond;
+
+			if hit {
+				println!("breakpoint {}: {:?}", i, state.bp);
+				stop = true;
+			}
+		}
+
+		if nes.watchpoints().matches(AddrSpace::Cpu, Access::Execute, regs.pc as usize) {
+			println!("watchpoint: CPU execute ${:04X}", regs.pc);
+			stop = true;
+		}
+
+		stop |= match self.mode {
+			RunMode::Stop => true,
+			RunMode::Continue => false,
+			RunMode::Step(left) => {
+				self.mode = RunMode::Step(left - 1);
+				left <= 1
+			}
+			RunMode::Next {
+				ret,
+				sp,
+			} => regs.pc == ret && regs.sp == sp,
+			RunMode::Finish {
+				sp,
+			} => (prev_op == RTS || prev_op == RTI) && regs.sp > sp,
+			RunMode::Scanline(sl) => scanline == sl && prev_scanline != sl,
+		};
+
+		stop
+	}
+
+	fn resume(&mut self, mode: RunMode) {
+		self.mode = mode;
+		self.resumed = true;
+	}
+
+	fn repl(&mut self, nes: &mut Nes) {
+		self.print_position(nes);
+
+		let stdin = io::stdin();
+		loop {
+			print!("(dbg) ");
+			io::stdout().flush().unwrap_or(());
+
+			let mut line = String::new();
+			match stdin.lock().read_line(&mut line) {
+				// stdin is closed
+				Ok(0) | Err(_) => {
+					self.quit = true;
+					return;
+				}
+				Ok(_) => (),
+			}
+
+			// an empty line repeats the last command, e.g. to keep stepping
+			if line.trim().is_empty() {
+				line = self.last_line.clone();
+			} else {
+				self.last_line = line.clone();
+			}
+
+			match command::parse(&line) {
+				Ok(cmd) => {
+					if self.execute(nes, cmd) {
+						return;
+					}
+				}
+				Err(e) => println!("{:?}", e),
+			}
+		}
+	}
+
+	// returns true if the emulation continues
+	fn execute(&mut self, nes: &mut Nes, cmd: Command) -> bool {
+		let regs = nes.registers();
+		match cmd {
+			Command::Continue => self.resume(RunMode::Continue),
+			Command::Step(cnt) => self.resume(RunMode::Step(cnt.max(1))),
+			Command::Next => {
+				if nes.peek(regs.pc as usize) == JSR {
+					self.resume(RunMode::Next {
+						ret: regs.pc.wrapping_add(JSR_LEN),
+						sp: regs.sp,
+					});
+				} else {
+					self.resume(RunMode::Step(1));
+				}
+			}
+			Command::Finish => self.resume(RunMode::Finish {
+				sp: regs.sp,
+			}),
+			Command::Scanline(sl) => self.resume(RunMode::Scanline(sl)),
+			Command::Quit => {
+				self.quit = true;
+				return true;
+			}
+			_ => {
+				self.inspect(nes, cmd);
+				return false;
+			}
+		}
+
+		true
+	}
+
+	// commands which don't continue the emulation
+	fn inspect(&mut self, nes: &mut Nes, cmd: Command) {
+		match cmd {
+			Command::Break(bp) => {
+				println!("breakpoint {}: {:?}", self.breakpoints.len(), bp);
+				self.breakpoints.push(BreakpointState {
+					bp,
+					cond_held: false,
+				});
+			}
+			Command::Delete(idx) if idx < self.breakpoints.len() => {
+				self.breakpoints.remove(idx);
+			}
+			Command::Delete(idx) => println!("no breakpoint {}", idx),
+			Command::Watch(wp) => {
+				println!("watchpoint {}: {:?}", nes.watchpoints().list().len(), wp);
+				nes.watchpoints().add(wp);
+			}
+			Command::Unwatch(idx) if nes.watchpoints().remove(idx).is_none() => {
+				println!("no watchpoint {}", idx);
+			}
+			Command::List => {
+				for (i, state) in self.breakpoints.iter().enumerate() {
+					println!("breakpoint {}: {:?}", i, state.bp);
+				}
+				for (i, wp) in nes.watchpoints().list().iter().enumerate() {
+					println!("watchpoint {}: {:?}", i, wp);
+				}
+			}
+			Command::Registers => self.print_registers(nes),
+			Command::Memory(space, addr, len) => Self::dump(nes, space, addr, len),
+			Command::Disassemble(addr, cnt) => {
+				let mut addr = addr.unwrap_or(nes.registers().pc);
+				for _ in 0..cnt {
+					if let Some(label) = nes.label(addr) {
+						println!("{}:", label);
+					}
+					let (line, next) = nes.disassemble(addr);
+					println!("{}", line);
+					addr = next;
+				}
+			}
+			Command::Help => println!("{}", command::HELP),
+			_ => (),
+		}
+	}
+
+	fn print_position(&self, nes: &Nes) {
+		if let Some(label) = nes.label(nes.registers().pc) {
+			println!("{}:", label);
+		}
+		match nes.trace_line() {
+			Some(line) => println!("{}", line),
+			None => println!("${:04X}", nes.registers().pc),
+		}
+	}
+
+	fn print_registers(&self, nes: &Nes) {
+		const FLAGS: &str = "NV-BDIZC";
+
+		let regs = nes.registers();
+		let flags: String = FLAGS
+			.chars()
+			.enumerate()
+			.map(|(i, c)| {
+				if (regs.p >> (7 - i)) & 0x01 > 0 {
+					c
+				} else {
+					'.'
+				}
+			})
+			.collect();
+		let (scanline, dot) = nes.ppu_position();
+
+		println!(
+			"PC:${:04X} A:${:02X} X:${:02X} Y:${:02X} SP:${:02X} P:${:02X} [{}]",
+			regs.pc, regs.a, regs.x, regs.y, regs.sp, regs.p, flags
+		);
+		println!("scanline {}, dot {}, CPU cycle {}", scanline, dot, nes.cycles());
+	}
+
+	fn dump(nes: &mut Nes, space: AddrSpace, addr: usize, len: usize) {
+		let end = match space {
+			AddrSpace::Cpu => 0x10000,
+			AddrSpace::Ppu => 0x4000,
+		};
+
+		let mut line = String::new();
+		for a in addr..(addr + len).min(end) {
+			if a == addr || a % DUMP_COLUMNS == 0 {
+				if !line.is_empty() {
+					println!("{}", line);
+				}
+				line = format!("${:04X}:", a);
+				// keep the columns aligned if the start isn't
+				line.push_str(&"   ".repeat(a % DUMP_COLUMNS));
+			}
+
+			let val = match space {
+				AddrSpace::Cpu => nes.peek(a),
+				AddrSpace::Ppu => nes.ppu_peek(a),
+			};
+			line.push_str(&format!(" {:02X}", val));
+		}
+
+		if !line.is_empty() {
+			println!("{}", line);
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::nes::LoadOptions;
+
+	// nestest in its automation mode, see doc/misc/nestest.log
+	fn start() -> (Nes, Debugger) {
+		let mut nes = Nes::new("rom/test/nestest.nes", &LoadOptions::default()).unwrap();
+		nes.start_at(0xC000);
+		(nes, Debugger::new())
+	}
+
+	fn run(dbg: &mut Debugger, nes: &mut Nes, line: &str) -> u16 {
+		assert!(dbg.execute(nes, command::parse(line).unwrap()));
+		while nes.run_until(|nes| dbg.check(nes)) != RunEvent::Break {}
+		nes.registers().pc
+	}
+
+	#[test]
+	fn stepping() {
+		let (mut nes, mut dbg) = start();
+		assert!(!dbg.execute(&mut nes, command::parse("b c5fd").unwrap()));
+
+		assert_eq!(run(&mut dbg, &mut nes, "c"), 0xC5FD);
+		assert_eq!(run(&mut dbg, &mut nes, "n"), 0xC600);
+		assert_eq!(run(&mut dbg, &mut nes, "s"), 0xC7DB);
+		assert_eq!(run(&mut dbg, &mut nes, "f"), 0xC603);
+		assert_eq!(run(&mut dbg, &mut nes, "s 2"), 0xC886);
+	}
+
+	#[test]
+	fn watchpoints() {
+		let (mut nes, mut dbg) = start();
+
+		// STX $00 at $C5F7
+		assert!(!dbg.execute(&mut nes, command::parse("w 0 w").unwrap()));
+		assert_eq!(run(&mut dbg, &mut nes, "c"), 0xC5F9);
+
+		assert!(!dbg.execute(&mut nes, command::parse("u 0").unwrap()));
+		assert!(!dbg.execute(&mut nes, command::parse("w c72d x").unwrap()));
+		assert_eq!(run(&mut dbg, &mut nes, "c"), 0xC72D);
+	}
+
+	#[test]
+	fn conditions() {
+		let (mut nes, mut dbg) = start();
+
+		// the first instruction which sets A
+		assert!(!dbg.execute(&mut nes, command::parse("b if a != 0").unwrap()));
+		let pc = run(&mut dbg, &mut nes, "c");
+		assert_ne!(nes.registers().a, 0, "stopped at ${:04X}", pc);
+	}
+}
@@ -0,0 +1,290 @@
+// Cycle profiler of the CPU. The stack of the called routines follows the changes of the control
+// flow (JSR, interrupts, RTS and RTI) and every cycle is accounted to the routine on top of it. A
+// routine is identified by its address and its offset in the PRG ROM, so routines of different
+// banks at the same address are kept apart. Returns pop the frames by the stack pointer, so an RTS
+// used as a jump (after pushing the target) or a stack which is reset by TXS doesn't confuse it.
+// The cycles of the NMI handler are also summed up per frame. The stacks can be written as folded
+// stacks, the input of flamegraph.pl and inferno, e.g. "main;nmi;$C5F5@5F5 1234".
+
+use crate::cpu::Flow;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+const ROOT_NAME: &str = "main";
+const SUMMARY_CNT: usize = 10;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Routine {
+	pub addr: u16,
+	pub prg_offset: Option<usize>,
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct RoutineStat {
+	pub calls: u64,
+	// cycles of the finished calls with and without the called routines
+	pub inclusive: u64,
+	pub exclusive: u64,
+}
+
+struct Frame {
+	// none for the code which isn't called, e.g. the main loop
+	routine: Option<Routine>,
+	// the stack pointer after the return
+	ret_sp: u8,
+	start: u64,
+	exclusive: u64,
+	// exclusive cycles which aren't in the folded stacks yet
+	pending: u64,
+	// the frame in which an NMI handler was called
+	nmi_frame: Option<u64>,
+}
+
+pub struct Profiler {
+	cycles: u64,
+	stack: Vec<Frame>,
+	stats: HashMap<Routine, RoutineStat>,
+	folded: HashMap<Vec<Option<Routine>>, u64>,
+	// index = frame
+	nmi_cycles: Vec<u64>,
+}
+
+impl Routine {
+	// the label or the address with the offset in the PRG ROM, e.g. "$C5F5@5F5"
+	pub fn name(&self, label: &impl Fn(u16) -> Option<String>) -> String {
+		match (label(self.addr), self.prg_offset) {
+			(Some(l), _) => l,
+			(None, Some(offset)) => format!("${:04X}@{:X}", self.addr, offset),
+			(None, None) => format!("${:04X}", self.addr),
+		}
+	}
+}
+
+impl Frame {
+	fn new(routine: Option<Routine>, ret_sp: u8, start: u64, nmi_frame: Option<u64>) -> Self {
+		Self {
+			routine,
+			ret_sp,
+			start,
+			exclusive: 0,
+			pending: 0,
+			nmi_frame,
+		}
+	}
+}
+
+impl Default for Profiler {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl Profiler {
+	pub fn new() -> Self {
+		Self {
+			cycles: 0,
+			stack: vec![Frame::new(None, 0, 0, None)],
+			stats: HashMap::new(),
+			folded: HashMap::new(),
+			nmi_cycles: Vec::new(),
+		}
+	}
+
+	// called after every step with the registers and the frame after it
+	pub fn observe(
+		&mut self,
+		flow: Flow,
+		cycles: u64,
+		pc: u16,
+		sp: u8,
+		prg_offset: Option<usize>,
+		frame: u64,
+	) {
+		// JSR and the interrupts are accounted to the caller, RTS and RTI to the callee
+		self.cycles += cycles;
+		let top = self.stack.last_mut().unwrap();
+		top.exclusive += cycles;
+		top.pending += cycles;
+
+		match flow {
+			Flow::Sequential => (),
+			Flow::Call | Flow::Nmi | Flow::Irq => {
+				let routine = Routine {
+					addr: pc,
+					prg_offset,
+				};
+				// JSR pushes the return address, an interrupt also P
+				let (pushed, nmi_frame) = match flow {
+					Flow::Call => (2, None),
+					Flow::Nmi => (3, Some(frame)),
+					_ => (3, None),
+				};
+
+				self.flush();
+				self.stats.entry(routine).or_default().calls += 1;
+				self.stack.push(Frame::new(
+					Some(routine),
+					sp.wrapping_add(pushed),
+					self.cycles,
+					nmi_frame,
+				));
+			}
+			Flow::Return => {
+				while self.stack.len() > 1 && self.stack.last().unwrap().ret_sp <= sp {
+					self.pop();
+				}
+			}
+			Flow::Reset => {
+				while self.stack.len() > 1 {
+					self.pop();
+				}
+			}
+		}
+	}
+
+	// moves the pending cycles of the top frame into the folded stacks
+	fn flush(&mut self) {
+		let top = self.stack.last_mut().unwrap();
+		if top.pending == 0 {
+			return;
+		}
+
+		let pending = top.pending;
+		top.pending = 0;
+		let path: Vec<Option<Routine>> = self.stack.iter().map(|f| f.routine).collect();
+		*self.folded.entry(path).or_default() += pending;
+	}
+
+	fn pop(&mut self) {
+		self.flush();
+		let frame = self.stack.pop().unwrap();
+		let inclusive = self.cycles - frame.start;
+
+		if let Some(routine) = frame.routine {
+			let stat = self.stats.entry(routine).or_default();
+			stat.inclusive += inclusive;
+			stat.exclusive += frame.exclusive;
+		}
+
+		if let Some(f) = frame.nmi_frame {
+			let f = f as usize;
+			if self.nmi_cycles.len() <= f {
+				self.nmi_cycles.resize(f + 1, 0);
+			}
+			self.nmi_cycles[f] += inclusive;
+		}
+	}
+
+	pub fn stats(&self) -> &HashMap<Routine, RoutineStat> {
+		&self.stats
+	}
+
+	// cycles of the finished NMI handlers per frame
+	pub fn nmi_cycles(&self) -> &[u64] {
+		&self.nmi_cycles
+	}
+
+	// the folded stacks with the exclusive cycles, including the routines which didn't return yet
+	pub fn folded(&mut self, label: impl Fn(u16) -> Option<String>) -> Vec<String> {
+		self.flush();
+		let mut lines: Vec<String> = self
+			.folded
+			.iter()
+			.map(|(path, cycles)| {
+				let names: Vec<String> = path
+					.iter()
+					.map(|r| r.map_or(String::from(ROOT_NAME), |r| r.name(&label)))
+					.collect();
+				format!("{} {}", names.join(";"), cycles)
+			})
+			.collect();
+		lines.sort();
+		lines
+	}
+
+	pub fn save_folded(
+		&mut self,
+		file: &str,
+		label: impl Fn(u16) -> Option<String>,
+	) -> io::Result<()> {
+		let mut out = BufWriter::new(File::create(file)?);
+		for line in self.folded(label) {
+			writeln!(out, "{}", line)?;
+		}
+		out.flush()
+	}
+
+	// the routines with the most exclusive cycles and the cycles of the NMI handler per frame
+	pub fn summary(&self, label: impl Fn(u16) -> Option<String>) -> Vec<String> {
+		let mut stats: Vec<(&Routine, &RoutineStat)> = self.stats.iter().collect();
+		stats.sort_by_key(|(_, stat)| Reverse(stat.exclusive));
+
+		let mut lines = vec![format!(
+			"{:<24} {:>8} {:>12} {:>12}",
+			"routine", "calls", "inclusive", "exclusive"
+		)];
+		for (routine, stat) in stats.iter().take(SUMMARY_CNT) {
+			lines.push(format!(
+				"{:<24} {:>8} {:>12} {:>12}",
+				routine.name(&label),
+				stat.calls,
+				stat.inclusive,
+				stat.exclusive
+			));
+		}
+
+		let nmi: Vec<u64> = self.nmi_cycles.iter().copied().filter(|&c| c > 0).collect();
+		if !nmi.is_empty() {
+			lines.push(format!(
+				"NMI per frame: {} frames, {} cycles on average, {} at most",
+				nmi.len(),
+				nmi.iter().sum::<u64>() / nmi.len() as u64,
+				nmi.iter().max().unwrap()
+			));
+		}
+
+		lines
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	const NO_LABEL: fn(u16) -> Option<String> = |_| None;
+
+	#[test]
+	fn call_stack() {
+		let mut p = Profiler::new();
+		p.observe(Flow::Sequential, 10, 0x8000, 0xFD, Some(0), 0);
+		// JSR $9000
+		p.observe(Flow::Call, 6, 0x9000, 0xFB, Some(0x1000), 0);
+		p.observe(Flow::Sequential, 20, 0x9002, 0xFB, Some(0x1002), 0);
+		// an RTS used as a jump doesn't leave the routine
+		p.observe(Flow::Return, 6, 0x9100, 0xFB, Some(0x1100), 0);
+		// NMI in the routine
+		p.observe(Flow::Nmi, 7, 0xC000, 0xF8, Some(0x4000), 0);
+		p.observe(Flow::Sequential, 100, 0xC010, 0xF8, Some(0x4010), 0);
+		p.observe(Flow::Return, 6, 0x9100, 0xFB, Some(0x1100), 1);
+		p.observe(Flow::Return, 6, 0x8003, 0xFD, Some(3), 1);
+
+		let sub = p.stats()[&Routine {
+			addr: 0x9000,
+			prg_offset: Some(0x1000),
+		}];
+		assert_eq!((sub.calls, sub.inclusive, sub.exclusive), (1, 145, 39));
+		let nmi = p.stats()[&Routine {
+			addr: 0xC000,
+			prg_offset: Some(0x4000),
+		}];
+		assert_eq!((nmi.inclusive, nmi.exclusive), (106, 106));
+		assert_eq!(p.nmi_cycles(), [106]);
+
+		assert_eq!(
+			p.folded(NO_LABEL),
+			["main 16", "main;$9000@1000 39", "main;$9000@1000;$C000@4000 106"]
+		);
+	}
+}
@@ -0,0 +1,193 @@
+// Records the executed instructions, either to a file or into a ring buffer in memory which keeps
+// only the last instructions. The ring buffer is dumped to stderr if the emulation panics, e.g. on
+// an access of an unmapped address, so the history which led to it isn't lost. Every line is the
+// trace line of Nintendulator (see Nes::trace_line()) followed by the frame and the flags of P,
+// e.g. "... PPU: 21,  0 CYC:7 F:0 nv-bdIzc".
+// The recording can start and stop by a condition and be limited to a range of addresses.
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::thread;
+
+const FLAG_NAMES: &[u8; 8] = b"NV-BDIZC";
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum TraceCond {
+	// the frame is reached
+	Frame(u64),
+	// a breakpoint, the address is executed for the first time
+	Pc(u16),
+}
+
+#[derive(Default)]
+pub struct TraceOptions {
+	// starts right away if none is set
+	pub start: Option<TraceCond>,
+	// runs until the emulator quits if none is set, the instruction which stops isn't recorded
+	pub stop: Option<TraceCond>,
+	// only instructions in the range (inclusive) are recorded, e.g. to skip the NMI handler
+	pub pc_range: Option<(u16, u16)>,
+}
+
+enum Output {
+	File(BufWriter<File>),
+	// lines and capacity
+	Ring(VecDeque<String>, usize),
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum State {
+	Waiting,
+	Running,
+	Stopped,
+}
+
+pub struct Tracer {
+	out: Output,
+	opts: TraceOptions,
+	state: State,
+}
+
+impl TraceCond {
+	// "frame:<n>" or "pc:<hex address>"
+	pub fn parse(s: &str) -> Option<Self> {
+		let (kind, val) = s.split_once(':')?;
+		match kind {
+			"frame" => val.parse().ok().map(Self::Frame),
+			"pc" => u16::from_str_radix(val.trim_start_matches('$'), 16).ok().map(Self::Pc),
+			_ => None,
+		}
+	}
+
+	fn met(&self, pc: u16, frame: u64) -> bool {
+		match *self {
+			Self::Frame(f) => frame >= f,
+			Self::Pc(addr) => pc == addr,
+		}
+	}
+}
+
+// the flags of P, upper case if set
+pub fn flags(p: u8) -> String {
+	FLAG_NAMES
+		.iter()
+		.enumerate()
+		.map(|(i, &name)| match (name, p & (0x80 >> i) != 0) {
+			(b'-', _) => '-',
+			(_, true) => name as char,
+			(_, false) => name.to_ascii_lowercase() as char,
+		})
+		.collect()
+}
+
+impl Tracer {
+	pub fn to_file(file: &str, opts: TraceOptions) -> io::Result<Self> {
+		let out = Output::File(BufWriter::new(File::create(file)?));
+		Ok(Self::new(out, opts))
+	}
+
+	// keeps the last instructions in memory
+	pub fn ring(capacity: usize, opts: TraceOptions) -> Self {
+		Self::new(Output::Ring(VecDeque::with_capacity(capacity), capacity), opts)
+	}
+
+	fn new(out: Output, opts: TraceOptions) -> Self {
+		let state = if opts.start.is_some() {
+			State::Waiting
+		} else {
+			State::Running
+		};
+
+		Self {
+			out,
+			opts,
+			state,
+		}
+	}
+
+	// checks the conditions before the instruction at pc, returns if it has to be recorded
+	pub fn wants(&mut self, pc: u16, frame: u64) -> bool {
+		if self.state == State::Waiting && self.opts.start.is_none_or(|c| c.met(pc, frame)) {
+			self.state = State::Running;
+		}
+		if self.state == State::Running && self.opts.stop.is_some_and(|c| c.met(pc, frame)) {
+			self.state = State::Stopped;
+		}
+
+		self.state == State::Running
+			&& self.opts.pc_range.is_none_or(|(start, end)| (start..=end).contains(&pc))
+	}
+
+	pub fn record(&mut self, line: String) {
+		match &mut self.out {
+			Output::File(f) => writeln!(f, "{}", line).unwrap_or(()),
+			Output::Ring(lines, capacity) => {
+				if lines.len() == *capacity {
+					lines.pop_front();
+				}
+				lines.push_back(line);
+			}
+		}
+	}
+
+	// the content of the ring buffer, oldest first, empty for a file
+	pub fn lines(&self) -> impl Iterator<Item = &str> {
+		let lines = match &self.out {
+			Output::Ring(lines, _) => Some(lines.iter().map(|l| l.as_str())),
+			Output::File(_) => None,
+		};
+		lines.into_iter().flatten()
+	}
+
+	pub fn dump<W: Write>(&self, out: &mut W) -> io::Result<()> {
+		for line in self.lines() {
+			writeln!(out, "{}", line)?;
+		}
+		Ok(())
+	}
+}
+
+impl Drop for Tracer {
+	// the Nes is dropped while the stack unwinds, a file is flushed by its BufWriter
+	fn drop(&mut self) {
+		if thread::panicking() && self.lines().next().is_some() {
+			eprintln!("Last executed instructions:");
+			self.dump(&mut io::stderr()).unwrap_or(());
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::nes::{LoadOptions, Nes};
+
+	#[test]
+	fn ring() {
+		let mut nes = Nes::new("rom/test/nestest.nes", &LoadOptions::default()).unwrap();
+		let opts = TraceOptions {
+			// the subroutine which tests the branches
+			start: Some(TraceCond::Pc(0xC72D)),
+			stop: Some(TraceCond::Pc(0xC7DB)),
+			// only the subroutine, not the code after it returned
+			pc_range: Some((0xC72D, 0xC7DA)),
+		};
+		nes.set_tracer(Some(Tracer::ring(4, opts)));
+		nes.start_at(0xC000);
+		for _ in 0..200 {
+			nes.step();
+		}
+
+		let lines: Vec<&str> = nes.tracer().unwrap().lines().collect();
+		assert_eq!(lines.len(), 4);
+		assert!(lines[3].starts_with("C7DA  60        RTS"), "{}", lines[3]);
+		assert!(lines[3].ends_with("CYC:155 F:0 Nv-bdIzc"), "{}", lines[3]);
+	}
+
+	#[test]
+	fn flag_names() {
+		assert_eq!(flags(0x24), "nv-bdIzc");
+		assert_eq!(flags(0xC3), "NV-bdiZC");
+	}
+}
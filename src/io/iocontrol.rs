@@ -20,6 +20,10 @@ struct Controller {
 pub struct IOControl {
 	ctrlr1: Controller,
 	ctrlr2: Controller,
+
+	vs_system: bool,
+	dip_switches: u8,
+	coins: [bool; 2],
 }
 
 impl Controller {
@@ -32,18 +36,17 @@ impl Controller {
 		}
 	}
 
+	// bit 0, the serial data of the shift register
 	pub fn read(&mut self) -> u8 {
-		const PREV_BYTE_ON_BUS: u8 = 0x40;
-
 		if !self.connected {
-			PREV_BYTE_ON_BUS | 0x00
+			0x00
 		} else if self.read_cnt < 8 {
-			let ret = (self.btns_latched & 0x01) | PREV_BYTE_ON_BUS;
+			let ret = self.btns_latched & 0x01;
 			self.read_cnt += 1;
 			self.btns_latched >>= 1;
 			ret
 		} else {
-			PREV_BYTE_ON_BUS | 0x01
+			0x01
 		}
 	}
 
@@ -52,11 +55,19 @@ impl Controller {
 		self.read_cnt = 0;
 	}
 
-	pub fn buttons_update(&mut self, jp: &JoyPad) {
+	pub fn buttons_update(&mut self, jp: &JoyPad, vs_system: bool) {
+		// The Vs. System reports its start buttons (1/2) where the NES reports select and the
+		// buttons 3/4 where the NES reports start. Swap them, so start stays start.
+		let (start, select) = if vs_system {
+			(jp.select, jp.start)
+		} else {
+			(jp.start, jp.select)
+		};
+
 		self.btns_state = (jp.a as u8) << IDX_A;
 		self.btns_state |= (jp.b as u8) << IDX_B;
-		self.btns_state |= (jp.start as u8) << IDX_START;
-		self.btns_state |= (jp.select as u8) << IDX_SELECT;
+		self.btns_state |= (start as u8) << IDX_START;
+		self.btns_state |= (select as u8) << IDX_SELECT;
 		self.btns_state |= (jp.up as u8) << IDX_UP;
 		self.btns_state |= (jp.down as u8) << IDX_DOWN;
 		self.btns_state |= (jp.left as u8) << IDX_LEFT;
@@ -69,20 +80,63 @@ impl IOControl {
 		Self {
 			ctrlr1: Controller::new(c1_connected),
 			ctrlr2: Controller::new(c2_connected),
+
+			vs_system: false,
+			dip_switches: 0,
+			coins: [false; 2],
 		}
 	}
 
+	// The Vs. System shares $4016 and $4017 between the controllers, the coin slots and the
+	// 8 DIP switches which configure the game (difficulty, lives, coins per credit, ...).
+	pub fn set_vs_system(&mut self, dip_switches: u8) {
+		self.vs_system = true;
+		self.dip_switches = dip_switches;
+	}
+
 	pub fn refresh_controller(&mut self, ctrl1: &JoyPad, ctrl2: &JoyPad) {
-		self.ctrlr1.buttons_update(ctrl1);
-		self.ctrlr2.buttons_update(ctrl2);
+		self.ctrlr1.buttons_update(ctrl1, self.vs_system);
+		self.ctrlr2.buttons_update(ctrl2, self.vs_system);
+		self.coins = [ctrl1.coin, ctrl2.coin];
+	}
+
+	// The NES only drives the lower 5 bits of the controller ports, the upper ones keep the value of
+	// the data bus, usually $40 from the high byte of the address.
+	fn open_bus_bits(val: u8, cpu_bus: u8) -> u8 {
+		const OPEN_BUS_MASK: u8 = 0xE0;
+		(val & !OPEN_BUS_MASK) | (cpu_bus & OPEN_BUS_MASK)
 	}
 
-	pub fn read_controller1(&mut self) -> u8 {
-		self.ctrlr1.read()
+	pub fn read_controller1(&mut self, cpu_bus: u8) -> u8 {
+		const DIP_MASK: u8 = 0x03;
+		const DIP_IDX: usize = 3;
+		const COIN1_IDX: usize = 5;
+		const COIN2_IDX: usize = 6;
+
+		let ret = self.ctrlr1.read();
+		if !self.vs_system {
+			return Self::open_bus_bits(ret, cpu_bus);
+		}
+
+		// bit 7 is 0 for the main CPU, the service button (bit 2) is not supported
+		let mut ret = ret & 0x01;
+		ret |= (self.dip_switches & DIP_MASK) << DIP_IDX;
+		ret |= (self.coins[0] as u8) << COIN1_IDX;
+		ret |= (self.coins[1] as u8) << COIN2_IDX;
+
+		ret
 	}
 
-	pub fn read_controller2(&mut self) -> u8 {
-		self.ctrlr2.read()
+	pub fn read_controller2(&mut self, cpu_bus: u8) -> u8 {
+		const DIP_MASK: u8 = 0xFC;
+
+		let ret = self.ctrlr2.read();
+		if !self.vs_system {
+			return Self::open_bus_bits(ret, cpu_bus);
+		}
+
+		// DIP switches 3 - 8 are mapped to the bits 2 - 7
+		(ret & 0x01) | (self.dip_switches & DIP_MASK)
 	}
 
 	pub fn reload_controller(&mut self, val: u8) {
@@ -10,4 +10,5 @@ pub struct JoyPad {
 	pub select: bool,
 	pub a: bool,
 	pub b: bool,
+	pub coin: bool, // coin slot of the player, only used by the Vs. System
 }
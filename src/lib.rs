@@ -1,8 +1,14 @@
+pub mod apu;
+pub mod archive;
 pub mod cartridge;
 pub mod cpu;
+pub mod debugger;
 pub mod io;
 pub mod mem;
 pub mod nes;
+pub mod patch;
 pub mod ppu;
+pub mod region;
 pub mod sdl2_wrapper;
+pub mod test_rom;
 pub mod util;
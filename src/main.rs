@@ -1,11 +1,16 @@
 #![allow(dead_code)]
 
+mod apu;
+mod archive;
 mod cartridge;
 mod cpu;
+mod debugger;
 mod io;
 mod mem;
 mod nes;
+mod patch;
 mod ppu;
+mod region;
 mod sdl2_wrapper;
 mod util;
 
@@ -17,8 +22,16 @@ use std::sync::{
 use std::thread;
 use std::time::Instant;
 
+use debugger::gdb::GdbStub;
+use debugger::trace::{TraceCond, TraceOptions, Tracer};
+use debugger::Debugger;
 use io::JoyPad;
-use nes::Nes;
+use nes::LoadOptions;
+use ppu::model::PpuModel;
+use ppu::ntsc::{NtscFilter, NtscFilterSettings, NTSC_WIDTH};
+use ppu::palette::{NtscSettings, Palette};
+use ppu::ppu::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use region::Region;
 use sdl2_wrapper::engine;
 
 type ShFb = Arc<RwLock<Vec<u8>>>;
@@ -29,37 +42,187 @@ fn main() {
 	let (tx_tb, rx_tb): (Sender<ShFb>, Receiver<ShFb>) = mpsc::channel();
 	let (tx_joy, rx_joy): (Sender<[JoyPad; 2]>, Receiver<[JoyPad; 2]>) = mpsc::channel();
 
-	let args: Vec<String> = env::args().collect();
-	if args.len() < 2 {
-		panic!("Please pass the path to the desired ROM!");
+	let mut rom_file: Option<String> = None;
+	let mut opts = LoadOptions::default();
+	let mut trace_file: Option<String> = None;
+	let mut trace_ring: Option<usize> = None;
+	let mut trace_opts = TraceOptions::default();
+	let mut label_files: Vec<String> = Vec::new();
+	let mut cdl_file: Option<String> = None;
+	let mut profile_file: Option<String> = None;
+	let mut palette: Option<Palette> = None;
+	let mut tv = NtscSettings::default();
+	let mut ntsc_filter: Option<String> = None;
+	let mut debugger: Option<Debugger> = None;
+	let mut gdb_port: Option<String> = None;
+
+	let mut args = env::args().skip(1);
+	while let Some(arg) = args.next() {
+		match arg.as_str() {
+			"--patch" => {
+				opts.patch_file =
+					Some(args.next().expect("Please pass the path to the patch file!"))
+			}
+			"--vs-ppu" => {
+				let name = args.next().expect("Please pass the PPU of the Vs. System!");
+				opts.vs_ppu = Some(PpuModel::from_name(name.as_str()).expect("Unknown PPU!"));
+			}
+			"--dip" => {
+				let dip = args.next().expect("Please pass the DIP switches of the Vs. System!");
+				opts.vs_dip_switches = dip.parse().expect("The DIP switches have to be a number!");
+			}
+			"--region" => {
+				let name = args.next().expect("Please pass the region (ntsc, pal or dendy)!");
+				opts.region = Some(Region::from_name(name.as_str()).expect("Unknown region!"));
+			}
+			"--entry" => {
+				opts.archive_entry =
+					Some(args.next().expect("Please pass the name of the archive entry!"))
+			}
+			"--trace" => {
+				trace_file = Some(args.next().expect("Please pass the path to the trace file!"))
+			}
+			"--trace-ring" => {
+				let cnt = args.next().expect("Please pass the number of instructions to keep!");
+				trace_ring =
+					Some(cnt.parse().expect("The number of instructions has to be a number!"));
+			}
+			"--trace-start" => {
+				let cond = args.next().expect("Please pass the start condition of the trace!");
+				trace_opts.start = Some(TraceCond::parse(&cond).expect("Unknown trace condition!"));
+			}
+			"--trace-stop" => {
+				let cond = args.next().expect("Please pass the stop condition of the trace!");
+				trace_opts.stop = Some(TraceCond::parse(&cond).expect("Unknown trace condition!"));
+			}
+			"--trace-range" => {
+				let range = args.next().expect("Please pass the address range of the trace!");
+				let addr = |a: &str| u16::from_str_radix(a.trim_start_matches('$'), 16).ok();
+				let range =
+					range.split_once('-').and_then(|(start, end)| Some((addr(start)?, addr(end)?)));
+				trace_opts.pc_range =
+					Some(range.expect("The address range has to be <start>-<end>!"));
+			}
+			"--labels" => {
+				label_files.push(args.next().expect("Please pass the path to the label file!"))
+			}
+			"--palette" => {
+				let file = args.next().expect("Please pass the path to the palette file!");
+				palette = Some(Palette::load(file.as_str()).expect("Unable to load the palette!"));
+			}
+			"--ntsc-palette" => {
+				let settings =
+					args.next().expect("Please pass hue, saturation, contrast, brightness!");
+				tv = NtscSettings::parse(&settings).expect("Invalid NTSC settings!");
+				palette = Some(Palette::ntsc(&tv));
+			}
+			"--ntsc-filter" => {
+				ntsc_filter =
+					Some(args.next().expect("Please pass sharpness, fringing, artifacts!"))
+			}
+			"--profile" => {
+				profile_file = Some(args.next().expect("Please pass the path to the profile file!"))
+			}
+			"--cdl" => cdl_file = Some(args.next().expect("Please pass the path to the CDL file!")),
+			"--debug" => debugger = Some(Debugger::new()),
+			"--gdb" => gdb_port = Some(args.next().expect("Please pass the port of the GDB stub!")),
+			_ => rom_file = Some(arg),
+		}
 	}
 
-	let mut nes = nes::Nes::new(args[1].as_str()).unwrap();
+	let rom_file = rom_file.expect("Please pass the path to the desired ROM!");
+	let mut nes = nes::Nes::new(rom_file.as_str(), &opts).unwrap();
+	if let Some(pf) = nes.patch_file() {
+		println!("Patch applied: {}", pf);
+	}
+	// without a file the trace is only kept in memory and printed if the emulator panics
+	let tracer = match (trace_file, trace_ring) {
+		(Some(tf), _) => Some(
+			Tracer::to_file(tf.as_str(), trace_opts).expect("Unable to create the trace file!"),
+		),
+		(None, Some(cnt)) => Some(Tracer::ring(cnt, trace_opts)),
+		(None, None) => None,
+	};
+	nes.set_tracer(tracer);
+	if let Some(p) = palette {
+		nes.set_palette(p);
+	}
+	if let Some(pf) = profile_file {
+		nes.profile_to(pf.as_str());
+	}
+	if let Some(cf) = cdl_file {
+		nes.log_code_data(cf.as_str()).expect("Unable to load the CDL file!");
+	}
+	for lf in label_files {
+		nes.load_labels(lf.as_str()).expect("Unable to load the label file!");
+	}
+
+	// the emulation starts once gdb is connected, so it can set breakpoints first
+	let mut gdb = gdb_port.map(|port| {
+		let mut stub = GdbStub::listen(&format!("127.0.0.1:{}", port))
+			.expect("Unable to listen on the GDB port!");
+		println!("Waiting for gdb on port {}", port);
+		stub.accept().expect("Unable to accept the gdb connection!");
+		stub
+	});
+
+	// the filter uses the TV settings of the NTSC palette
+	let mut ntsc_filter = ntsc_filter.map(|s| {
+		let settings = NtscFilterSettings::parse(&s, tv).expect("Invalid NTSC filter settings!");
+		(NtscFilter::new(settings), Arc::new(RwLock::new(vec![0; NTSC_WIDTH * SCREEN_HEIGHT * 3])))
+	});
+	let fb_width = if ntsc_filter.is_some() {
+		NTSC_WIDTH
+	} else {
+		SCREEN_WIDTH
+	};
 
-	let thr = engine::start(rx_quit, rx_fb, rx_tb, tx_joy);
+	let thr = engine::start(fb_width as u32, rx_quit, rx_fb, rx_tb, tx_joy);
 	tx_tb.send(nes.tile_buf()).unwrap();
 
 	println!("Start");
 	nes.start();
+	let mut jam_reported = false;
 	loop {
 		if let Ok(b) = rx_joy.try_recv() {
 			nes.button_update(b);
 		}
 
 		let frame_start = Instant::now();
-		if nes.run_frame() {
+		let fb_ready = match (debugger.as_mut(), gdb.as_mut()) {
+			(Some(dbg), _) => dbg.run_frame(&mut nes),
+			(None, Some(stub)) => stub.run_frame(&mut nes),
+			(None, None) => nes.run_frame(),
+		};
+
+		if fb_ready {
 			// the PPU finished rendering the framebuffer -> render it via SDL2
-			tx_fb.send(nes.get_fb()).unwrap_or(());
+			let fb = match ntsc_filter.as_mut() {
+				Some((filter, ntsc_fb)) => {
+					let (pixels, phase) = nes.pixels();
+					filter.filter(pixels, phase, &mut ntsc_fb.write().unwrap());
+					ntsc_fb.clone()
+				}
+				None => nes.get_fb(),
+			};
+			tx_fb.send(fb).unwrap_or(());
 			tx_tb.send(nes.tile_buf()).unwrap_or(());
 		} else {
-			// vertical blank of the PPU finished -> 1 frame finished -> wait until 1/60Hz elapse
+			// vertical blank of the PPU finished -> 1 frame finished -> wait until the frame time elapsed
 			let frame_time = Instant::now().duration_since(frame_start);
-			if frame_time < Nes::FRAME_TIME_NS {
-				thread::sleep(Nes::FRAME_TIME_NS - frame_time);
+			if frame_time < nes.frame_time() {
+				thread::sleep(nes.frame_time() - frame_time);
 			}
 		}
 
-		if thr.is_finished() {
+		if nes.cpu_jammed() && !jam_reported {
+			println!("The CPU is jammed by an illegal op-code!");
+			jam_reported = true;
+		}
+
+		let quit = debugger.as_ref().map_or(false, |d| d.quit_requested())
+			|| gdb.as_ref().map_or(false, |g| g.quit_requested());
+		if thr.is_finished() || quit {
 			break;
 		}
 	}
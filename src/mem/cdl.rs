@@ -0,0 +1,121 @@
+// Code/Data Logger in the format of FCEUX. The .cdl file has one byte per byte of the PRG ROM
+// followed by one byte per byte of the CHR ROM. PRG ROM bytes are logged by the reads of the CPU
+// with the bank mapping at that time, CHR ROM bytes by the pattern fetches of the PPU and reads
+// of $2007. The flags of the PRG ROM are:
+// bit 0: code, bit 1: data, bits 2-3: the 8KB window ($8000 - $E000) it was mapped to,
+// bit 5: data accessed indirectly (e.g. LDA ($00),Y), bit 6: PCM audio fetched by the DMC.
+// Bit 4 (code reached indirectly) isn't logged. The flags of the CHR ROM are:
+// bit 0: rendered, bit 1: read by the CPU.
+
+use std::fs;
+use std::io;
+
+const PRG_CODE: u8 = 0x01;
+const PRG_DATA: u8 = 0x02;
+const PRG_INDIRECT_DATA: u8 = 0x20;
+const PRG_PCM: u8 = 0x40;
+const PRG_WINDOW_SHIFT: usize = 13;
+const PRG_WINDOW_BITS: usize = 2;
+
+pub const CHR_RENDERED: u8 = 0x01;
+pub const CHR_READ: u8 = 0x02;
+
+// what the CPU reads, set by the CPU before the read since the bus can't tell it by the address
+#[derive(Clone, Copy, PartialEq)]
+pub enum ReadKind {
+	// op-codes and operands
+	Code,
+	Data,
+	// data read through a pointer of the zeropage
+	IndirectData,
+	// samples of the DMC
+	Pcm,
+	// reads while calculating an address, which don't use the value
+	Dummy,
+}
+
+pub struct CodeDataLog {
+	prg: Vec<u8>,
+	chr: Vec<u8>,
+}
+
+impl CodeDataLog {
+	pub fn new(prg_size: usize, chr_size: usize) -> Self {
+		Self {
+			prg: vec![0; prg_size],
+			chr: vec![0; chr_size],
+		}
+	}
+
+	// continues an existing log, if it belongs to a ROM of the same size
+	pub fn load(&mut self, file: &str) -> io::Result<()> {
+		let data = fs::read(file)?;
+		if data.len() != self.prg.len() + self.chr.len() {
+			return Err(io::Error::new(io::ErrorKind::InvalidData, "size of the ROM differs"));
+		}
+
+		let (prg, chr) = data.split_at(self.prg.len());
+		self.prg.copy_from_slice(prg);
+		self.chr.copy_from_slice(chr);
+		Ok(())
+	}
+
+	pub fn save(&self, file: &str) -> io::Result<()> {
+		fs::write(file, [self.prg.as_slice(), self.chr.as_slice()].concat())
+	}
+
+	pub fn prg(&self) -> &[u8] {
+		&self.prg
+	}
+
+	pub fn chr(&self) -> &[u8] {
+		&self.chr
+	}
+
+	pub fn log_prg(&mut self, offset: usize, addr: usize, kind: ReadKind) {
+		let flags = match kind {
+			ReadKind::Code => PRG_CODE,
+			ReadKind::Data => PRG_DATA,
+			ReadKind::IndirectData => PRG_DATA | PRG_INDIRECT_DATA,
+			ReadKind::Pcm => PRG_DATA | PRG_PCM,
+			ReadKind::Dummy => return,
+		};
+		let window = ((addr >> PRG_WINDOW_SHIFT) & ((1 << PRG_WINDOW_BITS) - 1)) as u8;
+
+		if let Some(b) = self.prg.get_mut(offset) {
+			*b |= flags | (window << 2);
+		}
+	}
+
+	pub fn log_chr(&mut self, offset: usize, flags: u8) {
+		if let Some(b) = self.chr.get_mut(offset) {
+			*b |= flags;
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::nes::{LoadOptions, Nes};
+
+	#[test]
+	fn nestest() {
+		let mut nes = Nes::new("rom/test/nestest.nes", &LoadOptions::default()).unwrap();
+		// the file is only written by save()
+		nes.log_code_data("target/nestest-unused.cdl").unwrap();
+		nes.start();
+		for _ in 0..30 {
+			nes.run_frame();
+		}
+
+		let cdl = nes.code_data_log().unwrap();
+		assert_eq!((cdl.prg().len(), cdl.chr().len()), (0x4000, 0x2000));
+		// the reset vector, mapped to $E000 - $FFFF
+		assert_eq!(cdl.prg()[0x3FFC], PRG_DATA | (3 << 2));
+		assert!(cdl.prg().iter().any(|b| b & PRG_CODE != 0));
+		// the strings of the menu are read through a pointer
+		assert!(cdl.prg().iter().any(|b| b & PRG_INDIRECT_DATA != 0));
+		assert!(cdl.chr().iter().any(|b| b & CHR_RENDERED != 0));
+	}
+}
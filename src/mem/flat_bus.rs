@@ -0,0 +1,79 @@
+use super::ram::Ram;
+use super::{CpuBus, PpuBus, OAM_SIZE};
+use crate::ppu::ppu_regs::PpuRegisters;
+
+const FLAT_SIZE: usize = 0x10000;
+
+// A plain 64KB memory without any mapped hardware, used to test the CPU on its own. Every access
+// is recorded as (address, value, is write), so the bus activity of every cycle can be checked.
+pub struct FlatBus {
+	mem: Vec<u8>,
+	pub(crate) accesses: Vec<(usize, u8, bool)>,
+
+	// only needed to satisfy PpuBus
+	ppu_regs: PpuRegisters,
+	oam: Ram<OAM_SIZE>,
+}
+
+impl FlatBus {
+	pub fn new() -> Self {
+		Self {
+			mem: vec![0x00; FLAT_SIZE],
+			accesses: Vec::new(),
+			ppu_regs: PpuRegisters::new(),
+			oam: Ram::empty(0x00),
+		}
+	}
+
+	// direct access without recording it
+	pub fn poke(&mut self, addr: usize, val: u8) {
+		self.mem[addr & (FLAT_SIZE - 1)] = val;
+	}
+}
+
+impl CpuBus for FlatBus {
+	fn read(&mut self, addr: usize) -> u8 {
+		let val = CpuBus::peek(self, addr);
+		self.accesses.push((addr & (FLAT_SIZE - 1), val, false));
+		val
+	}
+
+	fn write(&mut self, addr: usize, val: u8) {
+		self.poke(addr, val);
+		self.accesses.push((addr & (FLAT_SIZE - 1), val, true));
+	}
+
+	fn peek(&self, addr: usize) -> u8 {
+		self.mem[addr & (FLAT_SIZE - 1)]
+	}
+
+	fn prg_rom_offset(&self, _addr: usize) -> Option<usize> {
+		None
+	}
+
+	fn nmi_line(&mut self) -> bool {
+		false
+	}
+
+	fn irq_line(&mut self) -> bool {
+		false
+	}
+}
+
+impl PpuBus for FlatBus {
+	fn read(&mut self, addr: usize) -> u8 {
+		self.peek(addr)
+	}
+
+	fn write(&mut self, addr: usize, val: u8) {
+		self.poke(addr, val);
+	}
+
+	fn ppu_reg(&mut self) -> &mut PpuRegisters {
+		&mut self.ppu_regs
+	}
+
+	fn oam(&mut self) -> &mut Ram<OAM_SIZE> {
+		&mut self.oam
+	}
+}
@@ -1,15 +1,30 @@
+pub mod cdl;
+#[cfg(test)]
+mod flat_bus;
 mod ram;
+mod system_bus;
+pub mod watch;
 
+#[cfg(test)]
+pub use self::flat_bus::FlatBus;
 use self::ram::Ram;
+pub use self::system_bus::SystemBus;
+use crate::apu::dmc::Dmc;
+use crate::apu::frame_counter::FrameCounter;
 use crate::cartridge::Cartridge;
 use crate::io::{iocontrol::IOControl, JoyPad};
 use crate::mask;
+use crate::ppu::model::PpuModel;
 use crate::ppu::ppu_regs::PpuRegisters;
+use crate::region::Region;
+use cdl::{CodeDataLog, ReadKind, CHR_READ, CHR_RENDERED};
+use watch::{Access, AddrSpace, Watchpoints};
 
 const CPU_RAM_SIZE: usize = 0x800;
 const OAM_SIZE: usize = 0x100;
 const PALETTE_SIZE: usize = 0x20;
 const PALETTE_MIRROR: usize = mask!(usize, 1, 4, true);
+pub(crate) const PPU_ADDR_MASK: usize = 0x3FFF;
 
 pub trait Segment {
 	fn read(&self, addr: usize) -> u8;
@@ -20,6 +35,11 @@ pub trait PpuSegment {
 	fn read(&mut self, addr: usize) -> u8;
 	fn write(&mut self, addr: usize, val: u8);
 	fn irq(&mut self) -> bool;
+
+	// read without side effects, only mappers which watch the PPU bus have to override it
+	fn peek(&mut self, addr: usize) -> u8 {
+		self.read(addr)
+	}
 }
 
 pub trait BankedSegment {
@@ -30,15 +50,31 @@ pub trait BankedSegment {
 pub trait PpuBus {
 	fn read(&mut self, addr: usize) -> u8;
 	fn write(&mut self, addr: usize, val: u8);
-	fn assert_nmi(&mut self);
 	fn ppu_reg(&mut self) -> &mut PpuRegisters;
 	fn oam(&mut self) -> &mut Ram<OAM_SIZE>;
+	// a pattern fetch of the rendering, for the code/data logger
+	fn log_chr_fetch(&mut self, _addr: usize) {}
 }
 
 pub trait CpuBus: PpuBus {
 	fn read(&mut self, addr: usize) -> u8;
 	fn write(&mut self, addr: usize, val: u8);
-	fn dma_transfer_occurred(&mut self);
+	// read without side effects and without taking a cycle, e.g. for traces
+	fn peek(&self, addr: usize) -> u8;
+	// the offset in the PRG ROM which is currently mapped to the address, e.g. for symbol files
+	fn prg_rom_offset(&self, addr: usize) -> Option<usize>;
+	// what the next read is for, for the code/data logger
+	fn set_read_kind(&mut self, _kind: ReadKind) {}
+	fn nmi_line(&mut self) -> bool;
+	fn irq_line(&mut self) -> bool;
+}
+
+// sources of the IRQ line, it's asserted as long as at least one of the sources asserts it
+#[derive(Clone, Copy)]
+pub enum IrqSource {
+	Mapper = 0x01,
+	FrameCounter = 0x02,
+	Dmc = 0x04,
 }
 
 pub trait PpuRegisterAccess {
@@ -46,14 +82,17 @@ pub trait PpuRegisterAccess {
 	fn ppu_mask_write(&mut self, val: u8);
 	fn ppu_scroll_write(&mut self, val: u8);
 	fn ppu_addr_write(&mut self, val: u8);
-	fn ppu_data_write(&mut self);
+	fn ppu_data_write(&mut self, val: u8);
 	fn oam_addr_write(&mut self, val: u8);
 	fn oam_data_write(&mut self, val: u8);
 
 	fn ppu_stat_read(&mut self) -> u8;
 	fn ppu_addr_get(&self) -> u16;
-	fn ppu_data_read(&mut self, new_val: u8) -> u8;
-	fn oam_data_read(&self) -> u8;
+	// the palette isn't buffered, its value is returned right away
+	fn ppu_data_read(&mut self, palette_val: Option<u8>) -> u8;
+	fn oam_data_read(&mut self) -> u8;
+	// reads of the write-only registers
+	fn io_latch_read(&mut self) -> u8;
 }
 
 // We do not include the VRAM of the PPU in this map, we include it in the individual mappers.
@@ -66,10 +105,17 @@ pub struct MemoryMap {
 	cartridge: Box<dyn Cartridge>,
 
 	ioctrl: IOControl,
+	frame_counter: FrameCounter,
+	dmc: Dmc,
+
+	oam_dma_page: Option<u8>,
+	irq_sources: u8,
 
-	dma_happened: bool,
-	nmi_asserted: bool,
-	irq_asserted: bool,
+	watch: Watchpoints,
+	cdl: Option<CodeDataLog>,
+	read_kind: ReadKind,
+	// the last value on the data bus of the CPU, returned by reads of unmapped addresses
+	cpu_bus: u8,
 }
 
 impl MemoryMap {
@@ -81,9 +127,14 @@ impl MemoryMap {
 			ppu_regs: PpuRegisters::new(),
 			cartridge: cartridge,
 			ioctrl: IOControl::new(true, true),
-			dma_happened: false,
-			nmi_asserted: false,
-			irq_asserted: false,
+			frame_counter: FrameCounter::new(Region::default()),
+			dmc: Dmc::new(Region::default()),
+			oam_dma_page: None,
+			irq_sources: 0,
+			watch: Watchpoints::default(),
+			cdl: None,
+			read_kind: ReadKind::Data,
+			cpu_bus: 0,
 		}
 	}
 
@@ -91,29 +142,75 @@ impl MemoryMap {
 		self.ioctrl.refresh_controller(&btns[0], &btns[1]);
 	}
 
+	pub fn set_vs_system(&mut self, model: PpuModel, dip_switches: u8) {
+		self.ppu_regs.set_model(model);
+		self.ioctrl.set_vs_system(dip_switches);
+	}
+
+	// the APU has different periods on PAL, it's set before the power-up
+	pub fn set_region(&mut self, region: Region) {
+		self.frame_counter = FrameCounter::new(region);
+		self.dmc = Dmc::new(region);
+	}
+
 	pub fn cartridge(&mut self) -> &mut Box<dyn Cartridge> {
 		&mut self.cartridge
 	}
 
-	pub fn get_dma(&mut self) -> bool {
-		let ret = self.dma_happened;
-		self.dma_happened = false;
+	// the page of an OAM DMA requested by a $4014 write
+	pub fn take_oam_dma(&mut self) -> Option<u8> {
+		self.oam_dma_page.take()
+	}
 
-		ret
+	pub fn dmc(&mut self) -> &mut Dmc {
+		&mut self.dmc
 	}
 
-	pub fn get_nmi(&mut self) -> bool {
-		let ret = self.nmi_asserted;
-		self.nmi_asserted = false;
+	// everything besides the PPU which is clocked with the CPU
+	pub fn cpu_cycle(&mut self) {
+		self.ppu_regs.clock();
+		self.frame_counter.clock();
+		self.dmc.clock();
+	}
 
-		ret
+	pub fn watchpoints(&mut self) -> &mut Watchpoints {
+		&mut self.watch
 	}
 
-	pub fn get_irq(&mut self) -> bool {
-		let ret = self.irq_asserted;
-		self.irq_asserted = false;
+	pub fn set_cdl(&mut self, cdl: Option<CodeDataLog>) {
+		self.cdl = cdl;
+	}
 
-		ret
+	pub fn cdl(&self) -> Option<&CodeDataLog> {
+		self.cdl.as_ref()
+	}
+
+	pub(crate) fn read_kind(&self) -> ReadKind {
+		self.read_kind
+	}
+
+	// PPU memory without side effects and without checking the watchpoints, e.g. for the debugger
+	pub fn ppu_peek(&mut self, addr: usize) -> u8 {
+		match addr & 0x3FFF {
+			0x3F00..=0x3FFF => self.palette_read(addr),
+			a => PpuSegment::peek(self.cartridge.as_mut(), a),
+		}
+	}
+
+	fn palette_read(&self, addr: usize) -> u8 {
+		let mut a = addr & (self.palette_ram.size() - 1);
+		if (a & 0x03) == 0 {
+			a &= PALETTE_MIRROR;
+		}
+		self.palette_ram.read(a)
+	}
+
+	pub fn set_irq(&mut self, src: IrqSource, asserted: bool) {
+		if asserted {
+			self.irq_sources |= src as u8;
+		} else {
+			self.irq_sources &= !(src as u8);
+		}
 	}
 
 	#[allow(dead_code)]
@@ -232,7 +329,7 @@ impl MemoryMap {
 
 impl CpuBus for MemoryMap {
 	fn read(&mut self, addr: usize) -> u8 {
-		match addr {
+		let val = match addr {
 			0x0..=0x1FFF => self.cpu_ram.read(addr),
 			0x2000..=0x3FFF => {
 				let reg = addr & 0x07;
@@ -240,35 +337,61 @@ impl CpuBus for MemoryMap {
 					2 => self.ppu_regs.ppu_stat_read(),
 					4 => self.ppu_regs.oam_data_read(),
 					7 => {
-						// read the actual value into the data register and return the previous one
-						let new_addr = self.ppu_regs.ppu_addr_get() as usize;
-						let new_data = PpuBus::read(self, new_addr);
-						self.ppu_regs.ppu_data_read(new_data)
-					}
-					_ => {
-						panic!(
-							"CpuBus read(): trying to read from a writeonly PPU register: {}",
-							reg
-						)
+						// return the buffer, the PPU reads the next value into it
+						let addr = self.ppu_regs.ppu_addr_get() as usize & PPU_ADDR_MASK;
+						if let Some(cdl) = self.cdl.as_mut() {
+							if let Some(offset) = self.cartridge.chr_rom_offset(addr) {
+								cdl.log_chr(offset, CHR_READ);
+							}
+						}
+						let palette_val = (addr >= 0x3F00).then(|| self.palette_read(addr));
+						self.ppu_regs.ppu_data_read(palette_val)
 					}
+					// write-only registers, e.g. hit by dummy reads
+					_ => self.ppu_regs.io_latch_read(),
 				}
 			}
-			0x4000..=0x4013 | 0x4015 => {
-				0
-				// todo!("CpuBus::read(): APU is not supported yet, addr: 0x{:x}", addr)
+			// bit 5 isn't driven
+			0x4015 => {
+				let status = self.frame_counter.status_read() | self.dmc.status_read();
+				status | (self.cpu_bus & 0x20)
+			}
+			// write-only registers of the APU and the disabled test registers
+			0x4000..=0x4014 | 0x4018..=0x401F => self.cpu_bus,
+			0x4016 => self.ioctrl.read_controller1(self.cpu_bus),
+			0x4017 => self.ioctrl.read_controller2(self.cpu_bus),
+			0x4020..=0xFFFF if !self.cartridge.cpu_read_mapped(addr) => self.cpu_bus,
+			0x4020..=0xFFFF => {
+				if let Some(cdl) = self.cdl.as_mut() {
+					if let Some(offset) = self.cartridge.prg_rom_offset(addr) {
+						cdl.log_prg(offset, addr, self.read_kind);
+					}
+				}
+				Segment::read(self.cartridge.as_ref(), addr)
 			}
-			0x4016 => self.ioctrl.read_controller1(),
-			0x4017 => self.ioctrl.read_controller2(),
-			0x4020..=0xFFFF => Segment::read(self.cartridge.as_ref(), addr),
 			_ => panic!("CpuBus::read(): address out of memory range: 0x{:x}", addr),
+		};
+
+		// $4015 is inside the CPU, so it doesn't drive the data bus
+		if addr != 0x4015 {
+			self.cpu_bus = val;
 		}
+
+		// the kind is only valid for one read
+		self.read_kind = ReadKind::Data;
+		self.watch.check(AddrSpace::Cpu, Access::Read, addr, val);
+		val
 	}
 
 	fn write(&mut self, addr: usize, val: u8) {
+		self.watch.check(AddrSpace::Cpu, Access::Write, addr, val);
+		self.cpu_bus = val;
+
 		match addr {
 			0x0..=0x1FFF => self.cpu_ram.write(addr, val),
 			0x2000..=0x3FFF => {
-				let reg = addr & 0x07;
+				self.ppu_regs.latch_write(val);
+				let reg = self.ppu_regs.register_idx(addr & 0x07);
 				match reg {
 					0 => self.ppu_regs.ppu_ctrl_write(val),
 					1 => self.ppu_regs.ppu_mask_write(val),
@@ -276,45 +399,69 @@ impl CpuBus for MemoryMap {
 					4 => self.ppu_regs.oam_data_write(val),
 					5 => self.ppu_regs.ppu_scroll_write(val),
 					6 => self.ppu_regs.ppu_addr_write(val),
-					7 => {
-						// get address, mask probably not necessary
-						let addr = self.ppu_regs.ppu_addr_get() as usize;
-						// write value to address
-						PpuBus::write(self, addr, val);
-						// trigger further actions when writing to the PPU Data register
-						self.ppu_regs.ppu_data_write();
-					}
-					_ => {
-						panic!("CpuBus write(): trying to write to readonly PPU register: {}", reg)
-					}
+					// written by the PPU on its next dot
+					7 => self.ppu_regs.ppu_data_write(val),
+					// $2002 is read-only, only the I/O latch is written
+					_ => (),
 				}
 			}
-			0x4014 => {
-				let page = (val as usize) << 8;
-				for i in 0x00..0x100usize {
-					let data = CpuBus::read(self, page + i);
-					self.oam.write(i, data);
-				}
-
-				CpuBus::dma_transfer_occurred(self);
-			}
-			0x4000..=0x4013 | 0x4015 => {
+			0x4010..=0x4013 => self.dmc.write(addr - 0x4010, val),
+			0x4014 => self.oam_dma_page = Some(val), // the transfer is done by the CPU
+			0x4015 => self.dmc.enable_write(val),
+			0x4000..=0x400F => {
 				// todo!("CpuBus::write(): APU is not supported yet, addr: 0x{:x}", addr)
 			}
-			0x4016 | 0x4017 => self.ioctrl.reload_controller(val),
-			0x4200..=0xFFFF => Segment::write(self.cartridge.as_mut(), addr, val),
+			0x4016 => {
+				self.ioctrl.reload_controller(val);
+				self.cartridge.out_latch_write(val);
+			}
+			0x4017 => self.frame_counter.write(val),
+			// the disabled test registers
+			0x4018..=0x401F => (),
+			0x4020..=0xFFFF => Segment::write(self.cartridge.as_mut(), addr, val),
 			_ => panic!("CpuBus::write(): address out of memory range: 0x{:x}", addr),
 		}
 	}
 
-	fn dma_transfer_occurred(&mut self) {
-		self.dma_happened = true;
+	fn peek(&self, addr: usize) -> u8 {
+		match addr {
+			0x0..=0x1FFF => self.cpu_ram.read(addr),
+			0x4020..=0xFFFF => Segment::read(self.cartridge.as_ref(), addr),
+			// reading the registers has side effects
+			_ => 0xFF,
+		}
+	}
+
+	fn prg_rom_offset(&self, addr: usize) -> Option<usize> {
+		match addr {
+			0x4020..=0xFFFF => self.cartridge.prg_rom_offset(addr),
+			_ => None,
+		}
+	}
+
+	fn set_read_kind(&mut self, kind: ReadKind) {
+		self.read_kind = kind;
+	}
+
+	fn nmi_line(&mut self) -> bool {
+		self.ppu_regs.nmi_line()
+	}
+
+	fn irq_line(&mut self) -> bool {
+		let mapper_irq = self.cartridge.irq();
+		self.set_irq(IrqSource::Mapper, mapper_irq);
+		let frame_irq = self.frame_counter.irq();
+		self.set_irq(IrqSource::FrameCounter, frame_irq);
+		let dmc_irq = self.dmc.irq();
+		self.set_irq(IrqSource::Dmc, dmc_irq);
+
+		self.irq_sources != 0
 	}
 }
 
 impl PpuBus for MemoryMap {
 	fn read(&mut self, addr: usize) -> u8 {
-		let ret = match addr {
+		let val = match addr {
 			0x0000..=0x1FFF
 			| 0x2000..=0x23FF
 			| 0x3000..=0x33FF
@@ -324,24 +471,17 @@ impl PpuBus for MemoryMap {
 			| 0x3800..=0x3BFF
 			| 0x2C00..=0x2FFF
 			| 0x3C00..=0x3EFF => PpuSegment::read(self.cartridge.as_mut(), addr),
-			0x3F00..=0x3FFF => {
-				let mut a = addr & (self.palette_ram.size() - 1);
-				if (a & 0x03) == 0 {
-					a &= PALETTE_MIRROR;
-				}
-				self.palette_ram.read(a)
-			}
+			0x3F00..=0x3FFF => self.palette_read(addr),
 			_ => panic!("PpuBus::read(): address out of memory range: 0x{:x}", addr),
 		};
 
-		if self.cartridge.irq() {
-			self.irq_asserted = true;
-		}
-
-		ret
+		self.watch.check(AddrSpace::Ppu, Access::Read, addr, val);
+		val
 	}
 
 	fn write(&mut self, addr: usize, val: u8) {
+		self.watch.check(AddrSpace::Ppu, Access::Write, addr, val);
+
 		match addr {
 			0x0000..=0x1FFF
 			| 0x2000..=0x23FF
@@ -361,14 +501,6 @@ impl PpuBus for MemoryMap {
 			}
 			_ => panic!("PpuBus::write(): address out of memory range: 0x{:x}", addr),
 		}
-
-		if self.cartridge.irq() {
-			self.irq_asserted = true;
-		}
-	}
-
-	fn assert_nmi(&mut self) {
-		self.nmi_asserted = true;
 	}
 
 	fn ppu_reg(&mut self) -> &mut PpuRegisters {
@@ -378,4 +510,12 @@ impl PpuBus for MemoryMap {
 	fn oam(&mut self) -> &mut Ram<OAM_SIZE> {
 		&mut self.oam
 	}
+
+	fn log_chr_fetch(&mut self, addr: usize) {
+		if let Some(cdl) = self.cdl.as_mut() {
+			if let Some(offset) = self.cartridge.chr_rom_offset(addr) {
+				cdl.log_chr(offset, CHR_RENDERED);
+			}
+		}
+	}
 }
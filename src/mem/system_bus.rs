@@ -0,0 +1,200 @@
+use super::cdl::ReadKind;
+use super::ram::Ram;
+use super::{CpuBus, MemoryMap, PpuBus, OAM_SIZE};
+use crate::ppu::ppu::Ppu;
+use crate::ppu::ppu_regs::PpuRegisters;
+use crate::region::Region;
+
+const PPU_DOTS_BEFORE_ACCESS: usize = 2;
+
+// The bus as seen by the CPU. Every access of the CPU takes one cycle and the rest of the system
+// is clocked before the access is done, so e.g. the PPU sees every write at the correct dot.
+// DMA transfers also happen here, since they take over the bus while the CPU is halted.
+pub struct SystemBus {
+	pub(crate) mem: MemoryMap,
+	pub(crate) ppu: Ppu<MemoryMap>,
+
+	cycle_cnt: u64,
+	// PPU dots per CPU cycles, the fraction of a dot which is left and the dots of this cycle
+	dots_per_cycles: (usize, usize),
+	dot_fraction: usize,
+	cycle_dots: usize,
+
+	// DMA state, the CPU is halted on its next read
+	oam_dma: Option<u8>,
+	dmc_dma: bool,
+	need_halt: bool,
+	need_dummy_read: bool,
+}
+
+impl SystemBus {
+	pub fn new(mem: MemoryMap, ppu: Ppu<MemoryMap>, region: Region) -> Self {
+		SystemBus {
+			mem,
+			ppu,
+			cycle_cnt: 0,
+			dots_per_cycles: region.dots_per_cycles(),
+			dot_fraction: 0,
+			cycle_dots: 3,
+			oam_dma: None,
+			dmc_dma: false,
+			need_halt: false,
+			need_dummy_read: false,
+		}
+	}
+
+	// The PPU runs 3 times faster than the CPU (3.2 times on PAL, so every 5th cycle has 4 dots).
+	// The access happens after the first two PPU dots of the cycle, the interrupt lines are
+	// sampled by the CPU after the remaining ones.
+	fn start_cycle(&mut self) {
+		self.cycle_cnt += 1;
+		self.mem.cpu_cycle();
+
+		let (dots, cycles) = self.dots_per_cycles;
+		self.dot_fraction += dots;
+		self.cycle_dots = self.dot_fraction / cycles;
+		self.dot_fraction %= cycles;
+
+		for _ in 0..PPU_DOTS_BEFORE_ACCESS {
+			self.ppu.step(&mut self.mem);
+		}
+	}
+
+	fn end_cycle(&mut self) {
+		for _ in PPU_DOTS_BEFORE_ACCESS..self.cycle_dots {
+			self.ppu.step(&mut self.mem);
+		}
+
+		if let Some(page) = self.mem.take_oam_dma() {
+			self.oam_dma = Some(page);
+			self.need_halt = true;
+		}
+
+		if !self.dmc_dma && self.mem.dmc().dma_request() {
+			self.dmc_dma = true;
+			self.need_halt = true;
+			self.need_dummy_read = true;
+		}
+	}
+
+	// The DMA unit halts the CPU on a read cycle, which is repeated (e.g. $2007 is read twice).
+	// Afterwards it alternates between get and put cycles. A DMC fetch needs a halt and a dummy
+	// cycle, which can also be cycles of a running OAM DMA, and takes precedence on a get cycle.
+	fn run_dma(&mut self, addr: usize) {
+		// only the first read of the controller ports has an effect on the NES
+		let skip_dummy_reads = addr == 0x4016 || addr == 0x4017;
+
+		// the reads of the DMA aren't the ones the CPU logs
+		let read_kind = self.mem.read_kind();
+
+		self.need_halt = false;
+		self.start_cycle();
+		self.mem.set_read_kind(ReadKind::Dummy);
+		CpuBus::read(&mut self.mem, addr);
+		self.end_cycle();
+
+		let mut oam_cnt: usize = 0;
+		let mut val = 0;
+		while self.dmc_dma || self.oam_dma.is_some() {
+			// the APU clock decides about get and put, the next cycle is a get if the count is odd
+			let get_cycle = (self.cycle_cnt & 0x01) > 0;
+			let dmc_ready = self.dmc_dma && !self.need_halt && !self.need_dummy_read;
+
+			if self.need_halt {
+				self.need_halt = false;
+			} else if self.need_dummy_read {
+				self.need_dummy_read = false;
+			}
+
+			self.start_cycle();
+			match (get_cycle, self.oam_dma) {
+				(true, _) if dmc_ready => {
+					let dmc_addr = self.mem.dmc().dma_addr() as usize;
+					self.mem.set_read_kind(ReadKind::Pcm);
+					let dmc_val = CpuBus::read(&mut self.mem, dmc_addr);
+					self.mem.dmc().dma_complete(dmc_val);
+					self.dmc_dma = false;
+				}
+				(true, Some(page)) => {
+					val = CpuBus::read(&mut self.mem, ((page as usize) << 8) | (oam_cnt >> 1));
+					oam_cnt += 1;
+				}
+				(false, Some(_)) if (oam_cnt & 0x01) > 0 => {
+					CpuBus::write(&mut self.mem, 0x2004, val);
+					oam_cnt += 1;
+					if oam_cnt == 0x200 {
+						self.oam_dma = None;
+					}
+				}
+				_ => {
+					// halt, dummy or alignment cycle
+					if !skip_dummy_reads {
+						self.mem.set_read_kind(ReadKind::Dummy);
+						CpuBus::read(&mut self.mem, addr);
+					}
+				}
+			}
+			self.end_cycle();
+		}
+
+		self.mem.set_read_kind(read_kind);
+	}
+}
+
+impl CpuBus for SystemBus {
+	fn read(&mut self, addr: usize) -> u8 {
+		if self.need_halt {
+			self.run_dma(addr);
+		}
+
+		self.start_cycle();
+		let val = CpuBus::read(&mut self.mem, addr);
+		self.end_cycle();
+
+		val
+	}
+
+	fn write(&mut self, addr: usize, val: u8) {
+		self.start_cycle();
+		CpuBus::write(&mut self.mem, addr, val);
+		self.end_cycle();
+	}
+
+	fn peek(&self, addr: usize) -> u8 {
+		self.mem.peek(addr)
+	}
+
+	fn prg_rom_offset(&self, addr: usize) -> Option<usize> {
+		self.mem.prg_rom_offset(addr)
+	}
+
+	fn set_read_kind(&mut self, kind: ReadKind) {
+		self.mem.set_read_kind(kind);
+	}
+
+	fn nmi_line(&mut self) -> bool {
+		self.mem.nmi_line()
+	}
+
+	fn irq_line(&mut self) -> bool {
+		self.mem.irq_line()
+	}
+}
+
+impl PpuBus for SystemBus {
+	fn read(&mut self, addr: usize) -> u8 {
+		PpuBus::read(&mut self.mem, addr)
+	}
+
+	fn write(&mut self, addr: usize, val: u8) {
+		PpuBus::write(&mut self.mem, addr, val);
+	}
+
+	fn ppu_reg(&mut self) -> &mut PpuRegisters {
+		self.mem.ppu_reg()
+	}
+
+	fn oam(&mut self) -> &mut Ram<OAM_SIZE> {
+		self.mem.oam()
+	}
+}
@@ -0,0 +1,140 @@
+// Watchpoints on address ranges of the CPU and the PPU bus, used by the debugger. The memory map
+// checks every access against them and collects the hits, the debugger takes them after the
+// instruction which caused them. Executions are checked by the debugger before every instruction.
+
+use std::fmt;
+
+// the number of hits kept until the debugger takes them, e.g. for a watched OAM DMA
+const MAX_HITS: usize = 16;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum AddrSpace {
+	Cpu,
+	Ppu,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Access {
+	Read,
+	Write,
+	Execute,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct Watchpoint {
+	pub space: AddrSpace,
+	pub start: usize,
+	pub end: usize, // inclusive
+	pub read: bool,
+	pub write: bool,
+	pub execute: bool,
+}
+
+pub struct WatchHit {
+	pub space: AddrSpace,
+	pub access: Access,
+	pub addr: usize,
+	pub val: u8,
+}
+
+#[derive(Default)]
+pub struct Watchpoints {
+	list: Vec<Watchpoint>,
+	hits: Vec<WatchHit>,
+}
+
+impl Watchpoint {
+	pub fn matches(&self, space: AddrSpace, access: Access, addr: usize) -> bool {
+		let access_match = match access {
+			Access::Read => self.read,
+			Access::Write => self.write,
+			Access::Execute => self.execute,
+		};
+
+		access_match && self.space == space && (self.start..=self.end).contains(&addr)
+	}
+}
+
+impl Watchpoints {
+	pub fn add(&mut self, wp: Watchpoint) {
+		self.list.push(wp);
+	}
+
+	pub fn remove(&mut self, idx: usize) -> Option<Watchpoint> {
+		if idx < self.list.len() {
+			Some(self.list.remove(idx))
+		} else {
+			None
+		}
+	}
+
+	pub fn list(&self) -> &[Watchpoint] {
+		&self.list
+	}
+
+	pub fn matches(&self, space: AddrSpace, access: Access, addr: usize) -> bool {
+		self.list.iter().any(|wp| wp.matches(space, access, addr))
+	}
+
+	// called on every access, so it has to be cheap without watchpoints
+	pub fn check(&mut self, space: AddrSpace, access: Access, addr: usize, val: u8) {
+		if self.list.is_empty() || self.hits.len() >= MAX_HITS {
+			return;
+		}
+
+		if self.matches(space, access, addr) {
+			self.hits.push(WatchHit {
+				space,
+				access,
+				addr,
+				val,
+			});
+		}
+	}
+
+	pub fn take_hits(&mut self) -> Vec<WatchHit> {
+		std::mem::take(&mut self.hits)
+	}
+}
+
+impl fmt::Debug for AddrSpace {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match &self {
+			Self::Cpu => write!(f, "CPU"),
+			Self::Ppu => write!(f, "PPU"),
+		}
+	}
+}
+
+impl fmt::Debug for Watchpoint {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let flag = |set: bool, c: char| {
+			if set {
+				c
+			} else {
+				'-'
+			}
+		};
+		write!(
+			f,
+			"{:?} ${:04X}-${:04X} {}{}{}",
+			self.space,
+			self.start,
+			self.end,
+			flag(self.read, 'r'),
+			flag(self.write, 'w'),
+			flag(self.execute, 'x')
+		)
+	}
+}
+
+impl fmt::Debug for WatchHit {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let access = match self.access {
+			Access::Read => "read",
+			Access::Write => "write",
+			Access::Execute => "execute",
+		};
+		write!(f, "{:?} {} ${:04X} = ${:02X}", self.space, access, self.addr, self.val)
+	}
+}
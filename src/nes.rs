@@ -1,11 +1,22 @@
-use crate::cartridge::{self, banked_mem, CartridgeErr, CartridgeInfo, PpuMirror};
-use crate::cpu::{Cpu, InterruptSource};
+use crate::archive::{self, ArchiveErr};
+use crate::cartridge::{self, banked_mem, CartridgeErr, CartridgeInfo, PpuMirror, TRAINER_SIZE};
+use crate::cpu::{self, Cpu, Registers};
+use crate::debugger::labels::{LabelErr, Labels};
+use crate::debugger::profile::Profiler;
+use crate::debugger::trace::{self, TraceOptions, Tracer};
 use crate::io::JoyPad;
-use crate::mem::MemoryMap;
+use crate::mem::cdl::CodeDataLog;
+use crate::mem::watch::Watchpoints;
+use crate::mem::{CpuBus, MemoryMap, SystemBus};
+use crate::patch::{self, PatchErr};
+use crate::ppu::model::PpuModel;
+use crate::ppu::palette::Palette;
 use crate::ppu::ppu::Ppu;
+use crate::region::Region;
 
 use std::fmt;
 use std::fs;
+use std::io;
 use std::path::Path;
 use std::sync::{Arc, RwLock};
 use std::time::Duration;
@@ -14,12 +25,41 @@ pub const SAVE_FILE_ENDING: &str = ".rsav";
 
 #[allow(dead_code)]
 pub struct Nes {
-	cpu: Cpu<MemoryMap>,
-	mem: MemoryMap,
-	ppu: Ppu<MemoryMap>,
+	cpu: Cpu<SystemBus>,
+	bus: SystemBus,
 	rom_info: RomInfo,
+	region: Region,
+	patch_file: Option<String>,
 
 	savefile: Option<String>,
+	tracer: Option<Tracer>,
+	labels: Labels,
+	cdl_file: Option<String>,
+	profiler: Option<Profiler>,
+	profile_file: Option<String>,
+}
+
+// options which influence how a ROM is loaded
+#[derive(Default)]
+pub struct LoadOptions {
+	// explicit patch file, if none is set a patch next to the ROM with the same name is used
+	pub patch_file: Option<String>,
+	// entry to load if the ROM is stored in an archive, if none is set the first ROM is used
+	pub archive_entry: Option<String>,
+	// PPU of a Vs. System, if none is set it is taken from the NES 2.0 header
+	pub vs_ppu: Option<PpuModel>,
+	// DIP switches of a Vs. System, bit 0 = switch 1
+	pub vs_dip_switches: u8,
+	// if none is set it is taken from the header or the tags in the file name, NTSC by default
+	pub region: Option<Region>,
+}
+
+// the reason why run_until() returned
+#[derive(Clone, Copy, PartialEq)]
+pub enum RunEvent {
+	FbReady,
+	FrameFinished,
+	Break,
 }
 
 #[derive(Default)]
@@ -27,15 +67,29 @@ struct RomInfo {
 	cartr_info: CartridgeInfo,
 	trainer: bool,
 	vs_unisystem: bool,
-	play_choice: bool,
+	// none if the header doesn't tell or the ROM runs in multiple regions
+	region: Option<Region>,
+	play_choice: Option<PlayChoiceData>,
 	ines_version: INesVersion,
 }
 
+// PlayChoice-10 data which is stored after the CHR_ROM, not used yet but kept for a PC-10 mode
+#[derive(Default)]
+pub struct PlayChoiceData {
+	pub inst_rom: Vec<u8>,     // 8KB instruction ROM with the hint screens
+	pub prom: Option<Vec<u8>>, // 16 bytes PROM data + 16 bytes CounterOut, missing in a lot of dumps
+}
+
+impl RomInfo {
+	fn play_choice(&self) -> Option<&PlayChoiceData> {
+		self.play_choice.as_ref()
+	}
+}
+
 #[derive(Default)]
 struct INesV2Info {
-	tv_system: bool, // false = NTSC, true = PAL
-	tv_system_support: TVSystemSupport,
-	bus_conflicts: bool,
+	vs_ppu: Option<PpuModel>,
+	vs_hardware: u8,
 }
 
 pub enum RomErr {
@@ -44,6 +98,8 @@ pub enum RomErr {
 	FileCorrupted,
 	SavefileWrite,
 	CartridgeError(CartridgeErr),
+	PatchError(PatchErr),
+	ArchiveError(ArchiveErr),
 	Unknown,
 }
 
@@ -52,12 +108,6 @@ enum INesVersion {
 	V2(INesV2Info),
 }
 
-enum TVSystemSupport {
-	Ntsc,
-	Pal,
-	Both,
-}
-
 impl fmt::Debug for RomErr {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 		match &self {
@@ -67,87 +117,296 @@ impl fmt::Debug for RomErr {
 			Self::SavefileWrite => write!(f, "SavefileWrite"),
 			Self::Unknown => write!(f, "Unknown"),
 			Self::CartridgeError(ce) => write!(f, "{:?}", ce),
+			Self::PatchError(pe) => write!(f, "PatchError: {:?}", pe),
+			Self::ArchiveError(ae) => write!(f, "ArchiveError: {:?}", ae),
 		}
 	}
 }
 
 impl Nes {
-	const PLAY_CHOICE_SIZE: usize = 8192;
+	const HEADER_SIZE: usize = 16;
+	const INST_ROM_SIZE: usize = 8192;
+	const PROM_SIZE: usize = 32;
+	// the engine refreshes the screen with 60Hz, the emulation runs with frame_time()
 	pub const FRAME_TIME_NS: Duration = Duration::new(0, 16_666_667);
 
+	// the patch which was applied to the ROM
+	pub fn patch_file(&self) -> Option<&str> {
+		self.patch_file.as_deref()
+	}
+
+	pub fn region(&self) -> Region {
+		self.region
+	}
+
+	pub fn frame_time(&self) -> Duration {
+		self.region.frame_time()
+	}
+
+	// none if the ROM isn't a PlayChoice-10 dump
+	pub fn play_choice(&self) -> Option<&PlayChoiceData> {
+		self.rom_info.play_choice()
+	}
+
 	pub fn start(&mut self) {
-		self.cpu.assert_interrupt(InterruptSource::RESET);
+		self.cpu.assert_reset();
 	}
 
-	pub fn run_frame(&mut self) -> bool {
-		loop {
-			self.ppu.step(&mut self.mem);
-			self.ppu.step(&mut self.mem);
-			self.ppu.step(&mut self.mem);
+	// starts without the reset sequence, e.g. for the automation mode of nestest
+	pub fn start_at(&mut self, pc: u16) {
+		self.cpu.set_pc(pc);
+	}
 
-			if self.mem.get_nmi() {
-				self.cpu.assert_interrupt(InterruptSource::NMI);
-			}
+	// writes every executed instruction to the file, in the trace format of Nintendulator
+	pub fn trace_to(&mut self, file: &str) -> io::Result<()> {
+		self.tracer = Some(Tracer::to_file(file, TraceOptions::default())?);
+		Ok(())
+	}
 
-			if self.mem.get_irq() {
-				self.cpu.assert_interrupt(InterruptSource::IRQ);
+	pub fn set_tracer(&mut self, tracer: Option<Tracer>) {
+		self.tracer = tracer;
+	}
+
+	pub fn tracer(&self) -> Option<&Tracer> {
+		self.tracer.as_ref()
+	}
+
+	// Logs which bytes of the PRG and CHR ROM are used as code or data, in the format of FCEUX.
+	// An existing log of the ROM is continued, the file is written by save().
+	pub fn log_code_data(&mut self, file: &str) -> io::Result<()> {
+		let info = &self.rom_info.cartr_info;
+		let mut cdl = CodeDataLog::new(
+			info.prg_rom_cnt * banked_mem::PRG_ROM_BANK_SIZE,
+			info.chr_rom_cnt * banked_mem::CHR_ROM_BANK_SIZE,
+		);
+		if Path::new(file).exists() {
+			cdl.load(file)?;
+		}
+
+		self.bus.mem.set_cdl(Some(cdl));
+		self.cdl_file = Some(String::from(file));
+		Ok(())
+	}
+
+	pub fn code_data_log(&self) -> Option<&CodeDataLog> {
+		self.bus.mem.cdl()
+	}
+
+	// Accounts the cycles to the called routines and the NMI handler, the folded stacks are
+	// written to the file by save().
+	pub fn profile_to(&mut self, file: &str) {
+		self.profiler = Some(Profiler::new());
+		self.profile_file = Some(String::from(file));
+	}
+
+	pub fn profiler(&self) -> Option<&Profiler> {
+		self.profiler.as_ref()
+	}
+
+	// labels of a symbol file, shown by the trace and the disassembler
+	pub fn load_labels(&mut self, file: &str) -> Result<(), LabelErr> {
+		self.labels.load(file)
+	}
+
+	pub fn label(&self, addr: u16) -> Option<&str> {
+		self.labels.name(&self.bus, addr)
+	}
+
+	// the instruction at the address with the current PRG banks and the address of the next one
+	pub fn disassemble(&self, addr: u16) -> (String, u16) {
+		cpu::disassemble(&self.bus, addr, |a| self.label(a).map(String::from))
+	}
+
+	// the next instruction with the PPU position (dot, scanline) and the CPU cycles before it
+	pub fn trace_line(&self) -> Option<String> {
+		let (scanline, dot) = self.bus.ppu.position();
+		self.cpu
+			.trace(&self.bus, |a| self.label(a).map(String::from))
+			.map(|cpu| format!("{} PPU:{:3},{:3} CYC:{}", cpu, dot, scanline, self.cpu.cycles()))
+	}
+
+	// executes one instruction or interrupt
+	pub fn step(&mut self) {
+		// the tracer is put back before the step, so it's dumped if the step panics
+		if let Some(mut tracer) = self.tracer.take() {
+			let (regs, frame) = (self.cpu.registers(), self.frame_cnt());
+			if self.cpu.at_instruction() && tracer.wants(regs.pc, frame) {
+				let line = self.trace_line().unwrap_or_default();
+				tracer.record(format!("{} F:{} {}", line, frame, trace::flags(regs.p)));
 			}
+			self.tracer = Some(tracer);
+		}
+
+		let cycles = self.cpu.cycles();
+		self.cpu.step(&mut self.bus);
+
+		if let Some(profiler) = self.profiler.as_mut() {
+			// the cycles restart at a reset
+			let cycles = self.cpu.cycles().checked_sub(cycles).unwrap_or(self.cpu.cycles());
+			let regs = self.cpu.registers();
+			let offset = self.bus.prg_rom_offset(regs.pc as usize);
+			let frame = self.bus.ppu.frame_cnt();
+			profiler.observe(self.cpu.flow(), cycles, regs.pc, regs.sp, offset, frame);
+		}
+	}
 
-			self.cpu.step(&mut self.mem);
+	pub fn peek(&self, addr: usize) -> u8 {
+		self.bus.peek(addr)
+	}
 
-			if self.mem.get_dma() {
-				self.cpu.dma_transaction_occurred();
+	// a write of the CPU without its cycle, e.g. by a debugger
+	pub fn poke(&mut self, addr: usize, val: u8) {
+		self.bus.mem.write(addr, val);
+	}
+
+	pub fn ppu_peek(&mut self, addr: usize) -> u8 {
+		self.bus.mem.ppu_peek(addr)
+	}
+
+	pub fn registers(&self) -> Registers {
+		self.cpu.registers()
+	}
+
+	pub fn set_registers(&mut self, regs: Registers) {
+		self.cpu.set_registers(regs);
+	}
+
+	pub fn cycles(&self) -> u64 {
+		self.cpu.cycles()
+	}
+
+	pub fn at_instruction(&self) -> bool {
+		self.cpu.at_instruction()
+	}
+
+	// (scanline, dot)
+	pub fn ppu_position(&self) -> (usize, usize) {
+		self.bus.ppu.position()
+	}
+
+	// frames since the power-up, counted at the end of the pre-render line
+	pub fn frame_cnt(&self) -> u64 {
+		self.bus.ppu.frame_cnt()
+	}
+
+	pub fn watchpoints(&mut self) -> &mut Watchpoints {
+		self.bus.mem.watchpoints()
+	}
+
+	pub fn run_frame(&mut self) -> bool {
+		self.run_until(|_| false) == RunEvent::FbReady
+	}
+
+	// runs until the end of the frame, brk is asked before every step if it should stop earlier
+	pub fn run_until<F: FnMut(&mut Nes) -> bool>(&mut self, mut brk: F) -> RunEvent {
+		loop {
+			if brk(self) {
+				return RunEvent::Break;
 			}
 
-			if self.ppu.fb_ready() {
-				return true;
+			self.step();
+
+			if self.bus.ppu.fb_ready() {
+				return RunEvent::FbReady;
 			}
 
-			if self.ppu.frame_finished() {
-				// self.mem.dump();
-				return false;
+			if self.bus.ppu.frame_finished() {
+				// self.bus.mem.dump();
+				return RunEvent::FrameFinished;
 			}
 		}
 	}
 
+	// replaces the built-in colors, e.g. by a .pal file or the NTSC palette generator
+	pub fn set_palette(&mut self, palette: Palette) {
+		self.bus.ppu.set_palette(palette);
+	}
+
 	pub fn get_fb(&self) -> Arc<RwLock<Vec<u8>>> {
-		self.ppu.get_fb()
+		self.bus.ppu.get_fb()
+	}
+
+	// the 9 bit values of the frame and the phase of the color carrier for the NTSC filter
+	pub fn pixels(&self) -> (&[u16], usize) {
+		(self.bus.ppu.pixels(), self.bus.ppu.burst_phase())
 	}
 
 	pub fn tile_buf(&mut self) -> Arc<RwLock<Vec<u8>>> {
-		self.ppu.tile_buf(&mut self.mem)
+		self.bus.ppu.tile_buf(&mut self.bus.mem)
+	}
+
+	// a jammed CPU only recovers with a reset, the PPU keeps running
+	pub fn cpu_jammed(&self) -> bool {
+		self.cpu.is_jammed()
 	}
 
 	pub fn button_update(&mut self, btns: [JoyPad; 2]) {
-		self.mem.button_update(btns);
+		self.bus.mem.button_update(btns);
 	}
 
 	pub fn save(&mut self) {
-		let c = self.mem.cartridge();
+		let c = self.bus.mem.cartridge();
 		if c.support_savestates() {
 			c.save(self.savefile.as_ref().unwrap().as_str()).unwrap();
 		}
+
+		if let (Some(cdl), Some(file)) = (self.bus.mem.cdl(), self.cdl_file.as_ref()) {
+			if let Err(e) = cdl.save(file) {
+				println!("Unable to write the code/data log: {}", e);
+			}
+		}
+
+		if let (Some(mut profiler), Some(file)) = (self.profiler.take(), self.profile_file.as_ref())
+		{
+			let label = |a| self.labels.name(&self.bus, a).map(String::from);
+			for line in profiler.summary(label) {
+				println!("{}", line);
+			}
+			if let Err(e) = profiler.save_folded(file, label) {
+				println!("Unable to write the profile: {}", e);
+			}
+			self.profiler = Some(profiler);
+		}
 	}
 
 	// savestates currently not supported!!
-	pub fn new(rom_file: &str) -> Result<Nes, RomErr> {
+	// The ROM file can also be an archive, in this case the savefile and the patch are still named
+	// after the archive.
+	pub fn new(rom_file: &str, opts: &LoadOptions) -> Result<Nes, RomErr> {
 		if !Path::new(rom_file).exists() {
 			return Err(RomErr::FileNotFound);
 		}
 
-		let rom = fs::read(rom_file).or_else(|_| Err(RomErr::Unknown))?;
+		let mut rom = Nes::read_file(rom_file)?;
+
+		if archive::is_archive(&rom) {
+			rom = archive::extract(&rom, opts.archive_entry.as_deref())
+				.map_err(RomErr::ArchiveError)?;
+		}
+
+		let patch_file = opts.patch_file.clone().or_else(|| patch::find_patch(rom_file));
+		if let Some(pf) = patch_file.as_ref() {
+			if !Path::new(pf.as_str()).exists() {
+				return Err(RomErr::FileNotFound);
+			}
+
+			let p = Nes::read_file(pf.as_str())?;
+			rom = patch::apply(&rom, &p).map_err(RomErr::PatchError)?;
+		}
+
 		let rom_info = Nes::parse_ines(&rom)?;
 
 		let start_idx = if rom_info.trainer {
-			16 + 512
+			Nes::HEADER_SIZE + TRAINER_SIZE
 		} else {
-			16
+			Nes::HEADER_SIZE
 		};
 
-		let end_idx = if rom_info.play_choice {
-			rom.len() - Nes::PLAY_CHOICE_SIZE
-		} else {
-			rom.len()
+		let end_idx = match rom_info.play_choice.as_ref() {
+			Some(pc) => {
+				rom.len() - pc.inst_rom.len() - pc.prom.as_ref().map_or(0, |prom| prom.len())
+			}
+			None => rom.len(),
 		};
 
 		let mut cartr = cartridge::load(&rom[start_idx..end_idx], &rom_info.cartr_info)
@@ -173,13 +432,70 @@ impl Nes {
 			None
 		};
 
+		if rom_info.trainer {
+			cartr.load_trainer(&rom[Nes::HEADER_SIZE..start_idx]);
+		}
+
+		let mut mem = MemoryMap::new(cartr);
+		let ppu_model = if rom_info.vs_unisystem {
+			let model = Nes::vs_ppu_model(&rom_info, opts);
+			println!("Vs. System, PPU: {:?}", model);
+
+			mem.set_vs_system(model, opts.vs_dip_switches);
+			model
+		} else {
+			PpuModel::Rp2C02
+		};
+
+		// without a ROM database, the tags in the file name are the last hint
+		let region = opts
+			.region
+			.or(rom_info.region)
+			.or_else(|| Region::from_file_name(rom_file))
+			.unwrap_or_default();
+		if region != Region::Ntsc {
+			println!("Region: {:?}", region);
+		}
+		mem.set_region(region);
+
 		Ok(Self {
 			cpu: Cpu::new(),
-			mem: MemoryMap::new(cartr),
-			ppu: Ppu::new(),
+			bus: SystemBus::new(mem, Ppu::new(ppu_model, region), region),
 			rom_info: rom_info,
+			region,
+			patch_file,
 
 			savefile,
+			tracer: None,
+			labels: Labels::default(),
+			cdl_file: None,
+			profiler: None,
+			profile_file: None,
+		})
+	}
+
+	// The PPU can only be read out of NES 2.0 headers, for iNES ROMs it has to be passed by the
+	// user since every game needs a different one (the RP2C03 is the only one without a scrambled
+	// palette).
+	fn vs_ppu_model(rom_info: &RomInfo, opts: &LoadOptions) -> PpuModel {
+		let from_header = match &rom_info.ines_version {
+			INesVersion::V2(v2) => {
+				if v2.vs_hardware != 0 {
+					println!("Vs. System: only the UniSystem is supported: {}", v2.vs_hardware);
+				}
+
+				v2.vs_ppu
+			}
+			INesVersion::V1 => None,
+		};
+
+		opts.vs_ppu.or(from_header).unwrap_or(PpuModel::Rp2C03)
+	}
+
+	fn read_file(file: &str) -> Result<Vec<u8>, RomErr> {
+		fs::read(file).map_err(|e| match e.kind() {
+			io::ErrorKind::NotFound => RomErr::FileNotFound,
+			_ => RomErr::Unknown,
 		})
 	}
 
@@ -203,7 +519,7 @@ impl Nes {
 			return Err(RomErr::FileCorrupted);
 		}
 
-		let mut file_len: usize = 16;
+		let mut file_len: usize = Nes::HEADER_SIZE;
 		let mut desc = RomInfo::default();
 
 		desc.cartr_info.prg_rom_cnt = bytes[4] as usize; // nr. of 16KB PRG_ROM banks
@@ -213,7 +529,7 @@ impl Nes {
 		if (bytes[6] & (1 << 2)) > 0 {
 			// trainer-block present
 			desc.trainer = true;
-			file_len += 512;
+			file_len += TRAINER_SIZE;
 		}
 
 		if (bytes[6] & (1 << 3)) > 0 {
@@ -230,19 +546,32 @@ impl Nes {
 		desc.cartr_info.mapper_id = (bytes[6] >> 4) & 0x0F;
 		desc.cartr_info.mapper_id += bytes[7] & 0xF0;
 
-		// not sure what this bit is for, just parse it in case of future useage
+		// arcade ROM for the Vs. System
 		desc.vs_unisystem = (bytes[7] & (1 << 0)) > 0;
 
-		// if 8KB useless Hint-Screen data is stored after CHR-data
-		// can be used to slice the actual data correctly for creating
-		// the Cartridge-instance
-		desc.play_choice = (bytes[7] & (1 << 1)) > 0;
+		// if the PlayChoice-10 INST-ROM (and maybe the PROM) is stored after CHR-data
+		let play_choice = (bytes[7] & (1 << 1)) > 0;
 
 		// get version of the iNES header, if the bits equal 2, it is V2
 		let v2 = ((bytes[7] >> 2) & 0x03) == 2;
 
-		// nr. 8KB RAM-banks
-		if bytes[8] == 0 {
+		// the unofficial bytes 9 and 10 of iNES are ignored for dirty headers with garbage in the
+		// unused bytes, e.g. "DiskDude!"
+		let clean_ines = !v2 && bytes[11..16].iter().all(|b| *b == 0);
+
+		if clean_ines && (bytes[9] & (1 << 0)) > 0 {
+			// the TV system of iNES, only set by a few dumps
+			desc.region = Some(Region::Pal);
+		}
+
+		// the bus conflict flag of iNES, in NES 2.0 headers this byte holds the PRG-RAM sizes and
+		// only the submapper tells about bus conflicts
+		if clean_ines {
+			desc.cartr_info.bus_conflicts = (bytes[10] & (1 << 5)) > 0;
+		}
+
+		// nr. 8KB RAM-banks, in NES 2.0 headers this byte holds the submapper
+		if v2 || bytes[8] == 0 {
 			// if this value is 0, 1 bank should be assumed
 			desc.cartr_info.prg_ram_cnt = 1;
 		} else {
@@ -252,26 +581,48 @@ impl Nes {
 		if v2 {
 			let mut v2_data = INesV2Info::default();
 
-			// get TV-System, NTSC or PAL
-			v2_data.tv_system = (bytes[9] & (1 << 0)) > 0;
+			// the CPU/PPU timing: NTSC, PAL, multiple regions or Dendy
+			desc.region = Region::from_ines2_header(bytes[12]);
 
-			// if the ROM supports NTSC, PAL or both
-			match bytes[10] & 0x03 {
-				0 => v2_data.tv_system_support = TVSystemSupport::Ntsc,
-				2 => v2_data.tv_system_support = TVSystemSupport::Pal,
-				1 | 3 => v2_data.tv_system_support = TVSystemSupport::Both,
-				_ => (),
-			}
+			// the submapper tells a lot of boards apart, e.g. if the board has bus conflicts
+			desc.cartr_info.submapper_id = bytes[8] >> 4;
 
-			// true if the rom can cause bus-conflicts by writing to PRG_ROM
-			// this will be ignored for now
-			v2_data.bus_conflicts = (bytes[10] & (1 << 5)) > 0;
+			// PPU and hardware type of Vs. System ROMs
+			if desc.vs_unisystem {
+				v2_data.vs_ppu = PpuModel::from_vs_header(bytes[13]);
+				v2_data.vs_hardware = bytes[13] >> 4;
+			}
 			desc.ines_version = INesVersion::V2(v2_data);
 		}
 
 		file_len += desc.cartr_info.prg_rom_cnt * banked_mem::PRG_ROM_BANK_SIZE;
 		file_len += desc.cartr_info.chr_rom_cnt * banked_mem::CHR_ROM_BANK_SIZE;
 
+		if play_choice {
+			let start = file_len;
+			file_len += Nes::INST_ROM_SIZE;
+
+			if bytes.len() < file_len {
+				return Err(RomErr::FileCorrupted);
+			}
+
+			let inst_rom = bytes[start..file_len].to_vec();
+
+			// the PROM is optional since it was not dumped for a lot of games
+			let prom = if bytes.len() == file_len + Nes::PROM_SIZE {
+				let prom = bytes[file_len..].to_vec();
+				file_len += Nes::PROM_SIZE;
+				Some(prom)
+			} else {
+				None
+			};
+
+			desc.play_choice = Some(PlayChoiceData {
+				inst_rom,
+				prom,
+			});
+		}
+
 		if file_len != bytes.len() {
 			return Err(RomErr::FileCorrupted);
 		}
@@ -288,8 +639,45 @@ impl Default for INesVersion {
 	}
 }
 
-impl Default for TVSystemSupport {
-	fn default() -> Self {
-		TVSystemSupport::Ntsc
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn trainer_and_play_choice() {
+		// NROM with a trainer, 16KB PRG_ROM, 8KB CHR_ROM and the PlayChoice-10 data
+		let mut rom = vec![b'N', b'E', b'S', 0x1A, 1, 1, 0x04, 0x02, 0, 0, 0, 0, 0, 0, 0, 0];
+		rom.extend((0..TRAINER_SIZE).map(|i| (i as u8) ^ 0x5A));
+		rom.extend(vec![0xEA; banked_mem::PRG_ROM_BANK_SIZE]);
+		rom.extend(vec![0; banked_mem::CHR_ROM_BANK_SIZE]);
+		rom.extend(vec![0x11; Nes::INST_ROM_SIZE]);
+		rom.extend(vec![0x22; Nes::PROM_SIZE]);
+
+		// unique per process, since test runs may be concurrent
+		let name = format!("rnes_trainer_test_{}.nes", std::process::id());
+		let file = std::env::temp_dir().join(name);
+		fs::write(&file, &rom).unwrap();
+		let nes = Nes::new(file.to_str().unwrap(), &LoadOptions::default());
+		fs::remove_file(&file).unwrap();
+		let nes = nes.unwrap();
+
+		for i in 0..TRAINER_SIZE {
+			assert_eq!(nes.peek(0x7000 + i), (i as u8) ^ 0x5A);
+		}
+
+		let pc = nes.play_choice().unwrap();
+		assert_eq!(pc.inst_rom, vec![0x11; Nes::INST_ROM_SIZE]);
+		assert_eq!(pc.prom, Some(vec![0x22; Nes::PROM_SIZE]));
+	}
+
+	#[test]
+	fn dirty_header() {
+		let mut rom = vec![b'N', b'E', b'S', 0x1A, 1, 1, 0, 0, 0, 0x01, 0, 0, 0, 0, 0, 0];
+		rom.extend(vec![0; banked_mem::PRG_ROM_BANK_SIZE + banked_mem::CHR_ROM_BANK_SIZE]);
+		assert_eq!(Nes::parse_ines(&rom).unwrap().region, Some(Region::Pal));
+
+		// "DiskDude!" in bytes 7 - 15, the 's' in byte 9 is no TV system
+		rom[7..16].copy_from_slice(b"DiskDude!");
+		assert_eq!(Nes::parse_ines(&rom).unwrap().region, None);
 	}
 }
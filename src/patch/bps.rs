@@ -0,0 +1,96 @@
+use super::{check_source, check_target, read_footer, read_varint, PatchErr, FOOTER_SIZE};
+
+pub(super) const MAGIC: &[u8] = b"BPS1";
+
+const SOURCE_READ: usize = 0;
+const TARGET_READ: usize = 1;
+const SOURCE_COPY: usize = 2;
+const TARGET_COPY: usize = 3;
+
+// the offsets of the copy commands are stored as sign and magnitude relative to the last offset
+fn read_relative(patch: &[u8], pos: &mut usize, offset: usize) -> Result<usize, PatchErr> {
+	let data = read_varint(patch, pos)?;
+	let delta = data >> 1;
+
+	if (data & 0x01) > 0 {
+		offset.checked_sub(delta).ok_or(PatchErr::Corrupted)
+	} else {
+		offset.checked_add(delta).ok_or(PatchErr::Corrupted)
+	}
+}
+
+pub(super) fn apply(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchErr> {
+	let footer = read_footer(patch)?;
+	let patch = &patch[..patch.len() - FOOTER_SIZE];
+	let mut pos = MAGIC.len();
+
+	let source_size = read_varint(patch, &mut pos)?;
+	let target_size = read_varint(patch, &mut pos)?;
+	let metadata_size = read_varint(patch, &mut pos)?;
+	// metadata is not interesting for us
+	pos = pos.checked_add(metadata_size).ok_or(PatchErr::Corrupted)?;
+	if pos > patch.len() {
+		return Err(PatchErr::Corrupted);
+	}
+
+	if source_size != rom.len() {
+		return Err(PatchErr::SourceSize {
+			expected: source_size,
+			actual: rom.len(),
+		});
+	}
+	check_source(rom, &footer)?;
+
+	// the size isn't trusted for the allocation, it's checked at the end
+	let mut out: Vec<u8> = Vec::new();
+	let mut source_offset = 0usize;
+	let mut target_offset = 0usize;
+
+	while pos < patch.len() {
+		let data = read_varint(patch, &mut pos)?;
+		let len = (data >> 2) + 1;
+
+		if len > target_size - out.len() {
+			return Err(PatchErr::Corrupted);
+		}
+
+		match data & 0x03 {
+			SOURCE_READ => {
+				let start = out.len();
+				let src = rom.get(start..start + len).ok_or(PatchErr::Corrupted)?;
+				out.extend_from_slice(src);
+			}
+			TARGET_READ => {
+				let end = pos.checked_add(len).ok_or(PatchErr::Corrupted)?;
+				let src = patch.get(pos..end).ok_or(PatchErr::Corrupted)?;
+				out.extend_from_slice(src);
+				pos = end;
+			}
+			SOURCE_COPY => {
+				source_offset = read_relative(patch, &mut pos, source_offset)?;
+				let end = source_offset.checked_add(len).ok_or(PatchErr::Corrupted)?;
+				let src = rom.get(source_offset..end).ok_or(PatchErr::Corrupted)?;
+				out.extend_from_slice(src);
+				source_offset = end;
+			}
+			TARGET_COPY => {
+				target_offset = read_relative(patch, &mut pos, target_offset)?;
+
+				// source and destination may overlap, so this has to be done byte by byte
+				for _ in 0..len {
+					let b = *out.get(target_offset).ok_or(PatchErr::Corrupted)?;
+					out.push(b);
+					target_offset += 1;
+				}
+			}
+			_ => {}
+		}
+	}
+
+	if out.len() != target_size {
+		return Err(PatchErr::Corrupted);
+	}
+	check_target(&out, &footer)?;
+
+	Ok(out)
+}
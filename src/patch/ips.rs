@@ -0,0 +1,54 @@
+use super::PatchErr;
+
+pub(super) const MAGIC: &[u8] = b"PATCH";
+const EOF_MARKER: &[u8] = b"EOF";
+
+fn read_be(patch: &[u8], pos: &mut usize, bytes: usize) -> Result<usize, PatchErr> {
+	let b = patch.get(*pos..*pos + bytes).ok_or(PatchErr::Corrupted)?;
+	*pos += bytes;
+
+	Ok(b.iter().fold(0usize, |acc, v| (acc << 8) | (*v as usize)))
+}
+
+pub(super) fn apply(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchErr> {
+	let mut out = Vec::from(rom);
+	let mut pos = MAGIC.len();
+
+	loop {
+		if patch.get(pos..pos + EOF_MARKER.len()) == Some(EOF_MARKER) {
+			pos += EOF_MARKER.len();
+			break;
+		}
+
+		let offset = read_be(patch, &mut pos, 3)?;
+		let size = read_be(patch, &mut pos, 2)?;
+
+		// a size of 0 means that the record is RLE encoded
+		let (data, len) = if size == 0 {
+			let len = read_be(patch, &mut pos, 2)?;
+			let val = *patch.get(pos).ok_or(PatchErr::Corrupted)?;
+			pos += 1;
+
+			(vec![val; len], len)
+		} else {
+			let data = patch.get(pos..pos + size).ok_or(PatchErr::Corrupted)?;
+			pos += size;
+
+			(Vec::from(data), size)
+		};
+
+		// records are allowed to extend the ROM
+		if out.len() < offset + len {
+			out.resize(offset + len, 0);
+		}
+		out[offset..offset + len].copy_from_slice(&data);
+	}
+
+	// some patches contain an optional 3 byte truncation size after the EOF marker
+	if patch.len() == pos + 3 {
+		let size = read_be(patch, &mut pos, 3)?;
+		out.truncate(size);
+	}
+
+	Ok(out)
+}
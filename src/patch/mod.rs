@@ -0,0 +1,292 @@
+// Soft-patching of ROM images. The patch is applied to the whole content of the ROM file
+// (including the iNES header) right after it was read, the file on disk is never touched.
+
+mod bps;
+mod ips;
+mod ups;
+
+use std::fmt;
+use std::path::Path;
+
+use crate::util::crc32::crc32;
+
+// all supported patch formats, this order is also used when searching for a patch file
+pub const PATCH_FILE_ENDINGS: [&str; 3] = [".ips", ".ups", ".bps"];
+
+pub enum PatchErr {
+	UnknownFormat,
+	Corrupted,
+	SourceSize {
+		expected: usize,
+		actual: usize,
+	},
+	SourceChecksum {
+		expected: u32,
+		actual: u32,
+	},
+	TargetChecksum {
+		expected: u32,
+		actual: u32,
+	},
+	PatchChecksum {
+		expected: u32,
+		actual: u32,
+	},
+}
+
+impl fmt::Debug for PatchErr {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::UnknownFormat => write!(f, "UnknownFormat"),
+			Self::Corrupted => write!(f, "Corrupted"),
+			Self::SourceSize {
+				expected,
+				actual,
+			} => write!(f, "SourceSize, expected: {}, actual: {}", expected, actual),
+			Self::SourceChecksum {
+				expected,
+				actual,
+			} => write!(f, "SourceChecksum, expected: 0x{:08x}, actual: 0x{:08x}", expected, actual),
+			Self::TargetChecksum {
+				expected,
+				actual,
+			} => write!(f, "TargetChecksum, expected: 0x{:08x}, actual: 0x{:08x}", expected, actual),
+			Self::PatchChecksum {
+				expected,
+				actual,
+			} => write!(f, "PatchChecksum, expected: 0x{:08x}, actual: 0x{:08x}", expected, actual),
+		}
+	}
+}
+
+// apply the patch to the ROM, the format is detected by the magic bytes of the patch
+pub fn apply(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchErr> {
+	if patch.starts_with(ips::MAGIC) {
+		ips::apply(rom, patch)
+	} else if patch.starts_with(ups::MAGIC) {
+		ups::apply(rom, patch)
+	} else if patch.starts_with(bps::MAGIC) {
+		bps::apply(rom, patch)
+	} else {
+		Err(PatchErr::UnknownFormat)
+	}
+}
+
+// search for a patch file next to the ROM which has the same name as the ROM itself
+pub fn find_patch(rom_file: &str) -> Option<String> {
+	let p = Path::new(rom_file);
+	let stem = p.file_stem()?;
+
+	PATCH_FILE_ENDINGS
+		.iter()
+		.map(|ending| {
+			let mut name = stem.to_os_string();
+			name.push(ending);
+			p.with_file_name(name)
+		})
+		.find(|patch| patch.exists())
+		.map(|patch| patch.to_string_lossy().into_owned())
+}
+
+// variable-length integer as it is used by the UPS and BPS format
+fn read_varint(data: &[u8], pos: &mut usize) -> Result<usize, PatchErr> {
+	let mut val: usize = 0;
+	let mut shift: usize = 1;
+
+	loop {
+		let b = *data.get(*pos).ok_or(PatchErr::Corrupted)?;
+		*pos += 1;
+
+		val = (((b & 0x7F) as usize).checked_mul(shift))
+			.and_then(|v| v.checked_add(val))
+			.ok_or(PatchErr::Corrupted)?;
+
+		if (b & 0x80) > 0 {
+			return Ok(val);
+		}
+
+		shift = shift.checked_shl(7).ok_or(PatchErr::Corrupted)?;
+		val = val.checked_add(shift).ok_or(PatchErr::Corrupted)?;
+	}
+}
+
+fn read_u32_le(data: &[u8], pos: usize) -> Result<u32, PatchErr> {
+	let b = data.get(pos..pos + 4).ok_or(PatchErr::Corrupted)?;
+	Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+// UPS and BPS both end with the CRC32 of the source, the target and the patch itself
+struct Footer {
+	source_crc: u32,
+	target_crc: u32,
+}
+
+const FOOTER_SIZE: usize = 12;
+
+fn read_footer(patch: &[u8]) -> Result<Footer, PatchErr> {
+	if patch.len() < FOOTER_SIZE {
+		return Err(PatchErr::Corrupted);
+	}
+
+	let start = patch.len() - FOOTER_SIZE;
+	let patch_crc = read_u32_le(patch, start + 8)?;
+
+	// the CRC of the patch covers everything except the CRC itself
+	let actual = crc32(&patch[..start + 8]);
+	if actual != patch_crc {
+		return Err(PatchErr::PatchChecksum {
+			expected: patch_crc,
+			actual,
+		});
+	}
+
+	Ok(Footer {
+		source_crc: read_u32_le(patch, start)?,
+		target_crc: read_u32_le(patch, start + 4)?,
+	})
+}
+
+fn check_source(source: &[u8], footer: &Footer) -> Result<(), PatchErr> {
+	let actual = crc32(source);
+	if actual != footer.source_crc {
+		return Err(PatchErr::SourceChecksum {
+			expected: footer.source_crc,
+			actual,
+		});
+	}
+
+	Ok(())
+}
+
+fn check_target(target: &[u8], footer: &Footer) -> Result<(), PatchErr> {
+	let actual = crc32(target);
+	if actual != footer.target_crc {
+		return Err(PatchErr::TargetChecksum {
+			expected: footer.target_crc,
+			actual,
+		});
+	}
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn write_varint(out: &mut Vec<u8>, mut val: usize) {
+		loop {
+			let x = (val & 0x7F) as u8;
+			val >>= 7;
+			if val == 0 {
+				out.push(0x80 | x);
+				break;
+			}
+			out.push(x);
+			val -= 1;
+		}
+	}
+
+	fn append_footer(patch: &mut Vec<u8>, source: &[u8], target: &[u8]) {
+		patch.extend_from_slice(&crc32(source).to_le_bytes());
+		patch.extend_from_slice(&crc32(target).to_le_bytes());
+		let crc = crc32(patch);
+		patch.extend_from_slice(&crc.to_le_bytes());
+	}
+
+	#[test]
+	fn varint_roundtrip() {
+		for val in [0usize, 1, 0x7F, 0x80, 0x4000, 0x12_3456].iter() {
+			let mut data = Vec::new();
+			write_varint(&mut data, *val);
+
+			let mut pos = 0;
+			assert_eq!(read_varint(&data, &mut pos).ok(), Some(*val));
+			assert_eq!(pos, data.len());
+		}
+	}
+
+	#[test]
+	fn apply_ips() {
+		let rom = [0u8; 8];
+		let mut patch = Vec::from(b"PATCH".as_ref());
+		patch.extend_from_slice(&[0x00, 0x00, 0x01, 0x00, 0x02, 0xAA, 0xBB]); // normal record
+		patch.extend_from_slice(&[0x00, 0x00, 0x06, 0x00, 0x00, 0x00, 0x04, 0xCC]); // RLE record
+		patch.extend_from_slice(b"EOF");
+
+		let out = apply(&rom, &patch).unwrap();
+		assert_eq!(out, vec![0x00, 0xAA, 0xBB, 0x00, 0x00, 0x00, 0xCC, 0xCC, 0xCC, 0xCC]);
+	}
+
+	#[test]
+	fn apply_ups() {
+		let rom = [0x10u8, 0x20, 0x30, 0x40];
+		let target = [0x10u8, 0x21, 0x30, 0x40, 0x50];
+
+		let mut patch = Vec::from(b"UPS1".as_ref());
+		write_varint(&mut patch, rom.len());
+		write_varint(&mut patch, target.len());
+		write_varint(&mut patch, 1);
+		patch.extend_from_slice(&[0x20 ^ 0x21, 0x00]);
+		write_varint(&mut patch, 1); // relative to the byte after the last terminator
+		patch.extend_from_slice(&[0x50, 0x00]);
+		append_footer(&mut patch, &rom, &target);
+
+		assert_eq!(apply(&rom, &patch).unwrap(), target.to_vec());
+
+		// a wrong source must be detected instead of producing garbage
+		match apply(&[0x00, 0x20, 0x30, 0x40], &patch) {
+			Err(PatchErr::SourceChecksum {
+				..
+			}) => {}
+			_ => panic!("UPS: source checksum mismatch was not detected"),
+		}
+	}
+
+	#[test]
+	fn apply_bps() {
+		let rom = [0x01u8, 0x02, 0x03, 0x04];
+		let target = [0x01u8, 0x02, 0xEE, 0xEE, 0xEE, 0x03, 0x04];
+
+		let mut patch = Vec::from(b"BPS1".as_ref());
+		write_varint(&mut patch, rom.len());
+		write_varint(&mut patch, target.len());
+		write_varint(&mut patch, 0);
+		write_varint(&mut patch, ((2 - 1) << 2) | 0); // SourceRead of 2 bytes
+		write_varint(&mut patch, ((1 - 1) << 2) | 1); // TargetRead of 1 byte
+		patch.push(0xEE);
+		write_varint(&mut patch, ((2 - 1) << 2) | 3); // TargetCopy of 2 bytes from offset 2
+		write_varint(&mut patch, 2 << 1);
+		write_varint(&mut patch, ((2 - 1) << 2) | 2); // SourceCopy of 2 bytes from offset 2
+		write_varint(&mut patch, 2 << 1);
+		append_footer(&mut patch, &rom, &target);
+
+		assert_eq!(apply(&rom, &patch).unwrap(), target.to_vec());
+
+		// corrupt the patch, this has to be detected by the patch checksum
+		let len = patch.len();
+		patch[len - 13] ^= 0xFF;
+		match apply(&rom, &patch) {
+			Err(PatchErr::PatchChecksum {
+				..
+			}) => {}
+			_ => panic!("BPS: patch checksum mismatch was not detected"),
+		}
+	}
+
+	#[test]
+	fn bps_bad_sizes() {
+		let rom = [0x01u8, 0x02];
+
+		// metadata beyond the end of the patch and a target size which can't be allocated
+		for (target_size, metadata_size) in [(2, usize::MAX - 2), (usize::MAX, 0)] {
+			let mut patch = Vec::from(b"BPS1".as_ref());
+			write_varint(&mut patch, rom.len());
+			write_varint(&mut patch, target_size);
+			write_varint(&mut patch, metadata_size);
+			append_footer(&mut patch, &rom, &rom);
+
+			assert!(matches!(apply(&rom, &patch), Err(PatchErr::Corrupted)));
+		}
+	}
+}
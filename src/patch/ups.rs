@@ -0,0 +1,47 @@
+use super::{check_source, check_target, read_footer, read_varint, PatchErr, FOOTER_SIZE};
+
+pub(super) const MAGIC: &[u8] = b"UPS1";
+
+pub(super) fn apply(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchErr> {
+	let footer = read_footer(patch)?;
+	let end = patch.len() - FOOTER_SIZE;
+	let mut pos = MAGIC.len();
+
+	let source_size = read_varint(patch, &mut pos)?;
+	let target_size = read_varint(patch, &mut pos)?;
+
+	if source_size != rom.len() {
+		return Err(PatchErr::SourceSize {
+			expected: source_size,
+			actual: rom.len(),
+		});
+	}
+	check_source(rom, &footer)?;
+
+	let mut out = Vec::from(rom);
+	out.resize(target_size, 0);
+
+	// every hunk consists of a relative offset and the XOR-difference which is terminated by 0
+	let mut out_pos = 0usize;
+	while pos < end {
+		out_pos += read_varint(patch, &mut pos)?;
+
+		loop {
+			let x = *patch[..end].get(pos).ok_or(PatchErr::Corrupted)?;
+			pos += 1;
+
+			if out_pos < target_size {
+				out[out_pos] ^= x;
+			}
+			out_pos += 1;
+
+			if x == 0 {
+				break;
+			}
+		}
+	}
+
+	check_target(&out, &footer)?;
+
+	Ok(out)
+}
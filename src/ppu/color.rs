@@ -1,5 +1,6 @@
 use crate::cfn_assert;
 
+#[derive(Clone, Copy)]
 pub struct Color {
 	r: u8,
 	g: u8,
@@ -121,3 +122,69 @@ pub const COLOR_PALETTE: &[Color; COLOR_PALETTE_SIZE] = &[
 	c!(0xE4E594), c!(0xCFEF96), c!(0xBDF4AB), c!(0xB3F3CC),
 	c!(0xB5EBf2), c!(0xB8B8B8), c!(0x000000), c!(0x000000),
 ];
+
+// palette of the RGB PPUs (RP2C03, RP2C04, RC2C05) used by the Vs. System and the PlayChoice-10,
+// every channel only has 3 bits
+#[rustfmt::skip]
+pub const RGB_COLOR_PALETTE: &[Color; COLOR_PALETTE_SIZE] = &[
+	c!(0x6D6D6D), c!(0x002492), c!(0x0000DB), c!(0x6D49DB),
+	c!(0x92006D), c!(0xB6006D), c!(0xB62400), c!(0x924900),
+	c!(0x6D4900), c!(0x244900), c!(0x006D24), c!(0x009200),
+	c!(0x004949), c!(0x000000), c!(0x000000), c!(0x000000),
+	c!(0xB6B6B6), c!(0x006DDB), c!(0x0049FF), c!(0x9200FF),
+	c!(0xB600FF), c!(0xFF0092), c!(0xFF0000), c!(0xDB6D00),
+	c!(0x926D00), c!(0x249200), c!(0x009200), c!(0x00B66D),
+	c!(0x009292), c!(0x000000), c!(0x000000), c!(0x000000),
+	c!(0xFFFFFF), c!(0x6DB6FF), c!(0x9292FF), c!(0xDB6DFF),
+	c!(0xFF00FF), c!(0xFF6DFF), c!(0xFF9200), c!(0xFFB600),
+	c!(0xDBDB00), c!(0x6DDB00), c!(0x00FF00), c!(0x49FFDB),
+	c!(0x00FFFF), c!(0x000000), c!(0x000000), c!(0x000000),
+	c!(0xFFFFFF), c!(0xB6DBFF), c!(0xDBB6FF), c!(0xFFB6FF),
+	c!(0xFF92FF), c!(0xFFB6B6), c!(0xFFDB92), c!(0xFFFF49),
+	c!(0xFFFF6D), c!(0xB6FF49), c!(0x92FF6D), c!(0x49FFDB),
+	c!(0x92DBFF), c!(0x000000), c!(0x000000), c!(0x000000),
+];
+
+// lookup table for all PPUs which don't scramble the palette
+#[rustfmt::skip]
+pub const IDENTITY_LUT: &[u8; COLOR_PALETTE_SIZE] = &[
+	0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0A, 0x0B, 0x0C, 0x0D, 0x0E, 0x0F,
+	0x10, 0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17, 0x18, 0x19, 0x1A, 0x1B, 0x1C, 0x1D, 0x1E, 0x1F,
+	0x20, 0x21, 0x22, 0x23, 0x24, 0x25, 0x26, 0x27, 0x28, 0x29, 0x2A, 0x2B, 0x2C, 0x2D, 0x2E, 0x2F,
+	0x30, 0x31, 0x32, 0x33, 0x34, 0x35, 0x36, 0x37, 0x38, 0x39, 0x3A, 0x3B, 0x3C, 0x3D, 0x3E, 0x3F,
+];
+
+// The RP2C04 PPUs have scrambled palettes in order to prevent swapping the game-ROMs between
+// the different Vs. System games. These tables map a color index of a RP2C04 to the index of the
+// same color in the RGB palette.
+#[rustfmt::skip]
+pub const RP2C04_LUTS: [&[u8; COLOR_PALETTE_SIZE]; 4] = [
+	// RP2C04-0001
+	&[
+		0x35, 0x23, 0x16, 0x22, 0x1C, 0x09, 0x1D, 0x15, 0x20, 0x00, 0x27, 0x05, 0x04, 0x28, 0x08, 0x20,
+		0x21, 0x3E, 0x1F, 0x29, 0x3C, 0x32, 0x36, 0x12, 0x3F, 0x2B, 0x2E, 0x1E, 0x3D, 0x2D, 0x24, 0x01,
+		0x0E, 0x31, 0x33, 0x2A, 0x2C, 0x0C, 0x1B, 0x14, 0x2E, 0x07, 0x34, 0x06, 0x13, 0x02, 0x26, 0x2E,
+		0x2E, 0x19, 0x10, 0x0A, 0x39, 0x03, 0x37, 0x17, 0x0F, 0x11, 0x0B, 0x0D, 0x38, 0x25, 0x18, 0x3A,
+	],
+	// RP2C04-0002
+	&[
+		0x2E, 0x27, 0x18, 0x39, 0x3A, 0x25, 0x1C, 0x31, 0x16, 0x13, 0x38, 0x34, 0x20, 0x23, 0x3C, 0x0B,
+		0x0F, 0x21, 0x06, 0x3D, 0x1B, 0x29, 0x1E, 0x22, 0x1D, 0x24, 0x0E, 0x2B, 0x32, 0x08, 0x2E, 0x03,
+		0x04, 0x36, 0x26, 0x33, 0x11, 0x1F, 0x10, 0x02, 0x14, 0x3F, 0x00, 0x09, 0x12, 0x2E, 0x28, 0x20,
+		0x3E, 0x0D, 0x2A, 0x17, 0x0C, 0x01, 0x15, 0x19, 0x2E, 0x2C, 0x07, 0x37, 0x35, 0x05, 0x0A, 0x2F,
+	],
+	// RP2C04-0003
+	&[
+		0x14, 0x25, 0x3A, 0x10, 0x0B, 0x20, 0x31, 0x09, 0x01, 0x2E, 0x36, 0x08, 0x15, 0x3D, 0x3E, 0x3C,
+		0x22, 0x1C, 0x05, 0x12, 0x19, 0x18, 0x17, 0x1B, 0x00, 0x03, 0x2E, 0x02, 0x16, 0x06, 0x34, 0x35,
+		0x23, 0x0F, 0x0E, 0x37, 0x0D, 0x27, 0x26, 0x20, 0x29, 0x04, 0x21, 0x24, 0x11, 0x2D, 0x2E, 0x1F,
+		0x2C, 0x1E, 0x39, 0x33, 0x07, 0x2A, 0x28, 0x1D, 0x0A, 0x2E, 0x32, 0x38, 0x13, 0x2B, 0x3F, 0x0C,
+	],
+	// RP2C04-0004
+	&[
+		0x18, 0x03, 0x1C, 0x28, 0x2E, 0x35, 0x01, 0x17, 0x10, 0x1F, 0x2A, 0x0E, 0x36, 0x37, 0x0B, 0x39,
+		0x25, 0x1E, 0x12, 0x34, 0x2E, 0x1D, 0x06, 0x26, 0x3E, 0x1B, 0x22, 0x19, 0x04, 0x2E, 0x3A, 0x21,
+		0x05, 0x0A, 0x07, 0x02, 0x13, 0x14, 0x00, 0x15, 0x0C, 0x3D, 0x11, 0x0F, 0x0D, 0x38, 0x2D, 0x24,
+		0x33, 0x20, 0x08, 0x16, 0x3F, 0x2B, 0x20, 0x3C, 0x2E, 0x27, 0x23, 0x31, 0x29, 0x32, 0x2C, 0x09,
+	],
+];
@@ -6,6 +6,8 @@ pub struct FrameBuffer {
 	width: usize,
 	height: usize,
 	fb: Arc<RwLock<Vec<u8>>>,
+	// the 9 bit values of the PPU (emphasis << 6 | color) for the NTSC filter
+	pixels: Vec<u16>,
 }
 
 impl FrameBuffer {
@@ -15,13 +17,15 @@ impl FrameBuffer {
 			height: height_px,
 			// RGB, every pixel needs 3 bytes
 			fb: Arc::new(RwLock::new(vec![0x00; width_px * height_px * 3])),
+			pixels: vec![0x00; width_px * height_px],
 		}
 	}
 
-	pub fn render_pixel(&mut self, idx_x: usize, idx_y: usize, color: &Color) {
+	pub fn render_pixel(&mut self, idx_x: usize, idx_y: usize, color: &Color, pixel: u16) {
 		assert!(idx_x < self.width, "FB: idx_x out of range: {}", idx_x);
 		assert!(idx_y < self.height, "FB: idx_y out of range: {}", idx_y);
 
+		self.pixels[idx_y * self.width + idx_x] = pixel;
 		let idx = 3 * (idx_y * self.width + idx_x);
 
 		{
@@ -32,6 +36,10 @@ impl FrameBuffer {
 		}
 	}
 
+	pub fn pixels(&self) -> &[u16] {
+		&self.pixels
+	}
+
 	pub fn fb(&self) -> Arc<RwLock<Vec<u8>>> {
 		self.fb.clone()
 	}
@@ -0,0 +1,64 @@
+// The PPU keeps the last value which was written to or read from one of its registers on its
+// internal data bus (the I/O latch). Reads of the write-only registers return it, as well as the
+// bits which aren't driven by a register, e.g. the lower 5 bits of $2002. The latch is a
+// capacitance, so every bit decays to 0 about 600ms after it was last driven with a 1.
+
+// about 600ms in CPU cycles
+const DECAY_CYCLES: u64 = 1_073_864;
+
+#[derive(Default)]
+pub(crate) struct IoLatch {
+	val: u8,
+	// the CPU cycle at which the bit was driven
+	refreshed: [u64; 8],
+	cycle: u64,
+}
+
+impl IoLatch {
+	// called once every CPU cycle
+	pub(crate) fn clock(&mut self) {
+		self.cycle += 1;
+	}
+
+	// only the bits of the mask are driven, e.g. all of them by a write
+	pub(crate) fn set(&mut self, val: u8, mask: u8) {
+		self.val = (self.val & !mask) | (val & mask);
+		for (i, r) in self.refreshed.iter_mut().enumerate() {
+			if (mask & (1 << i)) > 0 {
+				*r = self.cycle;
+			}
+		}
+	}
+
+	pub(crate) fn get(&mut self) -> u8 {
+		for (i, r) in self.refreshed.iter().enumerate() {
+			if self.cycle - r > DECAY_CYCLES {
+				self.val &= !(1 << i);
+			}
+		}
+
+		self.val
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn decay() {
+		let mut latch = IoLatch::default();
+		latch.set(0xFF, 0xFF);
+		for _ in 0..DECAY_CYCLES / 2 {
+			latch.clock();
+		}
+		// only the upper bits are refreshed, e.g. by a $2002 read
+		latch.set(0xA0, 0xE0);
+		assert_eq!(latch.get(), 0xBF);
+
+		for _ in 0..DECAY_CYCLES / 2 + 1 {
+			latch.clock();
+		}
+		assert_eq!(latch.get(), 0xA0);
+	}
+}
@@ -1,6 +1,10 @@
 mod color;
 mod framebuffer;
+mod io_latch;
+pub mod model;
+pub mod ntsc;
 mod oam_buffer;
+pub mod palette;
 pub mod ppu;
 pub mod ppu_regs;
 mod shiftreg;
@@ -0,0 +1,107 @@
+use super::color::{
+	Color, COLOR_PALETTE, COLOR_PALETTE_SIZE, IDENTITY_LUT, RGB_COLOR_PALETTE, RP2C04_LUTS,
+};
+
+// The different PPUs which were used in the NES and in the arcade systems. The RGB PPUs of the
+// Vs. System output RGB instead of composite video, so they use a different palette.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum PpuModel {
+	#[default]
+	Rp2C02,
+	Rp2C03,
+	Rp2C04(u8), // variant 0001 - 0004
+	Rc2C05(u8), // variant 01 - 05
+}
+
+impl PpuModel {
+	// PPU type of a Vs. System ROM stored in the lower nibble of byte 13 of the NES 2.0 header
+	pub fn from_vs_header(val: u8) -> Option<Self> {
+		let val = val & 0x0F;
+		match val {
+			0x0 | 0x1 | 0x6 | 0x7 => Some(Self::Rp2C03),
+			0x2..=0x5 => Some(Self::Rp2C04(val - 0x1)),
+			0x8..=0xC => Some(Self::Rc2C05(val - 0x7)),
+			_ => None,
+		}
+	}
+
+	// parse the name of the PPU as it is printed on the chip, e.g. "2C04-0001" or "RC2C05-03"
+	pub fn from_name(name: &str) -> Option<Self> {
+		let name = name.to_uppercase();
+		let name = name.trim_start_matches('R').trim_start_matches(['P', 'C']);
+
+		match name.split_once('-') {
+			None if name == "2C02" => Some(Self::Rp2C02),
+			None if name == "2C03" => Some(Self::Rp2C03),
+			Some(("2C04", v)) => match v.parse::<u8>() {
+				Ok(v) if (1..=4).contains(&v) => Some(Self::Rp2C04(v)),
+				_ => None,
+			},
+			Some(("2C05", v)) => match v.parse::<u8>() {
+				Ok(v) if (1..=5).contains(&v) => Some(Self::Rc2C05(v)),
+				_ => None,
+			},
+			_ => None,
+		}
+	}
+
+	// the RGB PPUs emphasize by turning the channels fully on
+	pub fn rgb(&self) -> bool {
+		*self != Self::Rp2C02
+	}
+
+	pub fn palette(&self) -> &'static [Color; COLOR_PALETTE_SIZE] {
+		match self {
+			Self::Rp2C02 => COLOR_PALETTE,
+			_ => RGB_COLOR_PALETTE,
+		}
+	}
+
+	pub fn palette_lut(&self) -> &'static [u8; COLOR_PALETTE_SIZE] {
+		match self {
+			Self::Rp2C04(v) => RP2C04_LUTS[(*v - 1) as usize],
+			_ => IDENTITY_LUT,
+		}
+	}
+
+	// the 2C05 returns an ID in the lower 6 bits of PPUSTATUS instead of the I/O latch and the sprite
+	// overflow flag, games check it with $2002 & $3F
+	pub fn status_id(&self) -> Option<u8> {
+		match self {
+			Self::Rc2C05(1) | Self::Rc2C05(4) => Some(0x1B),
+			Self::Rc2C05(2) => Some(0x3D),
+			Self::Rc2C05(3) => Some(0x1C),
+			_ => None,
+		}
+	}
+
+	// PPUCTRL and PPUMASK are swapped on the 2C05
+	pub fn ctrl_mask_swapped(&self) -> bool {
+		matches!(self, Self::Rc2C05(_))
+	}
+}
+
+#[test]
+fn test_ppu_model_from_name() {
+	assert_eq!(PpuModel::from_name("2C02"), Some(PpuModel::Rp2C02));
+	assert_eq!(PpuModel::from_name("RP2C03"), Some(PpuModel::Rp2C03));
+	assert_eq!(PpuModel::from_name("rp2c04-0003"), Some(PpuModel::Rp2C04(3)));
+	assert_eq!(PpuModel::from_name("RC2C05-02"), Some(PpuModel::Rc2C05(2)));
+	assert_eq!(PpuModel::from_name("2C04-0005"), None);
+	assert_eq!(PpuModel::from_vs_header(0x05), Some(PpuModel::Rp2C04(4)));
+	assert_eq!(PpuModel::from_vs_header(0x0C), Some(PpuModel::Rc2C05(5)));
+}
+
+#[test]
+fn test_ppu_model_status_id() {
+	use crate::mem::PpuRegisterAccess;
+	use crate::ppu::ppu_regs::PpuRegisters;
+
+	for (model, id) in
+		[(PpuModel::Rc2C05(1), 0x1B), (PpuModel::Rc2C05(2), 0x3D), (PpuModel::Rc2C05(3), 0x1C)]
+	{
+		let mut regs = PpuRegisters::new();
+		regs.set_model(model);
+		assert_eq!(regs.ppu_stat_read() & 0x3F, id, "{:?}", model);
+	}
+}
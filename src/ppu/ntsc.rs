@@ -0,0 +1,229 @@
+// NTSC filter in the spirit of blargg's nes_ntsc: instead of mapping every pixel to a color, the
+// composite signal of a scanline is synthesized from the 9 bit values of the PPU (emphasis << 6 |
+// color) and decoded like a TV. A pixel lasts 8 of the 12 phases of the color carrier, so the
+// phase changes from pixel to pixel and from scanline to scanline (by 4, a scanline has 341 dots)
+// and the colors bleed into each other at the edges. Every line of 256 pixels is resampled to
+// 602 pixels (7 for 3), which keeps the aspect ratio of the TV and the details of the signal.
+// The decoding can be adjusted:
+// - sharpness: emphasizes (> 0) or blurs (< 0) the edges of the luma
+// - fringing: how much of the color carrier leaks into the luma, i.e. the dots at color edges
+// - artifacts: the bandwidth of the chroma, i.e. the rainbows at luma edges
+
+use super::color::Color;
+use super::palette::{composite_level, yiq_to_rgb, NtscSettings, PALETTE_SIZE, PHASES};
+use super::ppu::{SCREEN_HEIGHT, SCREEN_WIDTH};
+
+pub const NTSC_WIDTH: usize = (SCREEN_WIDTH - 1) / 3 * 7 + 7;
+
+// the signal is sampled at every master clock
+const SAMPLES_PER_PIXEL: usize = 8;
+// the edge pixels are repeated, so the windows at the border are filled
+const BORDER_PIXELS: usize = 4;
+const LINE_SAMPLES: usize = (SCREEN_WIDTH + 2 * BORDER_PIXELS) * SAMPLES_PER_PIXEL;
+
+// windows in samples, a full period of the carrier cancels the chroma out of the luma
+const LUMA_WINDOW: usize = PHASES;
+const FRINGE_WINDOW: usize = PHASES / 3;
+const BLUR_WINDOW: usize = 3 * PHASES;
+const CHROMA_WINDOW: usize = PHASES;
+const CHROMA_WIDE_WINDOW: usize = 3 * PHASES;
+
+#[derive(Clone, Copy)]
+pub struct NtscFilterSettings {
+	pub tv: NtscSettings,
+	// -1.0 to 1.0
+	pub sharpness: f64,
+	// 0.0 to 1.0
+	pub fringing: f64,
+	pub artifacts: f64,
+}
+
+pub struct NtscFilter {
+	settings: NtscFilterSettings,
+	// the adjusted level of every pixel value at every phase
+	levels: Vec<[f64; PHASES]>,
+	// the carrier at every phase, rotated by the hue
+	carrier: [(f64, f64); PHASES],
+	// prefix sums of the signal and the demodulated I and Q of a scanline
+	sums: [Vec<f64>; 3],
+}
+
+impl Default for NtscFilterSettings {
+	// a composite TV whose notch filter lets some of the carrier through
+	fn default() -> Self {
+		Self {
+			tv: NtscSettings::default(),
+			sharpness: 0.0,
+			fringing: 0.5,
+			artifacts: 1.0,
+		}
+	}
+}
+
+impl NtscFilterSettings {
+	// "<sharpness>,<fringing>,<artifacts>"
+	pub fn parse(s: &str, tv: NtscSettings) -> Option<Self> {
+		let vals: Vec<f64> = s.split(',').map(|v| v.trim().parse().ok()).collect::<Option<_>>()?;
+		match vals[..] {
+			[sharpness, fringing, artifacts] => Some(Self {
+				tv,
+				sharpness,
+				fringing,
+				artifacts,
+			}),
+			_ => None,
+		}
+	}
+}
+
+impl NtscFilter {
+	pub fn new(settings: NtscFilterSettings) -> Self {
+		let levels = (0..PALETTE_SIZE)
+			.map(|idx| {
+				let mut l = [0.0; PHASES];
+				for (p, level) in l.iter_mut().enumerate() {
+					*level = settings.tv.adjust(composite_level(idx, p));
+				}
+				l
+			})
+			.collect();
+
+		let mut carrier = [(0.0, 0.0); PHASES];
+		for (p, c) in carrier.iter_mut().enumerate() {
+			let angle = settings.tv.angle(p);
+			*c = (angle.cos(), angle.sin());
+		}
+
+		Self {
+			settings,
+			levels,
+			carrier,
+			sums: [
+				vec![0.0; LINE_SAMPLES + 1],
+				vec![0.0; LINE_SAMPLES + 1],
+				vec![0.0; LINE_SAMPLES + 1],
+			],
+		}
+	}
+
+	// filters a frame of 9 bit values into RGB with NTSC_WIDTH pixels per line, the phase is the
+	// one of the carrier at the first pixel (see Ppu::burst_phase())
+	pub fn filter(&mut self, pixels: &[u16], phase: usize, out: &mut [u8]) {
+		assert_eq!(pixels.len(), SCREEN_WIDTH * SCREEN_HEIGHT);
+		assert_eq!(out.len(), NTSC_WIDTH * SCREEN_HEIGHT * 3);
+
+		for (y, (line, out_line)) in
+			pixels.chunks_exact(SCREEN_WIDTH).zip(out.chunks_exact_mut(NTSC_WIDTH * 3)).enumerate()
+		{
+			// a scanline lasts 341 * 8 clocks
+			self.modulate(line, (phase + 4 * y) % PHASES);
+			for (x, rgb) in out_line.chunks_exact_mut(3).enumerate() {
+				let c = self.decode(x);
+				rgb.copy_from_slice(&[c.r(), c.g(), c.b()]);
+			}
+		}
+	}
+
+	// synthesizes the signal of a scanline and demodulates it into the prefix sums
+	fn modulate(&mut self, line: &[u16], phase: usize) {
+		// the border starts BORDER_PIXELS before the first pixel
+		let start = phase + PHASES - (BORDER_PIXELS * SAMPLES_PER_PIXEL) % PHASES;
+		let [sig, i, q] = &mut self.sums;
+
+		for s in 0..LINE_SAMPLES {
+			let x = (s / SAMPLES_PER_PIXEL).saturating_sub(BORDER_PIXELS).min(SCREEN_WIDTH - 1);
+			let p = (start + s) % PHASES;
+			let v = self.levels[line[x] as usize % PALETTE_SIZE][p];
+			let (cos, sin) = self.carrier[p];
+
+			sig[s + 1] = sig[s] + v;
+			i[s + 1] = i[s] + v * cos;
+			q[s + 1] = q[s] + v * sin;
+		}
+	}
+
+	// the average of a prefix sum over a window centered at the sample
+	fn window(sums: &[f64], center: f64, width: usize) -> f64 {
+		let start = (center - width as f64 / 2.0).round().max(0.0) as usize;
+		let end = (start + width).min(LINE_SAMPLES);
+		(sums[end] - sums[start]) / (end - start) as f64
+	}
+
+	fn decode(&self, x: usize) -> Color {
+		let s = &self.settings;
+		let [sig, i, q] = &self.sums;
+		let center = (BORDER_PIXELS * SAMPLES_PER_PIXEL) as f64
+			+ (x as f64 + 0.5) * (SCREEN_WIDTH * SAMPLES_PER_PIXEL) as f64 / NTSC_WIDTH as f64;
+
+		let luma = Self::window(sig, center, LUMA_WINDOW);
+		let fringe = Self::window(sig, center, FRINGE_WINDOW) - luma;
+		let blur = Self::window(sig, center, BLUR_WINDOW);
+		let y = luma + s.fringing * fringe + s.sharpness * (luma - blur);
+
+		// the demodulation yields half of the amplitude
+		let chroma = |sums: &[f64]| {
+			let narrow = Self::window(sums, center, CHROMA_WINDOW);
+			let wide = Self::window(sums, center, CHROMA_WIDE_WINDOW);
+			(s.artifacts * narrow + (1.0 - s.artifacts) * wide) * s.tv.saturation
+		};
+
+		yiq_to_rgb(y, chroma(i), chroma(q))
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::ppu::palette::Palette;
+
+	#[test]
+	fn flat_colors() {
+		assert_eq!(NTSC_WIDTH, 602);
+
+		// without fringing as the carrier would leave dots in the luma
+		let mut filter = NtscFilter::new(NtscFilterSettings {
+			fringing: 0.0,
+			..Default::default()
+		});
+		let palette = Palette::ntsc(&NtscSettings::default());
+		let mut out = vec![0; NTSC_WIDTH * SCREEN_HEIGHT * 3];
+
+		// a frame of a single color decodes to the color of the palette at every phase
+		for &idx in &[0x0F, 0x16, 0x2A, 0x30, (2 << 6) | 0x21] {
+			let pixels = vec![idx as u16; SCREEN_WIDTH * SCREEN_HEIGHT];
+			for phase in 0..PHASES {
+				filter.filter(&pixels, phase, &mut out);
+				let c = palette.color(idx);
+				for rgb in out.chunks_exact(3).step_by(97) {
+					for (a, b) in rgb.iter().zip(&[c.r(), c.g(), c.b()]) {
+						assert!((*a as i32 - *b as i32).abs() <= 1, "${:03X}: {:?}", idx, rgb);
+					}
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn fringing() {
+		let mut filter = NtscFilter::new(NtscFilterSettings::default());
+		let mut out = vec![0; NTSC_WIDTH * SCREEN_HEIGHT * 3];
+		// white on the left half, black on the right
+		let pixels: Vec<u16> = (0..SCREEN_WIDTH * SCREEN_HEIGHT)
+			.map(|i| {
+				if i % SCREEN_WIDTH < 128 {
+					0x30
+				} else {
+					0x0F
+				}
+			})
+			.collect();
+		filter.filter(&pixels, 0, &mut out);
+
+		let pixel = |x: usize| &out[3 * x..3 * x + 3];
+		assert_eq!(pixel(0), [0xFF, 0xFF, 0xFF]);
+		assert_eq!(pixel(NTSC_WIDTH - 1), [0x00, 0x00, 0x00]);
+		// the edge isn't grey
+		let edge = pixel(NTSC_WIDTH / 2);
+		assert!(edge[0] != edge[1] || edge[1] != edge[2], "{:?}", edge);
+	}
+}
@@ -1,8 +1,23 @@
-use crate::mem::PpuBus;
+use crate::mask;
+use crate::mem::{PpuBus, Segment};
 use crate::util::bit::{reverse_u8, test_bit};
 
 use super::shiftreg::ShiftReg8;
 
+const SEC_OAM_SIZE: usize = 32;
+const SPRITE_SIZE: usize = 4;
+const MAX_LINE_SPRITES: usize = 8;
+
+// dots of the PPU which handle the sprites of the next line
+pub(crate) const CLEAR_END: usize = 64;
+pub(crate) const EVAL_START: usize = 65;
+pub(crate) const EVAL_END: usize = 256;
+pub(crate) const FETCH_START: usize = 257;
+pub(crate) const FETCH_END: usize = 320;
+const FETCH_DOTS: usize = 8;
+// the last visible line, the pre-render line (261 on NTSC) doesn't evaluate sprites
+const EVAL_LINE_MAX: usize = 239;
+
 pub(crate) struct ComposedSprite {
 	pub(crate) sprite0: bool,
 	pub(crate) pix: u8,
@@ -22,10 +37,36 @@ pub(crate) struct Sprite {
 	sprite0: bool,
 }
 
+// State of the sprite evaluation, which reads a byte of the OAM on odd dots and acts on it on even
+// dots. The OAM is addressed by OAMADDR, so evaluation starts at a misaligned sprite if it isn't 0.
+#[derive(Clone, Copy, PartialEq)]
+enum EvalState {
+	// the byte is the Y coordinate of a sprite
+	Scan,
+	// bytes of the sprite in range which are left to copy
+	Copy(u8),
+	// bytes of the sprite which set the overflow flag which are left to read
+	Overflow(u8),
+	// all sprites are checked, OAMADDR keeps being incremented
+	Done,
+}
+
 pub(crate) struct OamBuffer {
+	// sprites of the current line, loaded during the fetches of the previous line
 	pub(crate) sprites_found: usize,
 
 	pub(crate) spr: [Sprite; 8],
+
+	// sprites of the next line
+	sec_oam: [u8; SEC_OAM_SIZE],
+	sec_idx: usize,
+	sec_cnt: usize,
+	sec_sprite0: bool,
+
+	state: EvalState,
+	// the byte read by the last odd dot, it's also the value of OAMDATA
+	latch: u8,
+	first_check: bool,
 }
 
 impl Sprite {
@@ -71,12 +112,8 @@ impl Sprite {
 		self.idx = val;
 	}
 
-	pub(crate) fn set_sprite0(&mut self) {
-		self.sprite0 = true;
-	}
-
-	pub(crate) fn flip_verticaly(&self) -> bool {
-		test_bit::<u8>(&self.attr, 7)
+	pub(crate) fn set_sprite0(&mut self, val: bool) {
+		self.sprite0 = val;
 	}
 
 	pub(crate) fn flip_horizontally(&self) -> bool {
@@ -93,6 +130,18 @@ impl Sprite {
 	}
 }
 
+// the bits 2-4 of the attributes don't exist and are read back as 0
+pub(crate) fn read_oam<B: PpuBus>(mem: &mut B, addr: u8) -> u8 {
+	const UNIMPLEMENTED_MASK: u8 = mask!(u8, 3, 2, true);
+
+	let val = mem.oam().read(addr as usize);
+	if (addr & 0x03) == 0x02 {
+		val & UNIMPLEMENTED_MASK
+	} else {
+		val
+	}
+}
+
 impl OamBuffer {
 	pub(crate) const fn new() -> Self {
 		Self {
@@ -108,69 +157,206 @@ impl OamBuffer {
 				Sprite::new(),
 				Sprite::new(),
 			],
+
+			sec_oam: [0xFF; SEC_OAM_SIZE],
+			sec_idx: 0,
+			sec_cnt: 0,
+			sec_sprite0: false,
+
+			state: EvalState::Done,
+			latch: 0xFF,
+			first_check: false,
 		}
 	}
 
-	pub(crate) fn reset(&mut self) {
-		self.sprites_found = 0;
+	// value of OAMDATA while rendering, the PPU shows the bytes it's working with
+	pub(crate) fn oam_data(&self, dot: usize) -> u8 {
+		match dot {
+			1..=CLEAR_END => 0xFF,
+			EVAL_START..=EVAL_END => self.latch,
+			FETCH_START..=FETCH_END => {
+				let slot = (dot - FETCH_START) / FETCH_DOTS;
+				let byte = ((dot - FETCH_START) % FETCH_DOTS).min(SPRITE_SIZE - 1);
+				self.sec_oam[slot * SPRITE_SIZE + byte]
+			}
+			_ => self.sec_oam[0],
+		}
+	}
 
-		for s in self.spr.iter_mut() {
-			s.reset();
+	// dots 1 - 64: the secondary OAM is filled with $FF, one byte every 2 dots
+	pub(crate) fn clear(&mut self, dot: usize) {
+		if dot.is_multiple_of(2) {
+			self.sec_oam[dot / 2 - 1] = 0xFF;
+		}
+
+		if dot == CLEAR_END {
+			self.sec_idx = 0;
+			self.sec_cnt = 0;
+			self.sec_sprite0 = false;
+			self.state = EvalState::Scan;
+			self.first_check = true;
 		}
 	}
 
-	// NOTE: has to be called before shift() was called
-	pub(crate) fn load_sprite_shifter<B: PpuBus>(
+	// Dots 65 - 256: the sprites in range of the next line are copied to the secondary OAM. Once 8
+	// sprites are found, the PPU keeps looking for a 9th one to set the overflow flag, but it
+	// increments the byte with the sprite, so it checks the wrong bytes. Returns if the overflow
+	// flag has to be set.
+	pub(crate) fn evaluate<B: PpuBus>(
 		&mut self,
-		bus: &mut B,
+		mem: &mut B,
+		dot: usize,
 		scanline: usize,
+		height: usize,
+		oam_addr: &mut u8,
+	) -> bool {
+		if dot % 2 == 1 {
+			self.latch = read_oam(mem, *oam_addr);
+			return false;
+		}
+
+		let in_range = scanline.wrapping_sub(self.latch as usize) < height;
+		let (addr, overflow) = match self.state {
+			EvalState::Scan if self.sec_cnt < MAX_LINE_SPRITES => {
+				// the Y coordinate is copied, but only kept if the sprite is in range
+				self.sec_oam[self.sec_idx] = self.latch;
+				if in_range {
+					self.sec_sprite0 |= self.first_check;
+					self.sec_idx += 1;
+					self.state = EvalState::Copy(SPRITE_SIZE as u8 - 1);
+					(oam_addr.overflowing_add(1), false)
+				} else {
+					(oam_addr.overflowing_add(SPRITE_SIZE as u8), false)
+				}
+			}
+			EvalState::Scan => {
+				if in_range {
+					self.state = EvalState::Overflow(SPRITE_SIZE as u8 - 1);
+					(oam_addr.overflowing_add(1), true)
+				} else {
+					// the hardware bug: the byte is incremented without a carry to the sprite
+					let (n, wrapped) = (*oam_addr & 0xFC).overflowing_add(SPRITE_SIZE as u8);
+					((n | (oam_addr.wrapping_add(1) & 0x03), wrapped), false)
+				}
+			}
+			EvalState::Copy(left) => {
+				self.sec_oam[self.sec_idx] = self.latch;
+				self.sec_idx += 1;
+				if left == 1 {
+					self.sec_cnt += 1;
+					self.state = EvalState::Scan;
+				} else {
+					self.state = EvalState::Copy(left - 1);
+				}
+				(oam_addr.overflowing_add(1), false)
+			}
+			EvalState::Overflow(left) => {
+				if left == 1 {
+					self.state = EvalState::Done;
+				} else {
+					self.state = EvalState::Overflow(left - 1);
+				}
+				(oam_addr.overflowing_add(1), false)
+			}
+			EvalState::Done => (oam_addr.overflowing_add(SPRITE_SIZE as u8), false),
+		};
+
+		self.first_check = false;
+		*oam_addr = addr.0;
+		// all 64 sprites are checked once OAMADDR wraps around
+		if addr.1 {
+			self.state = EvalState::Done;
+		}
+
+		overflow
+	}
+
+	// Dots 257 - 320: 8 dots per sprite of the secondary OAM, 2 garbage nametable fetches and the
+	// 2 bytes of the pattern. Empty slots fetch the tile $FF, so mappers which count the rises of
+	// A12 (MMC3) see the same fetches for every line.
+	pub(crate) fn fetch<B: PpuBus>(
+		&mut self,
+		mem: &mut B,
+		dot: usize,
+		scanline: usize,
+		nt_addr: u16,
 		pt_tbl: u16,
 		spr_8x16: bool,
 	) {
-		for s in self.spr[..self.sprites_found].iter_mut() {
-			let mut y_idx = (scanline as u16) - (s.y as u16);
+		let slot = (dot - FETCH_START) / FETCH_DOTS;
+		let sec = &self.sec_oam[slot * SPRITE_SIZE..(slot + 1) * SPRITE_SIZE];
+		let (y, idx, attr, x) = (sec[0], sec[1], sec[2], sec[3]);
+		let tile_addr = Self::tile_addr(scanline, y, idx, attr, pt_tbl, spr_8x16);
+
+		match (dot - FETCH_START) % FETCH_DOTS {
+			0 | 2 => {
+				let _ = mem.read(nt_addr as usize);
+			}
+			4 => {
+				self.latch = mem.read(tile_addr as usize);
+			}
+			6 => {
+				let mut spr_lsb = self.latch;
+				let mut spr_msb = mem.read(tile_addr.wrapping_add(8) as usize);
+
+				// the pre-render line doesn't evaluate, so its sprites aren't rendered on line 0
+				let s = &mut self.spr[slot];
+				if slot >= self.sec_cnt || scanline > EVAL_LINE_MAX {
+					s.reset();
+					return;
+				}
 
-			let pt = if spr_8x16 {
-				((s.idx & 0x01) as u16) * 0x1000
-			} else {
-				pt_tbl
-			};
+				mem.log_chr_fetch(tile_addr as usize);
+				mem.log_chr_fetch(tile_addr.wrapping_add(8) as usize);
 
-			let tile = if spr_8x16 {
-				if s.flip_verticaly() {
-					y_idx = 15 - y_idx;
+				s.set_y(y);
+				s.set_idx(idx);
+				s.set_attr(attr);
+				s.set_x(x);
+				s.set_sprite0(slot == 0 && self.sec_sprite0);
+
+				if s.flip_horizontally() {
+					spr_lsb = reverse_u8(spr_lsb);
+					spr_msb = reverse_u8(spr_msb);
 				}
 
-				// if y_idx is greater than 8, we have to add 1 to the tile and subtract 8 from the
-				// y-index
-				let offset = if y_idx >= 8 {
-					y_idx -= 8;
-					1
-				} else {
-					0
-				};
+				s.tile_lsb.reload(spr_lsb);
+				s.tile_msb.reload(spr_msb);
+			}
+			_ => (),
+		}
 
-				(((s.idx & 0xFE) as u16) + offset) << 4
+		if dot == FETCH_END {
+			self.sprites_found = if scanline > EVAL_LINE_MAX {
+				0
 			} else {
-				if s.flip_verticaly() {
-					y_idx = 7 - y_idx;
-				}
-
-				(s.idx as u16) << 4
+				self.sec_cnt
 			};
+		}
+	}
 
-			let tile_addr = pt | tile | y_idx;
+	fn tile_addr(scanline: usize, y: u8, idx: u8, attr: u8, pt_tbl: u16, spr_8x16: bool) -> u16 {
+		let flip_v = test_bit::<u8>(&attr, 7);
+		// empty slots and the pre-render line have garbage in the secondary OAM
+		let mut y_idx = (scanline as u16).wrapping_sub(y as u16);
 
-			let mut spr_lsb = bus.read(tile_addr as usize);
-			let mut spr_msb = bus.read(tile_addr.wrapping_add(8) as usize);
+		if spr_8x16 {
+			y_idx &= 0x0F;
+			if flip_v {
+				y_idx = 15 - y_idx;
+			}
 
-			if s.flip_horizontally() {
-				spr_lsb = reverse_u8(spr_lsb);
-				spr_msb = reverse_u8(spr_msb);
+			// the bottom half is the next tile
+			let pt = ((idx & 0x01) as u16) * 0x1000;
+			let tile = ((idx & 0xFE) as u16) + (y_idx >> 3);
+			pt | (tile << 4) | (y_idx & 0x07)
+		} else {
+			y_idx &= 0x07;
+			if flip_v {
+				y_idx = 7 - y_idx;
 			}
 
-			s.tile_lsb.reload(spr_lsb);
-			s.tile_msb.reload(spr_msb);
+			pt_tbl | ((idx as u16) << 4) | y_idx
 		}
 	}
 
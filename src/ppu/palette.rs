@@ -0,0 +1,272 @@
+// The 512 colors of the PPU: the 64 colors of the palette RAM times the 8 combinations of the
+// emphasis bits of PPUMASK (bit 5 red, bit 6 green, bit 7 blue), index = emphasis << 6 | color.
+// The composite PPU (2C02) emphasizes a color by attenuating the other parts of the signal, the
+// RGB PPUs of the Vs. System instead turn the channel fully on. A palette can be
+// - expanded from 64 colors, e.g. the built-in ones, by approximating the emphasis
+// - loaded from a .pal file with 64 or 512 colors (3 bytes RGB each)
+// - generated by decoding the composite signal of the 2C02 like a TV, which can be adjusted
+
+use super::color::{Color, COLOR_PALETTE_SIZE};
+use std::convert::TryInto;
+use std::f64::consts::PI;
+use std::fmt;
+use std::fs;
+
+pub const PALETTE_SIZE: usize = COLOR_PALETTE_SIZE * EMPHASIS_CNT;
+const EMPHASIS_CNT: usize = 8;
+const RGB_SIZE: usize = 3;
+
+// the signal of the other channels is reduced to about 75% by an emphasis bit
+const ATTENUATION: f64 = 0.746;
+
+// voltages of the composite signal of the 2C02 for the 4 levels, low and high part of the wave
+const SIGNAL_LOW: [f64; 4] = [0.350, 0.518, 0.962, 1.550];
+const SIGNAL_HIGH: [f64; 4] = [1.094, 1.506, 1.962, 1.962];
+const BLACK: f64 = 0.518;
+const WHITE: f64 = 1.962;
+// the color carrier has 12 phases per period, a pixel lasts 8 of them
+pub(super) const PHASES: usize = 12;
+const GAMMA: f64 = 2.2 / 1.8;
+
+pub enum PaletteErr {
+	FileNotFound,
+	InvalidSize(usize),
+}
+
+// adjustments of the TV which decodes the composite signal, the defaults are neutral
+#[derive(Clone, Copy)]
+pub struct NtscSettings {
+	// in degrees
+	pub hue: f64,
+	pub saturation: f64,
+	pub contrast: f64,
+	pub brightness: f64,
+}
+
+pub struct Palette {
+	colors: Vec<Color>,
+}
+
+impl fmt::Debug for PaletteErr {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match &self {
+			Self::FileNotFound => write!(f, "FileNotFound"),
+			Self::InvalidSize(s) => write!(f, "InvalidSize({})", s),
+		}
+	}
+}
+
+impl Default for NtscSettings {
+	fn default() -> Self {
+		Self {
+			hue: 0.0,
+			saturation: 1.0,
+			contrast: 1.0,
+			brightness: 1.0,
+		}
+	}
+}
+
+impl NtscSettings {
+	// "<hue>,<saturation>,<contrast>,<brightness>"
+	pub fn parse(s: &str) -> Option<Self> {
+		let vals: Vec<f64> = s.split(',').map(|v| v.trim().parse().ok()).collect::<Option<_>>()?;
+		match vals[..] {
+			[hue, saturation, contrast, brightness] => Some(Self {
+				hue,
+				saturation,
+				contrast,
+				brightness,
+			}),
+			_ => None,
+		}
+	}
+
+	pub(super) fn adjust(&self, level: f64) -> f64 {
+		((level - 0.5) * self.contrast + 0.5) * self.brightness
+	}
+
+	// the angle of the color carrier at the phase, rotated by the hue
+	pub(super) fn angle(&self, phase: usize) -> f64 {
+		PI / 6.0 * (phase as f64 + self.hue / 30.0)
+	}
+}
+
+fn emphasize(color: &Color, emphasis: usize, rgb: bool) -> Color {
+	let channel = |val: u8, bit: usize| {
+		let others = emphasis & !(1 << bit);
+		match (rgb, emphasis & (1 << bit) != 0) {
+			(true, true) => 0xFF,
+			(true, false) => val,
+			// every emphasis bit of another channel attenuates this one
+			(false, _) => (val as f64 * ATTENUATION.powi(others.count_ones() as i32)) as u8,
+		}
+	};
+
+	Color::from_rgb(channel(color.r(), 0), channel(color.g(), 1), channel(color.b(), 2))
+}
+
+// the voltage of the composite signal for the color (index = emphasis << 6 | color) at a phase of
+// the color carrier, 0.0 is black and 1.0 white
+pub(super) fn composite_level(idx: usize, phase: usize) -> f64 {
+	let (color, emphasis) = (idx % COLOR_PALETTE_SIZE, idx / COLOR_PALETTE_SIZE);
+	let hue = color & 0x0F;
+	// the colors $xE and $xF are black
+	let level = if hue < 0x0E {
+		(color >> 4) & 0x03
+	} else {
+		1
+	};
+
+	// hue 0 is a grey above the wave, hue $D a grey below it
+	let low = if hue == 0x00 {
+		SIGNAL_HIGH[level]
+	} else {
+		SIGNAL_LOW[level]
+	};
+	let high = if hue < 0x0D {
+		SIGNAL_HIGH[level]
+	} else {
+		SIGNAL_LOW[level]
+	};
+	let in_phase = |hue: usize| (hue + phase + 8) % PHASES < PHASES / 2;
+
+	let mut signal = if in_phase(hue) {
+		high
+	} else {
+		low
+	};
+	// an emphasis bit attenuates the half of the wave opposite to its color, i.e. the half of
+	// hue 0 for red, of hue 4 for green and of hue 8 for blue
+	if (0..3).any(|bit| emphasis & (1 << bit) != 0 && in_phase(bit * 4)) {
+		signal *= ATTENUATION;
+	}
+
+	(signal - BLACK) / (WHITE - BLACK)
+}
+
+// the conversion of the FCC
+pub(super) fn yiq_to_rgb(y: f64, i: f64, q: f64) -> Color {
+	let channel = |v: f64| {
+		let v = if v <= 0.0 {
+			0.0
+		} else {
+			v.powf(GAMMA)
+		};
+		(v * 255.95).clamp(0.0, 255.0) as u8
+	};
+	Color::from_rgb(
+		channel(y + 0.946882 * i + 0.623557 * q),
+		channel(y - 0.274788 * i - 0.635691 * q),
+		channel(y - 1.108545 * i + 1.709007 * q),
+	)
+}
+
+impl Palette {
+	// the emphasis of the 2C02 is approximated, the RGB PPUs turn the channels on
+	pub fn from_colors(colors: &[Color; COLOR_PALETTE_SIZE], rgb: bool) -> Self {
+		let colors = (0..PALETTE_SIZE)
+			.map(|i| emphasize(&colors[i % COLOR_PALETTE_SIZE], i / COLOR_PALETTE_SIZE, rgb))
+			.collect();
+		Self {
+			colors,
+		}
+	}
+
+	pub fn load(file: &str) -> Result<Self, PaletteErr> {
+		let data = fs::read(file).or(Err(PaletteErr::FileNotFound))?;
+		let colors: Vec<Color> =
+			data.chunks_exact(RGB_SIZE).map(|c| Color::from_rgb(c[0], c[1], c[2])).collect();
+
+		match (data.len() % RGB_SIZE, colors.len()) {
+			(0, PALETTE_SIZE) => Ok(Self {
+				colors,
+			}),
+			(0, COLOR_PALETTE_SIZE) => {
+				let colors: &[Color; COLOR_PALETTE_SIZE] = colors.as_slice().try_into().unwrap();
+				Ok(Self::from_colors(colors, false))
+			}
+			_ => Err(PaletteErr::InvalidSize(data.len())),
+		}
+	}
+
+	// Decodes the composite signal of every color like an ideal TV. The PPU outputs a square wave
+	// between two voltages, its phase is the hue. The wave is sampled at the 12 phases and
+	// demodulated into YIQ, which is converted to RGB.
+	pub fn ntsc(settings: &NtscSettings) -> Self {
+		let colors = (0..PALETTE_SIZE).map(|i| Self::ntsc_color(i, settings)).collect();
+		Self {
+			colors,
+		}
+	}
+
+	fn ntsc_color(idx: usize, settings: &NtscSettings) -> Color {
+		let (mut y, mut i, mut q) = (0.0, 0.0, 0.0);
+		for p in 0..PHASES {
+			let v = settings.adjust(composite_level(idx, p)) / PHASES as f64;
+			let angle = settings.angle(p);
+			y += v;
+			i += v * angle.cos();
+			q += v * angle.sin();
+		}
+
+		yiq_to_rgb(y, i * settings.saturation, q * settings.saturation)
+	}
+
+	// for the PPUs which scramble the colors, e.g. the RP2C04
+	pub(crate) fn remap(self, lut: &[u8; COLOR_PALETTE_SIZE]) -> Self {
+		let colors = (0..PALETTE_SIZE)
+			.map(|i| {
+				let base = i - i % COLOR_PALETTE_SIZE;
+				self.colors[base + lut[i % COLOR_PALETTE_SIZE] as usize]
+			})
+			.collect();
+		Self {
+			colors,
+		}
+	}
+
+	// index = emphasis << 6 | color
+	pub fn color(&self, idx: usize) -> &Color {
+		&self.colors[idx % PALETTE_SIZE]
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::ppu::color::COLOR_PALETTE;
+
+	#[test]
+	fn emphasis() {
+		let composite = Palette::from_colors(COLOR_PALETTE, false);
+		let c = composite.color(0x30);
+		assert_eq!((c.r(), c.g(), c.b()), (0xFF, 0xFE, 0xFF));
+		// red emphasis darkens green and blue
+		let c = composite.color((1 << 6) | 0x30);
+		assert_eq!((c.r(), c.g(), c.b()), (0xFF, 0xBD, 0xBE));
+
+		let rgb = Palette::from_colors(COLOR_PALETTE, true);
+		let c = rgb.color((4 << 6) | 0x0F);
+		assert_eq!((c.r(), c.g(), c.b()), (0x00, 0x00, 0xFF));
+	}
+
+	#[test]
+	fn ntsc() {
+		let palette = Palette::ntsc(&NtscSettings::default());
+		let grey = |idx: usize| {
+			let c = palette.color(idx);
+			assert!(c.r() == c.g() && c.g() == c.b(), "${:02X} isn't grey", idx);
+			c.r()
+		};
+
+		assert_eq!(grey(0x0F), 0x00);
+		assert_eq!(grey(0x20), 0xFF);
+		assert!(grey(0x00) < grey(0x10) && grey(0x10) < grey(0x20));
+
+		// red emphasis tints white and attenuates blue
+		let white = palette.color((1 << 6) | 0x20);
+		assert!(white.r() > white.g() && white.r() > white.b());
+		assert!(palette.color((1 << 6) | 0x11).b() < palette.color(0x11).b());
+	}
+}
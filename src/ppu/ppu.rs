@@ -1,17 +1,24 @@
 use std::marker::PhantomData;
 use std::sync::{Arc, RwLock};
 
-use super::color::{Color, COLOR_PALETTE};
+use super::color::{COLOR_PALETTE, COLOR_PALETTE_SIZE};
 use super::framebuffer::FrameBuffer;
-use super::oam_buffer::OamBuffer;
-use super::ppu_regs::{LoopyRegister, PpuCtrl, PpuMask};
+use super::model::PpuModel;
+use super::oam_buffer::{
+	read_oam, OamBuffer, CLEAR_END, EVAL_END, EVAL_START, FETCH_END, FETCH_START,
+};
+use super::palette::{Palette, PHASES};
+use super::ppu_regs::{DataAccess, LoopyRegister, PpuCtrl, PpuMask};
 use super::shiftreg::ShiftReg16;
-use crate::mask;
-use crate::mem::{PpuBus, Segment};
+use crate::mem::{PpuBus, Segment, PPU_ADDR_MASK};
+use crate::region::Region;
 
-const MAX_SPRITE_CNT: usize = 64;
+// size of the framebuffer in pixels
+pub const SCREEN_WIDTH: usize = 256;
+pub const SCREEN_HEIGHT: usize = 240;
 
 pub struct Ppu<B: PpuBus> {
+	region: Region,
 	cycle: usize,
 	scanline: usize,
 
@@ -26,18 +33,28 @@ pub struct Ppu<B: PpuBus> {
 
 	oam_buf: OamBuffer,
 
+	palette: Palette,
+	palette_lut: &'static [u8; COLOR_PALETTE_SIZE],
+
 	fb: FrameBuffer,
 	tile_fb: FrameBuffer,
 	frame_finished: bool,
+	odd_frame: bool,
+	// frames since the power-up
+	frame_cnt: u64,
+	// phase of the color carrier (in master clocks mod 12) at dot 0 of the scanline and of the
+	// first scanline of the frame
+	line_phase: usize,
+	frame_phase: usize,
 	fb_ready: bool,
 	cycle_cnt: usize, // for statistics
 	_phantom: PhantomData<B>,
 }
 
 impl<B: PpuBus> Ppu<B> {
-	pub fn new() -> Self {
-		// TODO: handle NTSC and PAL differently, for now we default to NTSC
+	pub fn new(model: PpuModel, region: Region) -> Self {
 		Self {
+			region,
 			cycle: 0,
 			scanline: 0,
 
@@ -52,10 +69,17 @@ impl<B: PpuBus> Ppu<B> {
 
 			oam_buf: OamBuffer::new(),
 
-			fb: FrameBuffer::new(256, 240),
+			palette: Palette::from_colors(model.palette(), model.rgb()).remap(model.palette_lut()),
+			palette_lut: model.palette_lut(),
+
+			fb: FrameBuffer::new(SCREEN_WIDTH, SCREEN_HEIGHT),
 			tile_fb: FrameBuffer::new(256, 128),
 			fb_ready: true,
 			frame_finished: false,
+			odd_frame: false,
+			frame_cnt: 0,
+			line_phase: 0,
+			frame_phase: 0,
 			cycle_cnt: 0,
 
 			_phantom: PhantomData,
@@ -92,12 +116,8 @@ impl<B: PpuBus> Ppu<B> {
 		pt_addr
 	}
 
-	fn get_color_from_palette(
-		palette: u8,
-		pixel: u8,
-		mask: &PpuMask,
-		mem: &mut B,
-	) -> &'static Color {
+	// the 9 bit value of the pixel, emphasis << 6 | color
+	fn get_pixel_from_palette(&self, palette: u8, pixel: u8, mask: &PpuMask, mem: &mut B) -> u16 {
 		const PALETTE_BASE: usize = 0x3F00;
 		const GREYSCALE_MASK: u8 = 0x30;
 
@@ -109,7 +129,18 @@ impl<B: PpuBus> Ppu<B> {
 			color &= GREYSCALE_MASK;
 		}
 
-		&COLOR_PALETTE[color as usize]
+		let mut emphasis = mask.emphasis() as u16;
+		if self.region.swaps_emphasis() {
+			// bit 0 green, bit 1 red
+			emphasis = (emphasis & 0x04) | ((emphasis & 0x01) << 1) | ((emphasis & 0x02) >> 1);
+		}
+
+		(emphasis << 6) | color as u16
+	}
+
+	// a palette for the colors of the 2C02, the PPUs which scramble the colors still do so
+	pub fn set_palette(&mut self, palette: Palette) {
+		self.palette = palette.remap(self.palette_lut);
 	}
 
 	pub fn step(&mut self, mem: &mut B) {
@@ -121,29 +152,50 @@ impl<B: PpuBus> Ppu<B> {
 		let mut status = mem.ppu_reg().ppu_status;
 		let mut oam_reg = mem.ppu_reg().oam;
 
+		let pre_render_line = self.region.scanlines() - 1;
+		let rendering = mask.render_background() || mask.render_sprites();
+		let render_line = self.scanline == pre_render_line || self.scanline <= 239;
+
 		// first, handle the OAM accesses
 		if oam_reg.write_stb {
-			// data is transferred to the OAM memory
-			mem.oam().write(oam_reg.addr as usize, oam_reg.data);
-			oam_reg.write_stb = false;
-		} else {
-			// data is read from the OAM memory
-			if self.cycle >= 1 && self.cycle <= 64 {
-				// during this time reading from 0x2004 (OAMDATA) returns always 0xFF (hardware)
-				oam_reg.data = 0xFF;
+			if rendering && render_line {
+				// while rendering the write is ignored, but OAMADDR moves to the next sprite
+				oam_reg.addr = oam_reg.addr.wrapping_add(4);
 			} else {
-				oam_reg.data = mem.oam().read(oam_reg.addr as usize);
+				// data is transferred to the OAM memory
+				mem.oam().write(oam_reg.addr as usize, oam_reg.data);
+				oam_reg.addr = oam_reg.addr.wrapping_add(1);
+			}
+			oam_reg.write_stb = false;
+		}
 
-				if (oam_reg.addr & 0x03) == 0x02 {
-					// attribute register
-					const UNIMPLEMENTED_MASK: u8 = mask!(u8, 3, 2, true);
-					oam_reg.data &= UNIMPLEMENTED_MASK;
+		// the $2007 access of the CPU
+		if let Some(access) = mem.ppu_reg().data_access.take() {
+			let addr = v.get() as usize & PPU_ADDR_MASK;
+			match access {
+				DataAccess::Write(val) => mem.write(addr, val),
+				// the palette is returned right away, the buffer gets the nametable underneath
+				DataAccess::Read => {
+					let addr = if addr >= 0x3F00 {
+						addr & 0x2FFF
+					} else {
+						addr
+					};
+					mem.ppu_reg().ppudata_buf = mem.read(addr);
 				}
 			}
+
+			if rendering && render_line {
+				// the increments of the rendering are triggered at the same time
+				v.inc_x();
+				v.inc_y();
+			} else {
+				v.inc(ctrl.get_inc());
+			}
 		}
 
 		// visible scan-lines -> actual rendering happens here
-		if self.scanline == 261 || self.scanline <= 239 {
+		if render_line {
 			if self.cycle == 0 {
 				// dummy read of BG LSB always at cycle = 0
 				let _ = mem.read(self.get_background_address(&ctrl, &v) as usize);
@@ -173,11 +225,17 @@ impl<B: PpuBus> Ppu<B> {
 					4 => {
 						let pt_addr = self.get_background_address(&ctrl, &v);
 						self.bg_next_tile_lsb = mem.read(pt_addr as usize);
+						if mask.render_background() {
+							mem.log_chr_fetch(pt_addr as usize);
+						}
 					}
 					6 => {
 						// 8 byte offset to the lowbyte
 						let pt_addr = self.get_background_address(&ctrl, &v).wrapping_add(8);
 						self.bg_next_tile_msb = mem.read(pt_addr as usize);
+						if mask.render_background() {
+							mem.log_chr_fetch(pt_addr as usize);
+						}
 					}
 					7 => {
 						if mask.render_background() || mask.render_sprites() {
@@ -198,51 +256,42 @@ impl<B: PpuBus> Ppu<B> {
 				self.oam_buf.shift();
 			}
 
-			if self.cycle == 257 && self.scanline != 261 {
+			if self.cycle == 257 && self.scanline != pre_render_line {
 				self.bg_shifter_reload();
 
 				if mask.render_background() || mask.render_sprites() {
 					v.transfer_x(&t);
 				}
+			}
 
-				// evaluate sprites, TODO: do this correctly later
-				self.oam_buf.reset();
-
-				for s in 0..MAX_SPRITE_CNT {
-					let sprite_height = ctrl.sprite_size() as usize;
-					let y = mem.oam().read(s * 4);
-
-					if self.scanline.wrapping_sub(y as usize) < sprite_height {
-						let sf = self.oam_buf.sprites_found;
-						if sf < 8 {
-							self.oam_buf.spr[sf].set_y(y);
-							self.oam_buf.spr[sf].set_idx(mem.oam().read(s * 4 + 1));
-							self.oam_buf.spr[sf].set_attr(mem.oam().read(s * 4 + 2));
-							self.oam_buf.spr[sf].set_x(mem.oam().read(s * 4 + 3));
-							if s == 0 {
-								self.oam_buf.spr[sf].set_sprite0();
-							}
-
-							self.oam_buf.sprites_found += 1;
-						} else {
+			// the sprites of the next line, the pre-render line only fetches
+			if rendering {
+				let (dot, scanline) = (self.cycle, self.scanline);
+				match dot {
+					1..=CLEAR_END if scanline != pre_render_line => self.oam_buf.clear(dot),
+					EVAL_START..=EVAL_END if scanline != pre_render_line => {
+						let height = ctrl.sprite_size() as usize;
+						let addr = &mut oam_reg.addr;
+						if self.oam_buf.evaluate(mem, dot, scanline, height, addr) {
 							status.set_sprite_overflow();
-							break;
 						}
 					}
+					FETCH_START..=FETCH_END => {
+						oam_reg.addr = 0;
+						self.oam_buf.fetch(
+							mem,
+							dot,
+							scanline,
+							v.get_nametable_addr(),
+							ctrl.sprites_tile_base(),
+							ctrl.sprite_size() > 8,
+						);
+					}
+					_ => (),
 				}
 			}
 
-			if self.cycle == 340 {
-				// TODO: this is probably the wrong place to do this, but for simplicity do it here
-				self.oam_buf.load_sprite_shifter::<B>(
-					mem,
-					self.scanline,
-					ctrl.sprites_tile_base(),
-					ctrl.sprite_size() > 8,
-				);
-			}
-
-			if self.scanline == 261 && (self.cycle >= 280 && self.cycle <= 304) {
+			if self.scanline == pre_render_line && (self.cycle >= 280 && self.cycle <= 304) {
 				if mask.render_background() || mask.render_sprites() {
 					v.transfer_y(&t);
 				}
@@ -254,17 +303,16 @@ impl<B: PpuBus> Ppu<B> {
 			}
 		}
 
-		if self.scanline == 241 && self.cycle == 1 {
-			status.set_vblank();
-
-			if ctrl.nmi_enabled() {
-				mem.assert_nmi();
+		// reading $2002 right before the flag gets set suppresses it and the NMI for this frame
+		if self.scanline == self.region.vblank_line() && self.cycle == 1 {
+			if !mem.ppu_reg().status_read {
+				status.set_vblank();
 			}
 
 			self.fb_ready = true;
 		}
 
-		if self.scanline == 261 && self.cycle == 1 {
+		if self.scanline == pre_render_line && self.cycle == 1 {
 			status.clear_vblank();
 			status.clear_sprite_overflow();
 			status.clear_sprite0_hit();
@@ -322,34 +370,44 @@ impl<B: PpuBus> Ppu<B> {
 
 			// if any of these bits is activated, we have to render the pixel
 			if mask.render_background() || mask.render_sprites() {
-				self.fb.render_pixel(
-					self.cycle - 1,
-					self.scanline,
-					Self::get_color_from_palette(pal, pix, &mask, mem),
-				);
+				let pixel = self.get_pixel_from_palette(pal, pix, &mask, mem);
+				let color = *self.palette.color(pixel as usize);
+				self.fb.render_pixel(self.cycle - 1, self.scanline, &color, pixel);
 			}
 		}
 
 		self.cycle += 1;
 		self.cycle_cnt += 1;
 
-		if self.cycle == 340 && self.scanline == 261 {
-			self.cycle = 1; // skip "odd" cycle
-			self.scanline = 0;
-
-			self.frame_finished = true;
-		} else if self.cycle > 340 {
+		// the last dot of the pre-render line is skipped on odd frames if rendering is enabled
+		let skip_dot = self.odd_frame && rendering && self.region.skips_dot();
+		if self.cycle > 340 || (self.cycle == 340 && self.scanline == pre_render_line && skip_dot) {
+			// a dot lasts 8 master clocks
+			self.line_phase = (self.line_phase + self.cycle * 8) % PHASES;
 			self.cycle = 0;
 			self.scanline += 1;
 		}
 
-		if self.scanline > 261 {
+		if self.scanline > pre_render_line {
 			self.scanline = 0;
+			self.odd_frame = !self.odd_frame;
+			self.frame_cnt += 1;
+			self.frame_phase = self.line_phase;
+
+			self.frame_finished = true;
 		}
 
+		// reading OAMDATA while rendering returns the byte the sprite evaluation works with
+		oam_reg.data = if rendering && render_line {
+			self.oam_buf.oam_data(self.cycle)
+		} else {
+			read_oam(mem, oam_reg.addr)
+		};
+
 		mem.ppu_reg().v = v;
 		mem.ppu_reg().ppu_status = status;
 		mem.ppu_reg().oam = oam_reg;
+		mem.ppu_reg().status_read = false;
 	}
 
 	pub fn frame_finished(&mut self) -> bool {
@@ -370,10 +428,29 @@ impl<B: PpuBus> Ppu<B> {
 		ret
 	}
 
+	// (scanline, dot) of the next step
+	pub fn position(&self) -> (usize, usize) {
+		(self.scanline, self.cycle)
+	}
+
+	pub fn frame_cnt(&self) -> u64 {
+		self.frame_cnt
+	}
+
 	pub fn get_fb(&self) -> Arc<RwLock<Vec<u8>>> {
 		self.fb.fb()
 	}
 
+	// the 9 bit values of the frame for the NTSC filter
+	pub fn pixels(&self) -> &[u16] {
+		self.fb.pixels()
+	}
+
+	// phase of the color carrier at the first pixel (dot 1) of the frame
+	pub fn burst_phase(&self) -> usize {
+		(self.frame_phase + 8) % PHASES
+	}
+
 	pub fn tile_buf(&mut self, mem: &mut B) -> Arc<RwLock<Vec<u8>>> {
 		const COLOR_LOOKUP: [usize; 4] = [0x20, 0x16, 0x2A, 0x11];
 
@@ -398,6 +475,7 @@ impl<B: PpuBus> Ppu<B> {
 							idx_x,
 							idx_y,
 							&COLOR_PALETTE[COLOR_LOOKUP[col_idx]],
+							COLOR_LOOKUP[col_idx] as u16,
 						);
 					}
 				}
@@ -1,3 +1,5 @@
+use super::io_latch::IoLatch;
+use super::model::PpuModel;
 use crate::mask;
 use crate::mem::PpuRegisterAccess;
 
@@ -28,6 +30,13 @@ pub(crate) struct Oam {
 	pub(crate) write_stb: bool,
 }
 
+// a $2007 access of the CPU, the PPU does it on its next dot
+#[derive(Copy, Clone, PartialEq)]
+pub(crate) enum DataAccess {
+	Read,
+	Write(u8),
+}
+
 pub struct PpuRegisters {
 	pub(crate) ppu_ctrl: PpuCtrl,
 	pub(crate) ppu_mask: PpuMask,
@@ -39,7 +48,12 @@ pub struct PpuRegisters {
 	pub(crate) v: LoopyRegister,
 	pub(crate) x: u8,
 
-	ppudata_buf: u8,
+	pub(crate) ppudata_buf: u8,
+	pub(crate) data_access: Option<DataAccess>,
+	io_latch: IoLatch,
+	status_id: Option<u8>,
+	ctrl_mask_swapped: bool,
+	pub(crate) status_read: bool, // $2002 was read since the last PPU dot
 }
 
 macro_rules! ppu_basic_regops {
@@ -259,7 +273,7 @@ impl LoopyRegister {
 		self.0 |= addr as u16;
 	}
 
-	fn inc(&mut self, inc: u16) {
+	pub(crate) fn inc(&mut self, inc: u16) {
 		// stupid incrementing, used when CPU accesses the PPUPDATA register
 		self.0 = self.0.wrapping_add(inc);
 	}
@@ -274,7 +288,7 @@ impl PpuCtrl {
 
 	const PATTERN_TABLE_SIZE: u16 = 0x1000;
 
-	fn get_inc(&self) -> u16 {
+	pub(crate) fn get_inc(&self) -> u16 {
 		if self.test_bit(Self::INC_IDX) {
 			32
 		} else {
@@ -309,6 +323,12 @@ impl PpuMask {
 	const RENDER_LEFT_SPRITES_IDX: u8 = 2;
 	const RENDER_BACKGROUND_IDX: u8 = 3;
 	const RENDER_SPRITES_IDX: u8 = 4;
+	const EMPHASIS_SHIFT: u8 = 5;
+
+	// bit 0 red, bit 1 green, bit 2 blue
+	pub(crate) fn emphasis(&self) -> u8 {
+		self.get() >> Self::EMPHASIS_SHIFT
+	}
 
 	pub(crate) fn greyscale(&self) -> bool {
 		self.test_bit(Self::GREY_SCALE_IDX)
@@ -356,6 +376,10 @@ impl PpuStatus {
 		self.test_bit(Self::SPRITE0_HIT)
 	}
 
+	pub(crate) fn get_vblank(&self) -> bool {
+		self.test_bit(Self::VBLANK_IDX)
+	}
+
 	pub(crate) fn clear_vblank(&mut self) {
 		self.clear_bit(Self::VBLANK_IDX);
 	}
@@ -377,11 +401,41 @@ impl PpuRegisters {
 			x: 0,
 			w: false,
 			ppudata_buf: 0,
+			data_access: None,
+			io_latch: IoLatch::default(),
+			status_id: None,
+			ctrl_mask_swapped: false,
+			status_read: false,
 		}
 	}
 
-	fn inc_ppu_addr(&mut self) {
-		self.v.inc(self.ppu_ctrl.get_inc());
+	pub(crate) fn set_model(&mut self, model: PpuModel) {
+		self.status_id = model.status_id();
+		self.ctrl_mask_swapped = model.ctrl_mask_swapped();
+	}
+
+	// map the register index to the actual register, some PPUs swap PPUCTRL and PPUMASK
+	pub(crate) fn register_idx(&self, reg: usize) -> usize {
+		if self.ctrl_mask_swapped && reg < 2 {
+			reg ^ 0x01
+		} else {
+			reg
+		}
+	}
+
+	// called once every CPU cycle
+	pub(crate) fn clock(&mut self) {
+		self.io_latch.clock();
+	}
+
+	// every write to a register, including the read-only ones, drives the I/O latch
+	pub(crate) fn latch_write(&mut self, val: u8) {
+		self.io_latch.set(val, 0xFF);
+	}
+
+	// the PPU pulls the NMI line as long as the vblank flag is set and NMIs are enabled
+	pub(crate) fn nmi_line(&self) -> bool {
+		self.ppu_status.get_vblank() && self.ppu_ctrl.nmi_enabled()
 	}
 }
 
@@ -421,15 +475,26 @@ impl PpuRegisterAccess for PpuRegisters {
 		self.w = !self.w
 	}
 
-	fn ppu_data_write(&mut self) {
-		self.inc_ppu_addr();
+	fn ppu_data_write(&mut self, val: u8) {
+		self.data_access = Some(DataAccess::Write(val));
 	}
 
 	fn ppu_stat_read(&mut self) -> u8 {
-		let ret = self.ppu_status.get();
+		const LATCH_MASK: u8 = mask!(u8, 5, 0, false);
+		const ID_MASK: u8 = mask!(u8, 6, 0, false);
+
+		// the lower bits are the ones of the I/O latch, unless the PPU reports its ID there
+		let mut ret = (self.ppu_status.get() & !LATCH_MASK) | (self.io_latch.get() & LATCH_MASK);
+		let mut driven = !LATCH_MASK;
+		if let Some(id) = self.status_id {
+			ret = (ret & !ID_MASK) | (id & ID_MASK);
+			driven |= ID_MASK;
+		}
+		self.io_latch.set(ret, driven);
 
 		self.ppu_status.clear_vblank();
 		self.w = false;
+		self.status_read = true;
 
 		ret
 	}
@@ -438,25 +503,33 @@ impl PpuRegisterAccess for PpuRegisters {
 		self.v.get()
 	}
 
-	fn ppu_data_read(&mut self, new_val: u8) -> u8 {
-		let ret = if self.v.get() < 0x3F00 {
-			let r = self.ppudata_buf;
-			self.ppudata_buf = new_val;
-
-			r
-		} else {
-			new_val
-		};
-
-		self.inc_ppu_addr();
+	fn ppu_data_read(&mut self, palette_val: Option<u8>) -> u8 {
+		const PALETTE_MASK: u8 = mask!(u8, 6, 0, false);
 
-		ret
+		// the buffer is filled by the PPU afterwards
+		self.data_access = Some(DataAccess::Read);
+		match palette_val {
+			// the upper 2 bits of the palette are the ones of the I/O latch
+			Some(val) => {
+				self.io_latch.set(val, PALETTE_MASK);
+				self.io_latch.get()
+			}
+			None => {
+				self.io_latch.set(self.ppudata_buf, 0xFF);
+				self.ppudata_buf
+			}
+		}
 	}
 
-	fn oam_data_read(&self) -> u8 {
+	fn oam_data_read(&mut self) -> u8 {
+		self.io_latch.set(self.oam.data, 0xFF);
 		self.oam.data
 	}
 
+	fn io_latch_read(&mut self) -> u8 {
+		self.io_latch.get()
+	}
+
 	fn oam_addr_write(&mut self, val: u8) {
 		self.oam.addr = val;
 	}
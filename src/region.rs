@@ -0,0 +1,159 @@
+// The regions differ in the timing of the whole system. The PAL NES divides the master clock by 16
+// for the CPU and by 5 for the PPU, so the PPU runs 3.2 dots per CPU cycle, and a frame has 312
+// scanlines with a longer vblank and without the skipped dot on odd frames. The APU has its own
+// period tables. The Dendy (a famiclone) has the CPU clock of PAL but the 3 dots per cycle of NTSC,
+// the NTSC APU and a post-render period of 51 lines before the vblank starts, so games written for
+// NTSC run with the same number of cycles per frame as on the NTSC NES.
+
+use std::time::Duration;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum Region {
+	#[default]
+	Ntsc,
+	Pal,
+	Dendy,
+}
+
+// the country tags in the names of the ROM sets of GoodNES and No-Intro which are PAL only
+const PAL_TAGS: &[&str] = &[
+	"E",
+	"Europe",
+	"PAL",
+	"Australia",
+	"France",
+	"Germany",
+	"Italy",
+	"Netherlands",
+	"Spain",
+	"Sweden",
+	"Scandinavia",
+	"UK",
+];
+
+impl Region {
+	// the CPU/PPU timing in byte 12 of the NES 2.0 header, none for multiple regions
+	pub fn from_ines2_header(val: u8) -> Option<Self> {
+		match val & 0x03 {
+			0 => Some(Self::Ntsc),
+			1 => Some(Self::Pal),
+			3 => Some(Self::Dendy),
+			_ => None,
+		}
+	}
+
+	pub fn from_name(name: &str) -> Option<Self> {
+		match name.to_lowercase().as_str() {
+			"ntsc" => Some(Self::Ntsc),
+			"pal" => Some(Self::Pal),
+			"dendy" => Some(Self::Dendy),
+			_ => None,
+		}
+	}
+
+	// PAL if the file is tagged as a PAL release, e.g. "Elite (Europe).nes" or "Elite (E).nes",
+	// "(USA, Europe)" also runs on NTSC. This replaces a lookup in a ROM database, which doesn't
+	// exist yet, for the iNES headers without the region.
+	pub fn from_file_name(name: &str) -> Option<Self> {
+		let pal = name
+			.split('(')
+			.skip(1)
+			.filter_map(|s| s.split_once(')'))
+			.any(|(tag, _)| tag.split(',').all(|c| PAL_TAGS.contains(&c.trim())));
+
+		if pal {
+			Some(Self::Pal)
+		} else {
+			None
+		}
+	}
+
+	// PPU dots per CPU cycles
+	pub(crate) fn dots_per_cycles(&self) -> (usize, usize) {
+		match self {
+			Self::Ntsc | Self::Dendy => (3, 1),
+			Self::Pal => (16, 5),
+		}
+	}
+
+	pub(crate) fn scanlines(&self) -> usize {
+		match self {
+			Self::Ntsc => 262,
+			Self::Pal | Self::Dendy => 312,
+		}
+	}
+
+	// the scanline on which the vblank flag is set
+	pub(crate) fn vblank_line(&self) -> usize {
+		match self {
+			Self::Ntsc | Self::Pal => 241,
+			Self::Dendy => 291,
+		}
+	}
+
+	// only the NTSC PPU skips the last dot of the pre-render line on odd frames
+	pub(crate) fn skips_dot(&self) -> bool {
+		*self == Self::Ntsc
+	}
+
+	// the PAL PPUs swap the emphasis bits of red and green
+	pub(crate) fn swaps_emphasis(&self) -> bool {
+		*self != Self::Ntsc
+	}
+
+	// the APU uses the period tables of PAL
+	pub(crate) fn pal_apu(&self) -> bool {
+		*self == Self::Pal
+	}
+
+	pub fn frame_time(&self) -> Duration {
+		match self {
+			Self::Ntsc => Duration::new(0, 16_666_667),
+			Self::Pal | Self::Dendy => Duration::new(0, 20_000_000),
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::nes::{LoadOptions, Nes, RunEvent};
+
+	#[test]
+	fn file_names() {
+		assert_eq!(Region::from_file_name("rom/Elite (Europe).nes"), Some(Region::Pal));
+		assert_eq!(Region::from_file_name("Elite (E) [!].nes"), Some(Region::Pal));
+		assert_eq!(Region::from_file_name("Tetris (USA, Europe).nes"), None);
+		assert_eq!(Region::from_file_name("Tetris (U) (Rev 1).nes"), None);
+	}
+
+	#[test]
+	fn frame_cycles() {
+		// CPU cycles of 2 frames, the odd frame of NTSC is shorter while rendering
+		for (region, cycles) in [
+			(Region::Ntsc, 2 * 29781 - 1),
+			(Region::Pal, 2 * 33247 + 1),
+			(Region::Dendy, 2 * 35464),
+		] {
+			let opts = LoadOptions {
+				region: Some(region),
+				..Default::default()
+			};
+			let mut nes = Nes::new("rom/test/nestest.nes", &opts).unwrap();
+			nes.start();
+
+			let frame_end = |nes: &mut Nes| {
+				while nes.run_until(|_| false) != RunEvent::FrameFinished {}
+				nes.cycles()
+			};
+			for _ in 0..4 {
+				frame_end(&mut nes);
+			}
+			let start = frame_end(&mut nes);
+			let end = (0..2).map(|_| frame_end(&mut nes)).last().unwrap();
+			// the frame ends in the middle of an instruction
+			let diff = (end - start) as i64 - cycles as i64;
+			assert!(diff.abs() <= 8, "{:?}: {} cycles", region, end - start);
+		}
+	}
+}
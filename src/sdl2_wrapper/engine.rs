@@ -98,6 +98,7 @@ fn handle_events(
 				Keycode::K => changed(&mut jp[0].a, true),
 				Keycode::Space => changed(&mut jp[0].start, true),
 				Keycode::Return => changed(&mut jp[0].select, true),
+				Keycode::C => changed(&mut jp[0].coin, true),
 				_ => {}
 			},
 			Event::KeyUp {
@@ -112,6 +113,7 @@ fn handle_events(
 				Keycode::K => changed(&mut jp[0].a, false),
 				Keycode::Space => changed(&mut jp[0].start, false),
 				Keycode::Return => changed(&mut jp[0].select, false),
+				Keycode::C => changed(&mut jp[0].coin, false),
 				_ => {}
 			},
 			_ => {}
@@ -154,7 +156,9 @@ fn update_texture(
 }
 */
 
+// the framebuffer is scaled to the screen, e.g. the 602 pixels of the NTSC filter
 pub fn start(
+	fb_width: u32,
 	rx_quit: Receiver<bool>,
 	rx_fb: Receiver<Arc<RwLock<Vec<u8>>>>,
 	rx_tb: Receiver<Arc<RwLock<Vec<u8>>>>,
@@ -179,7 +183,7 @@ pub fn start(
 		let texture_creator = canvas.texture_creator();
 
 		let mut texture_nes = texture_creator
-			.create_texture_streaming(PixelFormatEnum::RGB24, SCREEN_WIDTH, SCREEN_HEIGHT)
+			.create_texture_streaming(PixelFormatEnum::RGB24, fb_width, SCREEN_HEIGHT)
 			.map_err(|e| e.to_string())
 			.unwrap();
 		let mut texture_tiles = texture_creator
@@ -215,9 +219,13 @@ pub fn start(
 			// if we got a new framebuffer, we update the texture
 			if let Ok(fb) = rx_fb.try_recv() {
 				texture_nes
-					.with_lock(None, |buf: &mut [u8], _pitch: usize| {
+					.with_lock(None, |buf: &mut [u8], pitch: usize| {
+						// the lines of the texture may be padded
 						let fb_vec = &*fb.read().unwrap();
-						buf.copy_from_slice(fb_vec.as_slice());
+						let line = 3 * fb_width as usize;
+						for (dst, src) in buf.chunks_mut(pitch).zip(fb_vec.chunks_exact(line)) {
+							dst[..line].copy_from_slice(src);
+						}
 					})
 					.unwrap();
 				canvas
@@ -0,0 +1,287 @@
+// Runs the test ROMs of blargg (e.g. instr_test-v5, ppu_vbl_nmi, mmc3_test or apu_test), which
+// report their result via the PRG RAM: $6000 holds the status, $6001-$6003 the signature DE B0 61
+// and $6004 a NUL-terminated message. The status is $80 while the test is running, $81 if the
+// reset button has to be pressed and the result code when it's finished (0 = passed).
+
+use crate::nes::{LoadOptions, Nes, RomErr};
+use std::fmt;
+
+const STATUS_ADDR: usize = 0x6000;
+const SIGNATURE_ADDR: usize = 0x6001;
+const MESSAGE_ADDR: usize = 0x6004;
+const SIGNATURE: [u8; 3] = [0xDE, 0xB0, 0x61];
+const MAX_MESSAGE_LEN: usize = 0x1000;
+
+const STATUS_RUNNING: u8 = 0x80;
+const STATUS_RESET: u8 = 0x81;
+
+// the reset has to be pressed at least 100ms after it was requested
+const RESET_DELAY_FRAMES: usize = 6;
+
+pub enum TestStatus {
+	Passed,
+	Failed(u8),
+	Timeout,
+	Jammed,
+}
+
+pub struct TestResult {
+	pub status: TestStatus,
+	pub message: String,
+}
+
+impl TestResult {
+	pub fn passed(&self) -> bool {
+		matches!(self.status, TestStatus::Passed)
+	}
+}
+
+impl fmt::Debug for TestStatus {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match &self {
+			Self::Passed => write!(f, "Passed"),
+			Self::Failed(code) => write!(f, "Failed({})", code),
+			Self::Timeout => write!(f, "Timeout"),
+			Self::Jammed => write!(f, "Jammed"),
+		}
+	}
+}
+
+impl fmt::Debug for TestResult {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{:?}: {}", self.status, self.message.trim_end())
+	}
+}
+
+fn signature_valid(nes: &Nes) -> bool {
+	(0..SIGNATURE.len()).all(|i| nes.peek(SIGNATURE_ADDR + i) == SIGNATURE[i])
+}
+
+fn result(nes: &Nes, status: TestStatus) -> TestResult {
+	let mut message = String::new();
+	if signature_valid(nes) {
+		for addr in MESSAGE_ADDR..MESSAGE_ADDR + MAX_MESSAGE_LEN {
+			match nes.peek(addr) {
+				0 => break,
+				c => message.push(c as char),
+			}
+		}
+	}
+
+	TestResult {
+		status,
+		message,
+	}
+}
+
+// runs the ROM until it reports a result or the number of frames elapsed
+pub fn run(rom_file: &str, timeout_frames: usize) -> Result<TestResult, RomErr> {
+	let mut nes = Nes::new(rom_file, &LoadOptions::default())?;
+	nes.start();
+
+	let mut reset_delay: Option<usize> = None;
+	let mut frame = 0;
+	while frame < timeout_frames {
+		if !nes.run_frame() {
+			frame += 1;
+		}
+
+		if nes.cpu_jammed() {
+			return Ok(result(&nes, TestStatus::Jammed));
+		}
+
+		// until the signature is written, $6000 contains garbage
+		if !signature_valid(&nes) {
+			continue;
+		}
+
+		match nes.peek(STATUS_ADDR) {
+			STATUS_RUNNING => (),
+			STATUS_RESET => match reset_delay {
+				None => reset_delay = Some(frame + RESET_DELAY_FRAMES),
+				Some(f) if frame >= f => {
+					reset_delay = None;
+					nes.start();
+				}
+				_ => (),
+			},
+			0 => return Ok(result(&nes, TestStatus::Passed)),
+			code => return Ok(result(&nes, TestStatus::Failed(code))),
+		}
+	}
+
+	Ok(result(&nes, TestStatus::Timeout))
+}
+
+// runs the ROM for the number of frames and returns the RGB framebuffer, for test ROMs which only
+// show their result on the screen and for title screens of games
+pub fn screenshot(rom_file: &str, frames: usize) -> Result<Vec<u8>, RomErr> {
+	let mut nes = Nes::new(rom_file, &LoadOptions::default())?;
+	nes.start();
+
+	let mut frame = 0;
+	while frame < frames {
+		if !nes.run_frame() {
+			frame += 1;
+		}
+	}
+
+	let fb = nes.get_fb();
+	let rgb = fb.read().unwrap().clone();
+	Ok(rgb)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::ppu::ppu::{SCREEN_HEIGHT, SCREEN_WIDTH};
+	use crate::util::{crc32::crc32, png};
+	use std::fs;
+	use std::panic;
+	use std::path::{Path, PathBuf};
+
+	// ROMs of a local directory, e.g. an extracted instr_test-v5 or mmc3_test, can be run by setting
+	// TEST_ROM_DIR, all .nes files in it and its subdirectories are tested
+	const TEST_ROM_DIR: &str = "rom/test/local";
+	const TIMEOUT_FRAMES: usize = 60 * 60;
+
+	// Every line of the manifest holds a ROM, the number of frames and the CRC-32 of the screen
+	// after them. ROMs which aren't part of the repository (e.g. games) are skipped if they're
+	// missing. On a mismatch the actual screen is written to SCREENSHOT_DIR, so it can be reviewed
+	// and the manifest can be updated by running the test with SCREENSHOT_UPDATE set.
+	const SCREENSHOT_MANIFEST: &str = "rom/test/screenshots.txt";
+	const SCREENSHOT_DIR: &str = "target/screenshots";
+
+	fn run_test_rom(rom_file: &str) {
+		let res = run(rom_file, TIMEOUT_FRAMES).expect("unable to load the test ROM");
+		assert!(res.passed(), "{}: {:?}", rom_file, res);
+	}
+
+	fn find_roms(dir: &Path, roms: &mut Vec<PathBuf>) {
+		for entry in fs::read_dir(dir).unwrap() {
+			let path = entry.unwrap().path();
+			if path.is_dir() {
+				find_roms(&path, roms);
+			} else if path.extension().map_or(false, |e| e == "nes") {
+				roms.push(path);
+			}
+		}
+	}
+
+	#[test]
+	fn cpu_interrupts() {
+		run_test_rom("rom/test/cpu_interrupts.nes");
+	}
+
+	#[test]
+	fn cpu_exec_space_apu() {
+		run_test_rom("rom/test/cpu_exec_space_apu.nes");
+	}
+
+	#[test]
+	fn cpu_exec_space_ppuio() {
+		run_test_rom("rom/test/cpu_exec_space_ppuio.nes");
+	}
+
+	#[test]
+	fn cpu_dummy_writes_oam() {
+		run_test_rom("rom/test/cpu_dummy_writes_oam.nes");
+	}
+
+	#[test]
+	fn cpu_dummy_writes_ppumem() {
+		run_test_rom("rom/test/cpu_dummy_writes_ppumem.nes");
+	}
+
+	// cpu_dummy_reads and ppu_read_buffer are CNROM boards without PRG RAM, they can't report at
+	// $6000 and are covered by the screenshots instead
+
+	#[test]
+	fn test_rom_dir() {
+		let dir = std::env::var("TEST_ROM_DIR").unwrap_or(String::from(TEST_ROM_DIR));
+		if !Path::new(&dir).is_dir() {
+			println!("{} not found, skipped", dir);
+			return;
+		}
+
+		let mut roms = Vec::new();
+		find_roms(Path::new(&dir), &mut roms);
+		roms.sort();
+
+		// a panic of the emulator only fails the ROM which caused it
+		let mut failures = Vec::new();
+		for rom in roms.iter() {
+			let file = rom.to_str().unwrap();
+			match panic::catch_unwind(|| run(file, TIMEOUT_FRAMES)) {
+				Ok(Ok(res)) if res.passed() => println!("{}: passed", file),
+				Ok(Ok(res)) => failures.push(format!("{}: {:?}", file, res)),
+				Ok(Err(e)) => failures.push(format!("{}: {:?}", file, e)),
+				Err(_) => failures.push(format!("{}: panicked", file)),
+			}
+		}
+
+		assert!(
+			failures.is_empty(),
+			"{} of {} test ROMs failed:\n{}",
+			failures.len(),
+			roms.len(),
+			failures.join("\n")
+		);
+	}
+
+	#[test]
+	fn screenshots() {
+		let manifest = fs::read_to_string(SCREENSHOT_MANIFEST).expect("manifest not found");
+		let update = std::env::var("SCREENSHOT_UPDATE").is_ok();
+
+		let mut updated = String::new();
+		let mut failures = Vec::new();
+		for line in manifest.lines() {
+			let fields: Vec<&str> = line.split_whitespace().collect();
+			if line.starts_with('#') || fields.len() != 3 {
+				updated.push_str(line);
+				updated.push('\n');
+				continue;
+			}
+
+			let (rom, frames) = (fields[0], fields[1].parse().expect("invalid frame count"));
+			let expected = u32::from_str_radix(fields[2], 16).expect("invalid CRC-32");
+			if !Path::new(rom).exists() {
+				println!("{}: not found, skipped", rom);
+				updated.push_str(line);
+				updated.push('\n');
+				continue;
+			}
+
+			let rgb = match panic::catch_unwind(|| screenshot(rom, frames)) {
+				Ok(Ok(rgb)) => rgb,
+				Ok(Err(e)) => panic!("{}: {:?}", rom, e),
+				Err(_) => panic!("{}: panicked", rom),
+			};
+
+			let actual = crc32(&rgb);
+			updated.push_str(&format!("{} {} {:08X}\n", rom, frames, actual));
+			if actual != expected {
+				let name = Path::new(rom).file_stem().unwrap().to_str().unwrap();
+				let file = Path::new(SCREENSHOT_DIR).join(format!("{}.png", name));
+				fs::create_dir_all(SCREENSHOT_DIR).unwrap();
+				fs::write(&file, png::encode_rgb(SCREEN_WIDTH, SCREEN_HEIGHT, &rgb)).unwrap();
+
+				failures.push(format!(
+					"{}: expected {:08X}, got {:08X}, see {}",
+					rom,
+					expected,
+					actual,
+					file.display()
+				));
+			}
+		}
+
+		if update {
+			fs::write(SCREENSHOT_MANIFEST, updated).unwrap();
+			return;
+		}
+
+		assert!(failures.is_empty(), "screens changed:\n{}", failures.join("\n"));
+	}
+}
@@ -0,0 +1,46 @@
+// CRC-32 (IEEE 802.3, reflected polynomial 0xEDB88320) as it is used by zip, UPS and BPS
+
+const POLYNOMIAL: u32 = 0xEDB8_8320;
+
+const fn create_table() -> [u32; 256] {
+	let mut table = [0u32; 256];
+	let mut i = 0;
+
+	while i < 256 {
+		let mut crc = i as u32;
+		let mut bit = 0;
+
+		while bit < 8 {
+			crc = if (crc & 0x01) > 0 {
+				(crc >> 1) ^ POLYNOMIAL
+			} else {
+				crc >> 1
+			};
+			bit += 1;
+		}
+
+		table[i] = crc;
+		i += 1;
+	}
+
+	table
+}
+
+const TABLE: [u32; 256] = create_table();
+
+pub fn crc32(data: &[u8]) -> u32 {
+	let mut crc = !0u32;
+
+	for b in data.iter() {
+		crc = TABLE[((crc ^ (*b as u32)) & 0xFF) as usize] ^ (crc >> 8);
+	}
+
+	!crc
+}
+
+#[test]
+fn test_crc32_check_value() {
+	// the standard check value of CRC-32 for the ASCII string "123456789"
+	assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+	assert_eq!(crc32(&[]), 0);
+}
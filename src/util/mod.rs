@@ -1,3 +1,5 @@
 pub mod bit;
 pub mod const_assert;
+pub mod crc32;
+pub mod png;
 pub mod register;
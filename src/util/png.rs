@@ -0,0 +1,47 @@
+// Minimal PNG encoder for 8-bit RGB images, e.g. to store screenshots. The image data is stored
+// in a single IDAT chunk without any filtering.
+
+use super::crc32::crc32;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use std::io::Write;
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'];
+const BIT_DEPTH: u8 = 8;
+const COLOR_TYPE_RGB: u8 = 2;
+const FILTER_NONE: u8 = 0;
+
+fn chunk(png: &mut Vec<u8>, name: &[u8; 4], data: &[u8]) {
+	png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+
+	// the CRC covers the name and the data
+	let start = png.len();
+	png.extend_from_slice(name);
+	png.extend_from_slice(data);
+	let crc = crc32(&png[start..]);
+	png.extend_from_slice(&crc.to_be_bytes());
+}
+
+pub fn encode_rgb(width: usize, height: usize, rgb: &[u8]) -> Vec<u8> {
+	assert_eq!(rgb.len(), width * height * 3, "PNG: invalid size of the image data");
+
+	let mut header = Vec::new();
+	header.extend_from_slice(&(width as u32).to_be_bytes());
+	header.extend_from_slice(&(height as u32).to_be_bytes());
+	// bit depth, color type, compression, filter and interlace method
+	header.extend_from_slice(&[BIT_DEPTH, COLOR_TYPE_RGB, 0, 0, 0]);
+
+	// every row starts with its filter type
+	let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+	for row in rgb.chunks(width * 3) {
+		encoder.write_all(&[FILTER_NONE]).unwrap();
+		encoder.write_all(row).unwrap();
+	}
+	let data = encoder.finish().unwrap();
+
+	let mut png = SIGNATURE.to_vec();
+	chunk(&mut png, b"IHDR", &header);
+	chunk(&mut png, b"IDAT", &data);
+	chunk(&mut png, b"IEND", &[]);
+	png
+}